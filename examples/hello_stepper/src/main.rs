@@ -1,6 +1,5 @@
 use nsc_frame::{
-    Arbiter, Decision, Driver, Frame, FrameState, NoopMem, NoopStepper,
-    StepOutcome,
+    Arbiter, ArbiterContext, Decision, Driver, Frame, FrameView, NoopMem, NoopStepper, StepOutcome,
 };
 
 /// Simple arbiter that forces a Yield every 3 steps.
@@ -10,7 +9,7 @@ struct TickArbiter {
 }
 
 impl Arbiter<NoopMem> for TickArbiter {
-    fn decide(&mut self, _frame: &Frame<NoopMem>) -> Decision {
+    fn decide(&mut self, _frame: &FrameView<'_>, _ctx: &ArbiterContext) -> Decision {
         self.ticks += 1;
         if self.ticks % 3 == 0 {
             Decision::Yield
@@ -22,9 +21,9 @@ impl Arbiter<NoopMem> for TickArbiter {
 
 fn main() {
     // Frame with max 8 generated tokens.
-    let mem = NoopMem::default();
+    let mem = NoopMem;
     let frame = Frame::new(mem, 8);
-    let stepper = NoopStepper::default();
+    let stepper = NoopStepper;
     let arbiter = TickArbiter::default();
 
     let mut driver = Driver::with_arbiter(frame, stepper, arbiter);
@@ -45,6 +44,9 @@ fn main() {
             StepOutcome::Yielded => {
                 println!("yielded by arbiter at cursor={}", driver.frame.cursor.position);
             }
+            StepOutcome::Stalled => {
+                println!("stalled at cursor={}", driver.frame.cursor.position);
+            }
             StepOutcome::Finished => {
                 println!("finished: state={:?}", driver.frame.state);
                 break;
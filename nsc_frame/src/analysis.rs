@@ -0,0 +1,111 @@
+//! Per-kind receipt statistics.
+//!
+//! [`ReceiptStats`] gathers receipt values by kind from step results or recorded
+//! traces and summarizes them (count, min, max, mean, percentiles) or buckets them
+//! into histograms. Pure computation; values are kept, so memory grows with the
+//! number of receipts observed.
+
+use std::collections::BTreeMap;
+
+use crate::trace::Trace;
+use crate::StepResult;
+
+/// Summary of one receipt kind's values.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct KindSummary {
+    pub count: u64,
+    pub min: u64,
+    pub max: u64,
+    pub mean: f64,
+    pub p50: u64,
+    pub p90: u64,
+    pub p99: u64,
+}
+
+/// Receipt values grouped by kind.
+#[derive(Debug, Clone, Default)]
+pub struct ReceiptStats {
+    kinds: BTreeMap<String, Vec<u64>>,
+}
+
+impl ReceiptStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_trace(trace: &Trace) -> Self {
+        let mut stats = Self::new();
+        for e in &trace.entries {
+            for (kind, value) in &e.receipts {
+                stats.record(kind, *value);
+            }
+        }
+        stats
+    }
+
+    pub fn record(&mut self, kind: &str, value: u64) {
+        match self.kinds.get_mut(kind) {
+            Some(values) => values.push(value),
+            None => {
+                self.kinds.insert(kind.to_string(), vec![value]);
+            }
+        }
+    }
+
+    pub fn observe(&mut self, r: &StepResult) {
+        for x in &r.receipts {
+            self.record(x.kind, x.value_u64);
+        }
+    }
+
+    /// Kinds seen so far, in lexical order.
+    pub fn kinds(&self) -> impl Iterator<Item = &str> + '_ {
+        self.kinds.keys().map(String::as_str)
+    }
+
+    pub fn summary(&self, kind: &str) -> Option<KindSummary> {
+        let mut values = self.kinds.get(kind)?.clone();
+        values.sort_unstable();
+        let sum: u128 = values.iter().map(|&v| v as u128).sum();
+        Some(KindSummary {
+            count: values.len() as u64,
+            min: values[0],
+            max: values[values.len() - 1],
+            mean: sum as f64 / values.len() as f64,
+            p50: nearest_rank(&values, 50.0),
+            p90: nearest_rank(&values, 90.0),
+            p99: nearest_rank(&values, 99.0),
+        })
+    }
+
+    /// Summaries of every kind, in lexical order.
+    pub fn summaries(&self) -> Vec<(&str, KindSummary)> {
+        self.kinds()
+            .filter_map(|k| Some((k, self.summary(k)?)))
+            .collect()
+    }
+
+    /// Nearest-rank percentile `p` (0–100) of `kind`'s values.
+    pub fn percentile(&self, kind: &str, p: f64) -> Option<u64> {
+        let mut values = self.kinds.get(kind)?.clone();
+        values.sort_unstable();
+        Some(nearest_rank(&values, p))
+    }
+
+    /// Bucket counts of `kind`'s values for ascending `bounds`: bucket `i` holds
+    /// values in `(bounds[i - 1], bounds[i]]`, and one extra bucket holds values
+    /// above the last bound.
+    pub fn histogram(&self, kind: &str, bounds: &[u64]) -> Vec<u64> {
+        let mut counts = vec![0; bounds.len() + 1];
+        for &v in self.kinds.get(kind).into_iter().flatten() {
+            let bucket = bounds.partition_point(|&b| b < v);
+            counts[bucket] += 1;
+        }
+        counts
+    }
+}
+
+fn nearest_rank(sorted: &[u64], p: f64) -> u64 {
+    let rank = (p.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
@@ -0,0 +1,215 @@
+//! Built-in arbiters.
+
+use crate::{Arbiter, ArbiterContext, Decision, FrameState, FrameView, MemoryGauge, Receipt, Tag};
+
+/// Sheds load when memory utilization crosses configured thresholds.
+///
+/// - At or above `yield_permille`, frames tagged `low_priority` yield.
+/// - At or above `refuse_permille`, frames still in prefill are refused (no new
+///   decode work is started) and all other frames yield.
+///
+/// Every decision taken because of pressure leaves a `mem.pressure` receipt carrying
+/// the utilization in permille.
+#[derive(Debug, Clone)]
+pub struct MemoryPressureArbiter {
+    pub gauge: MemoryGauge,
+    pub yield_permille: u64,
+    pub refuse_permille: u64,
+    pub low_priority: Tag,
+    receipts: Vec<Receipt>,
+}
+
+impl MemoryPressureArbiter {
+    pub fn new(gauge: MemoryGauge, yield_permille: u64, refuse_permille: u64) -> Self {
+        Self {
+            gauge,
+            yield_permille,
+            refuse_permille,
+            low_priority: Tag::BATCH,
+            receipts: Vec::new(),
+        }
+    }
+
+    pub fn with_low_priority(mut self, tag: Tag) -> Self {
+        self.low_priority = tag;
+        self
+    }
+}
+
+impl<M> Arbiter<M> for MemoryPressureArbiter {
+    fn decide(&mut self, frame: &FrameView<'_>, _ctx: &ArbiterContext) -> Decision {
+        let util = self.gauge.utilization_permille();
+        let decision = if util >= self.refuse_permille {
+            if frame.state == FrameState::Prefill {
+                Decision::Refuse
+            } else {
+                Decision::Yield
+            }
+        } else if util >= self.yield_permille && frame.has_tag(self.low_priority) {
+            Decision::Yield
+        } else {
+            Decision::Allow
+        };
+
+        if decision != Decision::Allow {
+            self.receipts.push(Receipt::new("mem.pressure", util));
+        }
+        decision
+    }
+
+    fn drain_receipts(&mut self, out: &mut Vec<Receipt>) {
+        out.append(&mut self.receipts);
+    }
+}
+
+/// How a [`QuorumArbiter`] resolves its voters' decisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuorumRule {
+    /// Allow only if every voter allows; otherwise refuse if any refused, else yield.
+    UnanimousAllow,
+    /// The most common decision; ties go to the more restrictive one.
+    Majority,
+    /// Refuse if any voter refuses; otherwise the majority of allow and yield, ties
+    /// yielding.
+    AnyRefuse,
+}
+
+/// Boxed voter as held by [`QuorumArbiter`].
+pub type BoxedArbiter<M> = Box<dyn Arbiter<M> + Send + Sync>;
+
+/// Consults every inner arbiter and resolves their votes by a [`QuorumRule`].
+///
+/// Each vote is recorded as a `quorum.vote` receipt with value
+/// `voter_index * 4 + decision` (0 allow, 1 yield, 2 refuse, 3 stop), followed by
+/// the voters' own receipts in voter order.
+///
+/// The rule counts stop votes as refusals. When the outcome is a refusal and no
+/// voter actually refused, the frame is stopped with the first stop vote's reason
+/// instead; a single refusal keeps it a [`Decision::Refuse`].
+pub struct QuorumArbiter<M> {
+    pub rule: QuorumRule,
+    pub voters: Vec<BoxedArbiter<M>>,
+    receipts: Vec<Receipt>,
+}
+
+impl<M> QuorumArbiter<M> {
+    pub fn new(rule: QuorumRule, voters: Vec<BoxedArbiter<M>>) -> Self {
+        Self {
+            rule,
+            voters,
+            receipts: Vec::new(),
+        }
+    }
+}
+
+impl<M> Arbiter<M> for QuorumArbiter<M> {
+    fn decide(&mut self, frame: &FrameView<'_>, ctx: &ArbiterContext) -> Decision {
+        let mut counts = [0usize; 4];
+        let mut first_stop = None;
+        let mut own = Vec::new();
+        for (i, voter) in self.voters.iter_mut().enumerate() {
+            let d = voter.decide(frame, ctx);
+            voter.drain_receipts(&mut own);
+            counts[d.code() as usize] += 1;
+            if let Decision::Stop(reason) = d {
+                first_stop.get_or_insert(reason);
+            }
+            self.receipts
+                .push(Receipt::new("quorum.vote", i as u64 * 4 + d.code()));
+        }
+        self.receipts.append(&mut own);
+
+        // Stop votes count towards ending the frame; the frame is refused if any voter
+        // refused, and otherwise stopped with the first stop vote's reason.
+        let [allow, yield_, refused, stopped] = counts;
+        let refuse = refused + stopped;
+        let decision = match self.rule {
+            QuorumRule::UnanimousAllow if yield_ == 0 && refuse == 0 => Decision::Allow,
+            QuorumRule::UnanimousAllow if refuse > 0 => Decision::Refuse,
+            QuorumRule::UnanimousAllow => Decision::Yield,
+            QuorumRule::Majority if refuse >= allow && refuse >= yield_ && refuse > 0 => {
+                Decision::Refuse
+            }
+            QuorumRule::Majority if yield_ >= allow && yield_ > 0 => Decision::Yield,
+            QuorumRule::Majority => Decision::Allow,
+            QuorumRule::AnyRefuse if refuse > 0 => Decision::Refuse,
+            QuorumRule::AnyRefuse if yield_ >= allow && yield_ > 0 => Decision::Yield,
+            QuorumRule::AnyRefuse => Decision::Allow,
+        };
+        match (decision, first_stop) {
+            (Decision::Refuse, Some(reason)) if refused == 0 => Decision::Stop(reason),
+            _ => decision,
+        }
+    }
+
+    fn drain_receipts(&mut self, out: &mut Vec<Receipt>) {
+        out.append(&mut self.receipts);
+    }
+}
+
+/// Memoizes an inner arbiter's decision for up to `ttl` consecutive calls, or until
+/// the frame's state or the [`ArbiterContext`] changes.
+///
+/// Cached answers are replayed without consulting the inner arbiter, so it produces
+/// no receipts for them. A `ttl` of 0 or 1 disables caching.
+#[derive(Debug, Clone)]
+pub struct CachedArbiter<A> {
+    pub inner: A,
+    pub ttl: u64,
+    /// Calls answered from the cache.
+    pub hits: u64,
+    /// Calls forwarded to the inner arbiter.
+    pub misses: u64,
+    cached: Option<CachedDecision>,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct CachedDecision {
+    decision: Decision,
+    state: FrameState,
+    generation: u64,
+    uses: u64,
+}
+
+impl<A> CachedArbiter<A> {
+    pub fn new(inner: A, ttl: u64) -> Self {
+        Self {
+            inner,
+            ttl,
+            hits: 0,
+            misses: 0,
+            cached: None,
+        }
+    }
+
+    /// Drop the cached decision; the next call consults the inner arbiter.
+    pub fn invalidate(&mut self) {
+        self.cached = None;
+    }
+}
+
+impl<M, A: Arbiter<M>> Arbiter<M> for CachedArbiter<A> {
+    fn decide(&mut self, frame: &FrameView<'_>, ctx: &ArbiterContext) -> Decision {
+        let generation = ctx.generation();
+        if let Some(c) = &mut self.cached {
+            if c.state == frame.state && c.generation == generation && c.uses < self.ttl {
+                c.uses += 1;
+                self.hits += 1;
+                return c.decision;
+            }
+        }
+        let decision = self.inner.decide(frame, ctx);
+        self.misses += 1;
+        self.cached = Some(CachedDecision {
+            decision,
+            state: frame.state,
+            generation,
+            uses: 1,
+        });
+        decision
+    }
+
+    fn drain_receipts(&mut self, out: &mut Vec<Receipt>) {
+        self.inner.drain_receipts(out);
+    }
+}
@@ -0,0 +1,115 @@
+//! Usage metering folded from step envelopes.
+//!
+//! [`Billing`] reads only the receipt conventions defined by this crate, so it
+//! meters any backend that follows them:
+//!
+//! - committed tokens (the envelope's `tokens_committed`)
+//! - `prefill.tokens` — prompt tokens consumed by the step
+//! - `prefill.reused` — prompt tokens served from a cached prefix instead
+//! - `step.cost` — cost units ([`crate::STEP_COST`]); the driver stamps the default
+//!   unit on backend steps that report none
+//! - `mem.blocks_alloc` / `mem.blocks_free` — turned into block-steps (blocks held,
+//!   summed over steps)
+
+use std::collections::BTreeMap;
+
+use crate::{MemAccounting, StepOutcome, StepResult};
+
+/// Receipt kind for prompt tokens consumed by a prefill step.
+pub const PREFILL_TOKENS: &str = "prefill.tokens";
+
+/// Receipt kind for prompt tokens a frame skipped because the backend already had
+/// them cached (see [`Frame::with_prompt_resumed`](crate::Frame::with_prompt_resumed)).
+pub const PREFILL_REUSED: &str = "prefill.reused";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Invoice {
+    /// Envelopes in which the backend ran: those that advanced or stalled, or
+    /// carried a `step.cost` charge.
+    pub steps: u64,
+    pub tokens_generated: u64,
+    pub prefill_tokens: u64,
+    pub prefill_reused: u64,
+    pub cost_units: u64,
+    pub block_steps: u64,
+}
+
+impl Invoice {
+    pub fn add(&mut self, other: &Invoice) {
+        self.steps = self.steps.saturating_add(other.steps);
+        self.tokens_generated = self.tokens_generated.saturating_add(other.tokens_generated);
+        self.prefill_tokens = self.prefill_tokens.saturating_add(other.prefill_tokens);
+        self.prefill_reused = self.prefill_reused.saturating_add(other.prefill_reused);
+        self.cost_units = self.cost_units.saturating_add(other.cost_units);
+        self.block_steps = self.block_steps.saturating_add(other.block_steps);
+    }
+}
+
+/// Per-frame and per-tenant invoices. Frame ids and tenant names are the caller's.
+#[derive(Debug, Clone, Default)]
+pub struct Billing {
+    frames: BTreeMap<u64, (Invoice, MemAccounting)>,
+    tenants: BTreeMap<String, Invoice>,
+}
+
+impl Billing {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold one envelope of frame `frame_id`, owned by `tenant`.
+    pub fn record(&mut self, frame_id: u64, tenant: &str, r: &StepResult) {
+        let (invoice, mem) = self.frames.entry(frame_id).or_default();
+
+        let cost = r.reported_cost();
+        let ran = matches!(r.outcome, StepOutcome::Advanced | StepOutcome::Stalled);
+        let mut delta = Invoice {
+            steps: (ran || cost.is_some()) as u64,
+            tokens_generated: r.tokens_committed as u64,
+            cost_units: cost.unwrap_or(0),
+            ..Invoice::default()
+        };
+        for receipt in &r.receipts {
+            match receipt.kind {
+                PREFILL_TOKENS => {
+                    delta.prefill_tokens = delta.prefill_tokens.saturating_add(receipt.value_u64)
+                }
+                PREFILL_REUSED => {
+                    delta.prefill_reused = delta.prefill_reused.saturating_add(receipt.value_u64)
+                }
+                _ => {}
+            }
+        }
+        let before = mem.block_steps;
+        mem.observe(r);
+        delta.block_steps = mem.block_steps - before;
+
+        invoice.add(&delta);
+        match self.tenants.get_mut(tenant) {
+            Some(t) => t.add(&delta),
+            None => {
+                self.tenants.insert(tenant.to_string(), delta);
+            }
+        }
+    }
+
+    pub fn frame_invoice(&self, frame_id: u64) -> Option<Invoice> {
+        self.frames.get(&frame_id).map(|(invoice, _)| *invoice)
+    }
+
+    pub fn tenant_invoice(&self, tenant: &str) -> Option<Invoice> {
+        self.tenants.get(tenant).copied()
+    }
+
+    /// Frame invoices in ascending frame id order.
+    pub fn frames(&self) -> impl Iterator<Item = (u64, Invoice)> + '_ {
+        self.frames.iter().map(|(id, (invoice, _))| (*id, *invoice))
+    }
+
+    /// Tenant invoices in ascending name order.
+    pub fn tenants(&self) -> impl Iterator<Item = (&str, Invoice)> + '_ {
+        self.tenants
+            .iter()
+            .map(|(name, invoice)| (name.as_str(), *invoice))
+    }
+}
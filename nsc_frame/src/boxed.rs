@@ -0,0 +1,120 @@
+//! Runtime-selected backends without generic parameters.
+//!
+//! Every extension trait in the crate is dyn-compatible, and a `Box` of any of them
+//! implements the trait itself. [`DynDriver`] fixes the stepper and arbiter to boxed
+//! trait objects, so a plugin host can pick both at runtime and pass drivers around
+//! as one concrete type.
+
+use crate::arbiters::BoxedArbiter;
+use crate::{
+    Arbiter, ArbiterContext, Clock, Decision, Driver, Frame, FrameStepper, FrameView, NoArbiter,
+    Progress, Receipt, ReceiptSink, SinkControl, StepPreview, StepResult, StopCondition,
+    StopReason, TokenHasher, TokenSink,
+};
+
+/// Boxed stepper as held by [`DynDriver`].
+pub type BoxedStepper<M> = Box<dyn FrameStepper<M> + Send + Sync>;
+
+/// A driver whose stepper and arbiter are chosen at runtime.
+pub type DynDriver<M> = Driver<M, BoxedStepper<M>, BoxedArbiter<M>>;
+
+impl<M: 'static> Driver<M, BoxedStepper<M>, BoxedArbiter<M>> {
+    /// A [`DynDriver`] over `stepper` with no arbiter.
+    pub fn boxed(frame: Frame<M>, stepper: BoxedStepper<M>) -> Self {
+        Self::with_arbiter(frame, stepper, Box::new(NoArbiter))
+    }
+
+    /// A [`DynDriver`] over `stepper`, consulting `arbiter` before each step.
+    pub fn boxed_with_arbiter(
+        frame: Frame<M>,
+        stepper: BoxedStepper<M>,
+        arbiter: BoxedArbiter<M>,
+    ) -> Self {
+        Self::with_arbiter(frame, stepper, arbiter)
+    }
+}
+
+impl<M, S: FrameStepper<M> + ?Sized> FrameStepper<M> for Box<S> {
+    fn step(&mut self, frame: &mut Frame<M>) -> Result<StepResult, String> {
+        (**self).step(frame)
+    }
+
+    fn step_with(
+        &mut self,
+        frame: &mut Frame<M>,
+        progress: &mut Progress<'_>,
+    ) -> Result<StepResult, String> {
+        (**self).step_with(frame, progress)
+    }
+
+    fn peek(&mut self, frame: &Frame<M>) -> Option<StepPreview> {
+        (**self).peek(frame)
+    }
+
+    fn export_state(&mut self, frame: &Frame<M>) -> Option<Vec<u8>> {
+        (**self).export_state(frame)
+    }
+
+    fn import_state(&mut self, frame: &mut Frame<M>, state: Vec<u8>) -> Result<(), String> {
+        (**self).import_state(frame, state)
+    }
+}
+
+impl<M, A: Arbiter<M> + ?Sized> Arbiter<M> for Box<A> {
+    fn decide(&mut self, frame: &FrameView<'_>, ctx: &ArbiterContext) -> Decision {
+        (**self).decide(frame, ctx)
+    }
+
+    fn drain_receipts(&mut self, out: &mut Vec<Receipt>) {
+        (**self).drain_receipts(out)
+    }
+}
+
+impl<M, C: StopCondition<M> + ?Sized> StopCondition<M> for Box<C> {
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason> {
+        (**self).check(frame, step)
+    }
+}
+
+impl<T: TokenSink + ?Sized> TokenSink for Box<T> {
+    fn offer(&mut self, token: u32) -> SinkControl {
+        (**self).offer(token)
+    }
+}
+
+impl<T: ReceiptSink + ?Sized> ReceiptSink for Box<T> {
+    fn accept(&mut self, receipts: &[Receipt]) {
+        (**self).accept(receipts)
+    }
+}
+
+impl<H: TokenHasher + ?Sized> TokenHasher for Box<H> {
+    fn update(&mut self, token: u32) {
+        (**self).update(token)
+    }
+
+    fn digest(&self) -> u64 {
+        (**self).digest()
+    }
+}
+
+impl<C: Clock + ?Sized> Clock for Box<C> {
+    fn now_ticks(&self) -> u64 {
+        (**self).now_ticks()
+    }
+}
+
+// Dyn-compatibility of the extension traits is part of the API.
+const _: () = {
+    fn dyn_compatible<M>(
+        _: &dyn FrameStepper<M>,
+        _: &dyn Arbiter<M>,
+        _: &dyn StopCondition<M>,
+        _: &dyn TokenSink,
+        _: &dyn ReceiptSink,
+        _: &dyn TokenHasher,
+        _: &dyn Clock,
+    ) {
+    }
+    let _ = dyn_compatible::<crate::NoopMem>;
+};
@@ -0,0 +1,182 @@
+//! Bounded single-producer / single-consumer channel for emitted tokens.
+//!
+//! The driver side pushes tokens (and finally an end-of-stream marker with the stop
+//! reason); another thread, e.g. an HTTP handler, drains them. Each slot is one
+//! `AtomicU64`, so neither side ever takes a lock.
+
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use crate::{StepOutcome, StepResult, StopReason};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenEvent {
+    Token(u32),
+    End(StopReason),
+}
+
+impl TokenEvent {
+    /// The event a step envelope contributes to the stream, if any.
+    pub fn from_step(r: &StepResult) -> Option<Self> {
+        match (r.outcome, r.emitted_token, r.stop_reason) {
+            (StepOutcome::Finished, _, Some(reason)) => Some(TokenEvent::End(reason)),
+            (_, Some(tok), _) => Some(TokenEvent::Token(tok)),
+            _ => None,
+        }
+    }
+
+    /// The event as stored in one channel slot: bits 56.. tag, bits 32..40 stop
+    /// reason code, bits 0..32 payload.
+    pub fn to_bits(self) -> u64 {
+        match self {
+            TokenEvent::Token(tok) => (1 << 56) | tok as u64,
+            TokenEvent::End(reason) => {
+                let (code, payload) = reason.to_parts();
+                (2 << 56) | ((code as u64) << 32) | payload as u64
+            }
+        }
+    }
+
+    /// Inverse of [`TokenEvent::to_bits`]; `None` for a tag, stop reason code or
+    /// padding no sender writes.
+    pub fn from_bits(v: u64) -> Option<Self> {
+        let (tag, padding, code, payload) =
+            (v >> 56, (v >> 40) & 0xffff, (v >> 32) as u8, v as u32);
+        match (tag, padding, code) {
+            (1, 0, 0) => Some(TokenEvent::Token(payload)),
+            (2, 0, code) => StopReason::from_parts(code, payload).map(TokenEvent::End),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TrySendError {
+    /// The ring is full; the event was not sent.
+    Full(TokenEvent),
+    /// The receiver was dropped.
+    Disconnected(TokenEvent),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TryRecvError {
+    Empty,
+    /// The sender was dropped and every sent event has been received.
+    Disconnected,
+    /// A slot held bits no [`TokenEvent`] encodes to; the slot is consumed.
+    Malformed(u64),
+}
+
+#[derive(Debug)]
+struct Ring {
+    slots: Box<[AtomicU64]>,
+    /// Next index to read. Only the receiver stores it.
+    head: AtomicUsize,
+    /// Next index to write. Only the sender stores it.
+    tail: AtomicUsize,
+    sender_alive: AtomicBool,
+    receiver_alive: AtomicBool,
+}
+
+/// Create a channel holding at most `capacity` undelivered events (minimum 1).
+pub fn token_channel(capacity: usize) -> (TokenSender, TokenReceiver) {
+    let slots = (0..capacity.max(1)).map(|_| AtomicU64::new(0)).collect();
+    let ring = Arc::new(Ring {
+        slots,
+        head: AtomicUsize::new(0),
+        tail: AtomicUsize::new(0),
+        sender_alive: AtomicBool::new(true),
+        receiver_alive: AtomicBool::new(true),
+    });
+    (
+        TokenSender {
+            ring: Arc::clone(&ring),
+        },
+        TokenReceiver { ring },
+    )
+}
+
+/// Producer half. Not cloneable: there is exactly one producer.
+#[derive(Debug)]
+pub struct TokenSender {
+    ring: Arc<Ring>,
+}
+
+impl TokenSender {
+    pub fn try_send(&mut self, event: TokenEvent) -> Result<(), TrySendError> {
+        let ring = &*self.ring;
+        if !ring.receiver_alive.load(Ordering::Acquire) {
+            return Err(TrySendError::Disconnected(event));
+        }
+        let tail = ring.tail.load(Ordering::Relaxed);
+        let head = ring.head.load(Ordering::Acquire);
+        if tail.wrapping_sub(head) == ring.slots.len() {
+            return Err(TrySendError::Full(event));
+        }
+        ring.slots[tail % ring.slots.len()].store(event.to_bits(), Ordering::Relaxed);
+        ring.tail.store(tail.wrapping_add(1), Ordering::Release);
+        Ok(())
+    }
+
+    /// Forward whatever `r` contributes to the stream. Envelopes without a token or
+    /// stop reason are a no-op.
+    pub fn feed(&mut self, r: &StepResult) -> Result<(), TrySendError> {
+        match TokenEvent::from_step(r) {
+            Some(event) => self.try_send(event),
+            None => Ok(()),
+        }
+    }
+
+    pub fn is_full(&self) -> bool {
+        let ring = &*self.ring;
+        let tail = ring.tail.load(Ordering::Relaxed);
+        tail.wrapping_sub(ring.head.load(Ordering::Acquire)) == ring.slots.len()
+    }
+}
+
+impl Drop for TokenSender {
+    fn drop(&mut self) {
+        self.ring.sender_alive.store(false, Ordering::Release);
+    }
+}
+
+/// Consumer half. Not cloneable: there is exactly one consumer.
+#[derive(Debug)]
+pub struct TokenReceiver {
+    ring: Arc<Ring>,
+}
+
+impl TokenReceiver {
+    pub fn try_recv(&mut self) -> Result<TokenEvent, TryRecvError> {
+        let ring = &*self.ring;
+        let head = ring.head.load(Ordering::Relaxed);
+        // Read liveness before tail so a final send is never missed.
+        let alive = ring.sender_alive.load(Ordering::Acquire);
+        if head == ring.tail.load(Ordering::Acquire) {
+            return Err(if alive {
+                TryRecvError::Empty
+            } else {
+                TryRecvError::Disconnected
+            });
+        }
+        let v = ring.slots[head % ring.slots.len()].load(Ordering::Relaxed);
+        ring.head.store(head.wrapping_add(1), Ordering::Release);
+        TokenEvent::from_bits(v).ok_or(TryRecvError::Malformed(v))
+    }
+
+    /// Drain everything currently available into `out`. Returns how many were read.
+    pub fn drain_into(&mut self, out: &mut Vec<TokenEvent>) -> usize {
+        let mut n = 0;
+        while let Ok(event) = self.try_recv() {
+            out.push(event);
+            n += 1;
+        }
+        n
+    }
+}
+
+impl Drop for TokenReceiver {
+    fn drop(&mut self) {
+        self.ring.receiver_alive.store(false, Ordering::Release);
+    }
+}
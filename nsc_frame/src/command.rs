@@ -0,0 +1,138 @@
+//! Commands queued for a driver and applied at the next step boundary.
+//!
+//! External controllers never touch a frame mid-step: they enqueue a
+//! [`DriverCommand`], and [`Driver::step`] drains the queue before doing anything
+//! else. Each applied command leaves a `command.*` receipt on that step's envelope.
+
+use std::collections::VecDeque;
+
+use crate::{
+    Arbiter, Driver, FrameLimits, FrameState, FrameStepper, PauseReason, Receipt, StopReason,
+};
+
+/// Receipt kind for a command the driver refused to apply; the value says which
+/// (see [`ADJUST_LIMITS_UNBOUNDED`]).
+pub const COMMAND_REJECTED: &str = "command.rejected";
+
+/// [`COMMAND_REJECTED`] value: [`DriverCommand::AdjustLimits`] would have left the
+/// frame unbounded.
+pub const ADJUST_LIMITS_UNBOUNDED: u64 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DriverCommand {
+    /// Pause a running frame with [`PauseReason::Requested`].
+    Pause,
+    /// Resume a frame paused by [`DriverCommand::Pause`]. Frames paused for other
+    /// reasons stay paused.
+    Resume,
+    Cancel(CancelMode),
+    /// Finish a live frame with its output intact and this stop reason, e.g. a
+    /// [`StopReason::Custom`] code for "client disconnected".
+    Abort(StopReason),
+    /// Replace the frame's limits. Rejected, with a [`COMMAND_REJECTED`] receipt, when
+    /// the new limits would leave the frame no way to stop (see [`Driver::validate`]).
+    AdjustLimits(FrameLimits),
+    /// Store a snapshot of the frame in [`Driver::last_checkpoint`].
+    Checkpoint,
+    /// Add token credits for flow control (see [`Driver::with_flow_control`]).
+    /// Ignored when flow control is off.
+    GrantCredits(u64),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CancelMode {
+    /// Abandon the frame: state becomes `Cancelled`.
+    Immediate,
+    /// Finish the frame with its output intact: state becomes `Finished` with
+    /// [`StopReason::Cancelled`].
+    Graceful,
+}
+
+/// Pending commands, oldest first.
+pub type CommandQueue = VecDeque<DriverCommand>;
+
+impl<M, S, A> Driver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Queue `command` for the next step boundary.
+    pub fn enqueue(&mut self, command: DriverCommand) {
+        self.commands.push_back(command);
+    }
+
+    /// Finish the frame now with `reason`, as [`DriverCommand::Abort`] without waiting
+    /// for the queue. The next step returns the finished envelope.
+    pub fn abort(&mut self, reason: StopReason) {
+        self.apply_command(DriverCommand::Abort(reason));
+    }
+
+    /// Apply every queued command in order. Called at the top of [`Driver::step`].
+    pub(crate) fn drain_commands(&mut self) {
+        while let Some(command) = self.commands.pop_front() {
+            self.apply_command(command);
+        }
+    }
+
+    fn apply_command(&mut self, command: DriverCommand) {
+        let frame = &mut self.frame;
+        let receipt = match command {
+            DriverCommand::Pause => {
+                let paused = frame.pause(PauseReason::Requested);
+                Receipt::new("command.pause", paused as u64)
+            }
+            DriverCommand::Resume => {
+                let resumed =
+                    frame.state == FrameState::Paused(PauseReason::Requested) && frame.resume();
+                Receipt::new("command.resume", resumed as u64)
+            }
+            DriverCommand::Cancel(mode) => {
+                let live = !matches!(frame.state, FrameState::Finished | FrameState::Cancelled);
+                if live {
+                    frame.paused_from = None;
+                    frame.stop_reason = Some(StopReason::Cancelled);
+                    match mode {
+                        CancelMode::Immediate => frame.cancel(),
+                        CancelMode::Graceful => frame.state = FrameState::Finished,
+                    }
+                }
+                Receipt::new("command.cancel", live as u64)
+            }
+            DriverCommand::Abort(reason) => {
+                let live = !matches!(frame.state, FrameState::Finished | FrameState::Cancelled);
+                if live {
+                    frame.paused_from = None;
+                    frame.stop_reason = Some(reason);
+                    frame.state = FrameState::Finished;
+                }
+                Receipt::new("command.abort", live as u64)
+            }
+            DriverCommand::AdjustLimits(limits) => {
+                if !self.can_stop(&limits) {
+                    Receipt::new(COMMAND_REJECTED, ADJUST_LIMITS_UNBOUNDED)
+                } else {
+                    let max = limits.max_new_tokens.map_or(u64::MAX, |n| n as u64);
+                    self.frame.limits = limits;
+                    Receipt::new("command.adjust_limits", max)
+                }
+            }
+            DriverCommand::Checkpoint => {
+                let snapshot = self.snapshot();
+                let steps = snapshot.steps_taken;
+                self.last_checkpoint = Some(snapshot);
+                Receipt::new("command.checkpoint", steps)
+            }
+            DriverCommand::GrantCredits(n) => {
+                let granted = match &mut self.flow_credits {
+                    Some(c) => {
+                        *c = c.saturating_add(n);
+                        n
+                    }
+                    None => 0,
+                };
+                Receipt::new("command.grant_credits", granted)
+            }
+        };
+        self.pending_receipts.push(receipt);
+    }
+}
@@ -0,0 +1,196 @@
+//! Compact, lossless encoding of recorded traces.
+//!
+//! Long runs repeat themselves: the same outcome and receipt kinds step after step,
+//! positions moving by one, step indices counting up. [`CompactTrace`] stores
+//! consecutive entries that share a shape (state, outcome, stop reason, whether a
+//! token was emitted, receipt kinds) as one run, and within a run only deltas of
+//! step index and position plus varint prompt index, counters, token ids (the
+//! reported one and those committed to the output) and receipt values. Receipt kinds are interned once per trace.
+
+use crate::trace::{Trace, TraceEntry};
+use crate::wire::{DecodeError, Reader, Writer};
+use crate::{FrameState, StepOutcome, StopReason};
+
+/// A [`Trace`] in compact form; [`CompactTrace::expand`] restores it exactly.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CompactTrace {
+    bytes: Vec<u8>,
+}
+
+/// What consecutive entries of one run have in common.
+#[derive(PartialEq)]
+struct Shape {
+    state_after: FrameState,
+    outcome: StepOutcome,
+    stop_reason: Option<StopReason>,
+    has_token: bool,
+    kinds: Vec<u64>,
+}
+
+impl Trace {
+    pub fn compact(&self) -> CompactTrace {
+        let mut kinds: Vec<&str> = Vec::new();
+        let mut shapes = Vec::with_capacity(self.entries.len());
+        for e in &self.entries {
+            let mut shape_kinds = Vec::with_capacity(e.receipts.len());
+            for (kind, _) in &e.receipts {
+                let i = match kinds.iter().position(|k| k == kind) {
+                    Some(i) => i,
+                    None => {
+                        kinds.push(kind);
+                        kinds.len() - 1
+                    }
+                };
+                shape_kinds.push(i as u64);
+            }
+            shapes.push(Shape {
+                state_after: e.state_after,
+                outcome: e.outcome,
+                stop_reason: e.stop_reason,
+                has_token: e.emitted_token.is_some(),
+                kinds: shape_kinds,
+            });
+        }
+
+        let mut w = Writer::default();
+        w.varint(kinds.len() as u64);
+        for k in &kinds {
+            w.str(k);
+        }
+        w.varint(self.entries.len() as u64);
+
+        let (mut next_step, mut prev_pos) = (0u64, 0u32);
+        let mut i = 0;
+        while i < shapes.len() {
+            let run = shapes[i..].iter().take_while(|s| **s == shapes[i]).count();
+            let shape = &shapes[i];
+            w.varint(run as u64);
+            w.state(shape.state_after);
+            w.outcome(shape.outcome);
+            w.opt_stop_reason(shape.stop_reason);
+            w.bool(shape.has_token);
+            w.varint(shape.kinds.len() as u64);
+            for k in &shape.kinds {
+                w.varint(*k);
+            }
+            for e in &self.entries[i..i + run] {
+                // Two's complement difference: exact for any gap that fits in an i64.
+                w.zigzag(e.step_index.wrapping_sub(next_step) as i64);
+                w.zigzag(i64::from(e.position) - i64::from(prev_pos));
+                w.varint(e.prompt_index as u64);
+                w.varint(e.steps_taken);
+                w.varint(e.cost_spent);
+                if let Some(tok) = e.emitted_token {
+                    w.varint(tok as u64);
+                }
+                w.varint(e.committed.len() as u64);
+                for &tok in &e.committed {
+                    w.varint(tok as u64);
+                }
+                for (_, value) in &e.receipts {
+                    w.varint(*value);
+                }
+                next_step = e.step_index.wrapping_add(1);
+                prev_pos = e.position;
+            }
+            i += run;
+        }
+        CompactTrace { bytes: w.buf }
+    }
+}
+
+impl CompactTrace {
+    /// Wrap bytes produced by [`CompactTrace::as_bytes`]; checked on expansion.
+    pub fn from_bytes(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.bytes
+    }
+
+    /// Decode back into the trace this was built from.
+    pub fn expand(&self) -> Result<Trace, DecodeError> {
+        let mut r = Reader::new(&self.bytes);
+        let n_kinds = r.varint()?;
+        let kinds = (0..n_kinds)
+            .map(|_| r.string())
+            .collect::<Result<Vec<_>, _>>()?;
+        let total = r.varint()?;
+
+        let mut trace = Trace::new();
+        let (mut next_step, mut prev_pos) = (Some(0u64), 0u32);
+        while (trace.len() as u64) < total {
+            let run = r.varint()?;
+            if run == 0 || run > total - trace.len() as u64 {
+                return Err(DecodeError::Malformed("trace run"));
+            }
+            let state_after = r.state()?;
+            let outcome = r.outcome()?;
+            let stop_reason = r.opt_stop_reason()?;
+            let has_token = r.bool()?;
+            let n = r.varint()?;
+            let shape_kinds = (0..n)
+                .map(|_| {
+                    let i = r.varint()?;
+                    kinds
+                        .get(i as usize)
+                        .ok_or(DecodeError::Malformed("receipt kind"))
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            for _ in 0..run {
+                let step_index = r.zigzag()?;
+                let step_index = next_step
+                    .and_then(|next| next.checked_add_signed(step_index))
+                    .ok_or(DecodeError::Malformed("step index"))?;
+                let position = i64::from(prev_pos)
+                    .checked_add(r.zigzag()?)
+                    .and_then(|p| u32::try_from(p).ok())
+                    .ok_or(DecodeError::Malformed("position"))?;
+                let prompt_index = usize::try_from(r.varint()?)
+                    .map_err(|_| DecodeError::Malformed("prompt index"))?;
+                let steps_taken = r.varint()?;
+                let cost_spent = r.varint()?;
+                let emitted_token = match has_token {
+                    true => Some(narrow(r.varint()?, "token")?),
+                    false => None,
+                };
+                let n_committed = r.varint()?;
+                let committed = (0..n_committed)
+                    .map(|_| narrow(r.varint()?, "token"))
+                    .collect::<Result<Vec<_>, _>>()?;
+                let receipts = shape_kinds
+                    .iter()
+                    .map(|k| Ok(((*k).clone(), r.varint()?)))
+                    .collect::<Result<Vec<_>, DecodeError>>()?;
+                trace.push(TraceEntry {
+                    step_index,
+                    position,
+                    prompt_index,
+                    steps_taken,
+                    cost_spent,
+                    state_after,
+                    outcome,
+                    emitted_token,
+                    committed,
+                    stop_reason,
+                    receipts,
+                });
+                next_step = step_index.checked_add(1);
+                prev_pos = position;
+            }
+        }
+        if !r.is_empty() {
+            return Err(DecodeError::Malformed("trailing bytes"));
+        }
+        Ok(trace)
+    }
+}
+
+fn narrow(v: u64, what: &'static str) -> Result<u32, DecodeError> {
+    u32::try_from(v).map_err(|_| DecodeError::Malformed(what))
+}
@@ -0,0 +1,80 @@
+//! Backend-reported compute estimates.
+//!
+//! Receipt kinds (see the constructors on [`crate::Receipt`]):
+//!
+//! - `compute.flops_est` — estimated floating-point operations for the step
+//! - `compute.energy_mj_est` — estimated energy for the step, in millijoules
+//!
+//! Both are per-step deltas and are estimates; [`ComputeLedger`] only sums them.
+
+use std::collections::BTreeMap;
+
+use crate::StepResult;
+
+pub const COMPUTE_FLOPS_EST: &str = "compute.flops_est";
+pub const COMPUTE_ENERGY_MJ_EST: &str = "compute.energy_mj_est";
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ComputeTotals {
+    pub flops_est: u64,
+    pub energy_mj_est: u64,
+    /// Envelopes that carried at least one compute receipt.
+    pub reporting_steps: u64,
+}
+
+impl ComputeTotals {
+    /// Add the compute receipts of one envelope.
+    pub fn observe(&mut self, r: &StepResult) {
+        let mut reported = false;
+        for receipt in &r.receipts {
+            match receipt.kind {
+                COMPUTE_FLOPS_EST => {
+                    self.flops_est = self.flops_est.saturating_add(receipt.value_u64)
+                }
+                COMPUTE_ENERGY_MJ_EST => {
+                    self.energy_mj_est = self.energy_mj_est.saturating_add(receipt.value_u64)
+                }
+                _ => continue,
+            }
+            reported = true;
+        }
+        self.reporting_steps += reported as u64;
+    }
+}
+
+/// Compute totals per caller-assigned frame id.
+#[derive(Debug, Clone, Default)]
+pub struct ComputeLedger {
+    frames: BTreeMap<u64, ComputeTotals>,
+}
+
+impl ComputeLedger {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn record(&mut self, frame_id: u64, r: &StepResult) {
+        self.frames.entry(frame_id).or_default().observe(r);
+    }
+
+    pub fn frame(&self, frame_id: u64) -> Option<ComputeTotals> {
+        self.frames.get(&frame_id).copied()
+    }
+
+    /// Per-frame totals in ascending frame id order.
+    pub fn frames(&self) -> impl Iterator<Item = (u64, ComputeTotals)> + '_ {
+        self.frames.iter().map(|(id, t)| (*id, *t))
+    }
+
+    /// Totals across every frame.
+    pub fn total(&self) -> ComputeTotals {
+        self.frames
+            .values()
+            .fold(ComputeTotals::default(), |mut acc, t| {
+                acc.flops_est = acc.flops_est.saturating_add(t.flops_est);
+                acc.energy_mj_est = acc.energy_mj_est.saturating_add(t.energy_mj_est);
+                acc.reporting_steps += t.reporting_steps;
+                acc
+            })
+    }
+}
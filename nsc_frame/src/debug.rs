@@ -0,0 +1,84 @@
+//! Stepping a driver backwards for debugging.
+//!
+//! [`DebugDriver`] records every backend step with periodic keyframes and can undo
+//! them one at a time: [`DebugDriver::back`] rebuilds the frame as it was before the
+//! last backend step (see [`Trace::seek`]) and forgets that step, so the next
+//! [`DebugDriver::step`] runs it again.
+//!
+//! Only the frame is rewound. The backend's memory and private state, and driver
+//! bookkeeping such as ticks, timing and token digests, keep their current values;
+//! rewinding is meant for deterministic backends under investigation.
+
+use crate::trace::{RecordingStepper, Trace};
+use crate::{Arbiter, Driver, Frame, FrameStepper, NoArbiter, StepResult};
+
+/// Keyframe interval used by [`DebugDriver::new`].
+pub const DEBUG_KEYFRAME_EVERY: u64 = 16;
+
+/// A driver whose backend steps can be walked forwards and backwards.
+pub struct DebugDriver<M, S, A = NoArbiter>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    pub driver: Driver<M, RecordingStepper<S>, A>,
+}
+
+impl<M, S> DebugDriver<M, S, NoArbiter>
+where
+    S: FrameStepper<M>,
+{
+    pub fn new(frame: Frame<M>, stepper: S) -> Self {
+        Self::with_arbiter(frame, stepper, NoArbiter)
+    }
+}
+
+impl<M, S, A> DebugDriver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    pub fn with_arbiter(frame: Frame<M>, stepper: S, arbiter: A) -> Self {
+        let stepper = RecordingStepper::new(stepper).with_keyframes(DEBUG_KEYFRAME_EVERY);
+        Self {
+            driver: Driver::with_arbiter(frame, stepper, arbiter),
+        }
+    }
+
+    pub fn step(&mut self) -> Result<StepResult, String> {
+        self.driver.step()
+    }
+
+    /// Backend steps currently recorded, i.e. how far [`DebugDriver::back`] can go.
+    pub fn depth(&self) -> usize {
+        self.driver.stepper.trace.len()
+    }
+
+    pub fn trace(&self) -> &Trace {
+        &self.driver.stepper.trace
+    }
+
+    /// Undo the last backend step. Driver-only steps (yields, law envelopes) leave
+    /// nothing to undo and are skipped over.
+    pub fn back(&mut self) -> Result<(), String> {
+        let rec = &mut self.driver.stepper;
+        let n = rec
+            .trace
+            .len()
+            .checked_sub(1)
+            .ok_or_else(|| "back: no recorded step to undo".to_string())?;
+        let snapshot = rec.trace.seek(&rec.keyframes, n)?;
+        // Time does not rewind: the deadline keeps counting from the first step.
+        let frame = &mut self.driver.frame;
+        let (started_at, carried_ticks) = (frame.started_at, frame.carried_ticks);
+        snapshot.restore_into(frame);
+        frame.started_at = started_at;
+        frame.carried_ticks = carried_ticks;
+        rec.trace.entries.truncate(n);
+        rec.keyframes.retain(|k| k.step_index as usize <= n);
+        if let Some(cond) = &mut self.driver.stop_condition {
+            cond.rewind(&self.driver.frame);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,144 @@
+//! Runtime backend selection with static dispatch.
+//!
+//! [`EitherStepper`] covers the two-backend case; [`stepper_enum!`](crate::stepper_enum)
+//! declares an enum over any number of known steppers. Both dispatch with a `match`,
+//! with no boxing; see [`boxed`](crate::boxed) for open-ended plugin sets.
+
+use crate::{Frame, FrameStepper, Progress, StepPreview, StepResult};
+
+/// One of two steppers, chosen at construction.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EitherStepper<L, R> {
+    Left(L),
+    Right(R),
+}
+
+impl<M, L, R> FrameStepper<M> for EitherStepper<L, R>
+where
+    L: FrameStepper<M>,
+    R: FrameStepper<M>,
+{
+    fn step(&mut self, frame: &mut Frame<M>) -> Result<StepResult, String> {
+        match self {
+            EitherStepper::Left(s) => s.step(frame),
+            EitherStepper::Right(s) => s.step(frame),
+        }
+    }
+
+    fn step_with(
+        &mut self,
+        frame: &mut Frame<M>,
+        progress: &mut Progress<'_>,
+    ) -> Result<StepResult, String> {
+        match self {
+            EitherStepper::Left(s) => s.step_with(frame, progress),
+            EitherStepper::Right(s) => s.step_with(frame, progress),
+        }
+    }
+
+    fn peek(&mut self, frame: &Frame<M>) -> Option<StepPreview> {
+        match self {
+            EitherStepper::Left(s) => s.peek(frame),
+            EitherStepper::Right(s) => s.peek(frame),
+        }
+    }
+
+    fn export_state(&mut self, frame: &Frame<M>) -> Option<Vec<u8>> {
+        match self {
+            EitherStepper::Left(s) => s.export_state(frame),
+            EitherStepper::Right(s) => s.export_state(frame),
+        }
+    }
+
+    fn import_state(&mut self, frame: &mut Frame<M>, state: Vec<u8>) -> Result<(), String> {
+        match self {
+            EitherStepper::Left(s) => s.import_state(frame, state),
+            EitherStepper::Right(s) => s.import_state(frame, state),
+        }
+    }
+}
+
+/// Declare an enum over known steppers that implements [`FrameStepper`] for one
+/// memory type by dispatching to the active variant.
+///
+/// ```
+/// use nsc_frame::{stepper_enum, Driver, Frame, NoopMem, NoopStepper};
+///
+/// stepper_enum! {
+///     #[derive(Debug)]
+///     pub enum Backend: FrameStepper<NoopMem> {
+///         Primary(NoopStepper),
+///         Fallback(NoopStepper),
+///     }
+/// }
+///
+/// let mut driver = Driver::new(Frame::new(NoopMem, 4), Backend::Fallback(NoopStepper));
+/// driver.step().unwrap();
+/// ```
+#[macro_export]
+macro_rules! stepper_enum {
+    (
+        $(#[$meta:meta])*
+        $vis:vis enum $name:ident : FrameStepper<$mem:ty> {
+            $($variant:ident($stepper:ty)),+ $(,)?
+        }
+    ) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant($stepper)),+
+        }
+
+        impl $crate::FrameStepper<$mem> for $name {
+            fn step(
+                &mut self,
+                frame: &mut $crate::Frame<$mem>,
+            ) -> ::std::result::Result<$crate::StepResult, ::std::string::String> {
+                match self {
+                    $($name::$variant(s) => $crate::FrameStepper::<$mem>::step(s, frame)),+
+                }
+            }
+
+            fn step_with(
+                &mut self,
+                frame: &mut $crate::Frame<$mem>,
+                progress: &mut $crate::Progress<'_>,
+            ) -> ::std::result::Result<$crate::StepResult, ::std::string::String> {
+                match self {
+                    $($name::$variant(s) => {
+                        $crate::FrameStepper::<$mem>::step_with(s, frame, progress)
+                    }),+
+                }
+            }
+
+            fn peek(
+                &mut self,
+                frame: &$crate::Frame<$mem>,
+            ) -> ::std::option::Option<$crate::StepPreview> {
+                match self {
+                    $($name::$variant(s) => $crate::FrameStepper::<$mem>::peek(s, frame)),+
+                }
+            }
+
+            fn export_state(
+                &mut self,
+                frame: &$crate::Frame<$mem>,
+            ) -> ::std::option::Option<::std::vec::Vec<u8>> {
+                match self {
+                    $($name::$variant(s) => $crate::FrameStepper::<$mem>::export_state(s, frame)),+
+                }
+            }
+
+            fn import_state(
+                &mut self,
+                frame: &mut $crate::Frame<$mem>,
+                state: ::std::vec::Vec<u8>,
+            ) -> ::std::result::Result<(), ::std::string::String> {
+                match self {
+                    $($name::$variant(s) => {
+                        $crate::FrameStepper::<$mem>::import_state(s, frame, state)
+                    }),+
+                }
+            }
+        }
+    };
+}
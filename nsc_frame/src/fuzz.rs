@@ -0,0 +1,209 @@
+//! Differential fuzzing of two stepper implementations (feature `fuzz`).
+//!
+//! [`differential`] generates frames from a seed, runs each one under two steppers
+//! and compares their traces step by step. On divergence the case is shrunk while it
+//! still diverges and returned as a [`Divergence`] that prints everything needed to
+//! reproduce it.
+
+use std::fmt;
+
+use crate::rng::SplitMix64;
+use crate::trace::{RecordingStepper, Trace, TraceEntry};
+use crate::{Driver, Frame, FrameLimits, FrameStepper, StepOutcome};
+
+/// Bounds for generated cases.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FuzzConfig {
+    pub max_prompt_len: usize,
+    pub max_new_tokens: usize,
+    pub vocab_size: u32,
+    /// Steps per run before giving up on a frame that never finishes.
+    pub max_steps: u64,
+}
+
+impl Default for FuzzConfig {
+    fn default() -> Self {
+        Self {
+            max_prompt_len: 64,
+            max_new_tokens: 64,
+            vocab_size: 32_000,
+            max_steps: 1_024,
+        }
+    }
+}
+
+/// One generated frame. Generated and shrunk cases always pass
+/// [`FrameLimits::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FuzzCase {
+    pub seed: u64,
+    pub limits: FrameLimits,
+    pub prompt_token_ids: Vec<u32>,
+}
+
+impl FuzzCase {
+    pub fn generate(seed: u64, config: &FuzzConfig) -> Self {
+        let mut rng = SplitMix64::new(seed);
+        let prompt_len = rng.below(config.max_prompt_len as u64 + 1) as usize;
+        let prompt_token_ids = (0..prompt_len)
+            .map(|_| rng.below(config.vocab_size.max(1) as u64) as u32)
+            .collect();
+        let max_new_tokens = 1 + rng.below(config.max_new_tokens.max(1) as u64) as usize;
+        let mut limits = FrameLimits::new(max_new_tokens);
+        if rng.below(4) == 0 {
+            limits.max_total_tokens =
+                Some(prompt_len + 1 + rng.below(max_new_tokens as u64) as usize);
+        }
+        if rng.below(4) == 0 {
+            limits.max_cost = Some(1 + rng.below(config.max_steps.max(1)));
+        }
+        Self {
+            seed,
+            limits,
+            prompt_token_ids,
+        }
+    }
+
+    fn frame<M>(&self, mem: M) -> Result<Frame<M>, String> {
+        Frame::try_with_prompt(mem, self.limits.clone(), self.prompt_token_ids.clone())
+            .map_err(|e| format!("fuzz case {}: {}", self.seed, e))
+    }
+
+    /// Smaller variants of this case, most aggressive first.
+    fn shrink(&self) -> Vec<FuzzCase> {
+        let mut out = Vec::new();
+        let len = self.prompt_token_ids.len();
+        for keep in [0, len / 2, len.saturating_sub(1)] {
+            if keep < len {
+                let mut c = self.clone();
+                c.prompt_token_ids.truncate(keep);
+                out.push(c);
+            }
+        }
+        let max = self.limits.max_new_tokens.unwrap_or(0);
+        for keep in [1, max / 2, max.saturating_sub(1)] {
+            if keep >= 1 && keep < max {
+                let mut c = self.clone();
+                c.limits.max_new_tokens = Some(keep);
+                out.push(c);
+            }
+        }
+        if self.limits.max_total_tokens.is_some() || self.limits.max_cost.is_some() {
+            let mut c = self.clone();
+            c.limits.max_total_tokens = None;
+            c.limits.max_cost = None;
+            out.push(c);
+        }
+        out.retain(|c| c.limits.validate(&c.prompt_token_ids).is_ok());
+        out
+    }
+}
+
+/// How the two steppers disagreed on a (shrunk) case.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Divergence {
+    pub case: FuzzCase,
+    pub step_index: u64,
+    /// `Err` carries the step error; `None` means that side had already finished.
+    pub left: Option<Result<TraceEntry, String>>,
+    pub right: Option<Result<TraceEntry, String>>,
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "steppers diverged at step {}", self.step_index)?;
+        writeln!(f, "  seed:   {}", self.case.seed)?;
+        writeln!(f, "  limits: {:?}", self.case.limits)?;
+        writeln!(f, "  prompt: {:?}", self.case.prompt_token_ids)?;
+        writeln!(f, "  left:   {:?}", self.left)?;
+        write!(f, "  right:  {:?}", self.right)
+    }
+}
+
+/// Run `seeds` generated cases through both steppers; `Ok` holds the number of cases
+/// that matched.
+///
+/// `mem`, `left` and `right` build fresh values for every run, so steppers may keep
+/// per-frame state.
+pub fn differential<M, L, R>(
+    seeds: impl IntoIterator<Item = u64>,
+    config: &FuzzConfig,
+    mut mem: impl FnMut() -> M,
+    mut left: impl FnMut() -> L,
+    mut right: impl FnMut() -> R,
+) -> Result<u64, Box<Divergence>>
+where
+    L: FrameStepper<M>,
+    R: FrameStepper<M>,
+{
+    let mut cases = 0;
+    for seed in seeds {
+        let case = FuzzCase::generate(seed, config);
+        let mut compare = |case: &FuzzCase| {
+            let l = run_case(case, mem(), left(), config.max_steps);
+            let r = run_case(case, mem(), right(), config.max_steps);
+            first_divergence(case, &l, &r)
+        };
+        if let Some(mut found) = compare(&case) {
+            // Greedy shrink: take the first smaller case that still diverges.
+            'shrink: loop {
+                for smaller in found.case.shrink() {
+                    if let Some(d) = compare(&smaller) {
+                        found = d;
+                        continue 'shrink;
+                    }
+                }
+                return Err(Box::new(found));
+            }
+        }
+        cases += 1;
+    }
+    Ok(cases)
+}
+
+fn run_case<M, S: FrameStepper<M>>(
+    case: &FuzzCase,
+    mem: M,
+    stepper: S,
+    max_steps: u64,
+) -> (Trace, Option<String>) {
+    let frame = match case.frame(mem) {
+        Ok(frame) => frame,
+        Err(e) => return (Trace::new(), Some(e)),
+    };
+    let mut driver = Driver::new(frame, RecordingStepper::new(stepper));
+    let mut error = None;
+    for _ in 0..max_steps {
+        match driver.step() {
+            Ok(r) if r.outcome == StepOutcome::Finished => break,
+            Ok(_) => {}
+            Err(e) => {
+                error = Some(e);
+                break;
+            }
+        }
+    }
+    (driver.stepper.trace, error)
+}
+
+fn first_divergence(
+    case: &FuzzCase,
+    left: &(Trace, Option<String>),
+    right: &(Trace, Option<String>),
+) -> Option<Divergence> {
+    let at = |(trace, err): &(Trace, Option<String>), i: usize| match trace.get(i) {
+        Some(e) => Some(Ok(e.clone())),
+        None if i == trace.len() => err.clone().map(Err),
+        None => None,
+    };
+    let steps = left.0.len().max(right.0.len()) + 1;
+    (0..steps).find_map(|i| {
+        let (l, r) = (at(left, i), at(right, i));
+        (l != r).then(|| Divergence {
+            case: case.clone(),
+            step_index: i as u64,
+            left: l,
+            right: r,
+        })
+    })
+}
@@ -0,0 +1,613 @@
+//! Sibling frames that explicitly share one memory object.
+//!
+//! The shared object lives behind a handle `H` chosen by the caller (e.g.
+//! `Rc<RefCell<Kv>>` or `Arc<Mutex<Kv>>`); each member frame's `mem` is a clone of
+//! that handle. The sharing is therefore visible in the types instead of hidden
+//! inside a backend.
+
+use std::cmp::Reverse;
+use std::fmt;
+
+use crate::schedulers::RoundRobin;
+use crate::{
+    Arbiter, Driver, Frame, FrameProgress, FrameState, FrameStepper, NoArbiter, Receipt,
+    StepOutcome, StepResult, StopReason, Tag,
+};
+
+/// Receipt on the finishing envelope of an expired member; value is its age in rounds.
+pub const GROUP_EXPIRED: &str = "group.expired";
+
+/// Receipt on the envelope of a member stepped ahead of its admission slot by
+/// [`FrameGroup::with_finish_boost`]; value is its remaining token budget.
+pub const GROUP_BOOST: &str = "group.boost";
+
+/// Receipt on the first envelope of a member after it sat out rounds under
+/// [`FrameGroup::with_yield_backoff`]; value is the rounds it skipped.
+pub const GROUP_BACKOFF: &str = "group.backoff";
+
+/// Receipt on the envelope of a member stepped because it went
+/// [`FrameGroup::with_starvation_limit`] rounds without a step; value is the rounds it
+/// went without.
+pub const GROUP_STARVED: &str = "group.starved";
+
+/// How many rounds a member sits out after a yielded envelope.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum YieldBackoff {
+    /// The same number of rounds after every yield.
+    Fixed(u64),
+    /// `base` rounds after the first of consecutive yields, doubling with each
+    /// further one, never more than `max`.
+    Exponential { base: u64, max: u64 },
+}
+
+impl YieldBackoff {
+    /// Rounds to skip after the `streak`-th consecutive yield (counting from 1).
+    pub fn rounds(self, streak: u32) -> u64 {
+        match self {
+            YieldBackoff::Fixed(rounds) => rounds,
+            YieldBackoff::Exponential { base, max } => {
+                let doublings = streak.saturating_sub(1);
+                base.saturating_mul(2u64.saturating_pow(doublings)).min(max)
+            }
+        }
+    }
+}
+
+/// Scheduling state of one member.
+#[derive(Debug, Clone, Copy, Default)]
+struct Slot {
+    /// Round at which the member was admitted.
+    admitted_at: u64,
+    /// Consecutive yielded envelopes, for the backoff.
+    yield_streak: u32,
+    /// First round in which the member may be stepped again.
+    resume_at: u64,
+    /// Rounds skipped since the member was last stepped.
+    skipped: u64,
+    /// Yielded envelopes in total.
+    yields: u64,
+    /// Rounds skipped in total.
+    waits: u64,
+    /// First round after the member was last stepped; its admission round before that.
+    idle_since: u64,
+}
+
+/// Point-in-time numbers for one member, from [`FrameGroup::frame_stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameStats {
+    pub state: FrameState,
+    pub tokens_generated: usize,
+    pub steps_taken: u64,
+    /// Envelopes that came back [`StepOutcome::Yielded`].
+    pub yields: u64,
+    /// Rounds the member sat out under [`FrameGroup::with_yield_backoff`].
+    pub waits: u64,
+    /// Rounds since the member was admitted.
+    pub age_rounds: u64,
+    /// Rounds since the member was last stepped, or admitted if it never was.
+    pub idle_rounds: u64,
+    pub stop_reason: Option<StopReason>,
+}
+
+/// What a [`Scheduler`] sees of a member it may pick.
+#[derive(Debug, Clone)]
+pub struct Candidate {
+    pub index: usize,
+    pub stats: FrameStats,
+    pub progress: FrameProgress,
+    pub tags: Vec<Tag>,
+}
+
+/// Chooses the member each [`FrameGroup::step_tick`] steps.
+///
+/// Built-in policies live in [`schedulers`](crate::schedulers).
+pub trait Scheduler {
+    /// Index of the member to step in `round`, taken from `ready` (never empty), or
+    /// `None` to step nobody this tick.
+    fn pick(&mut self, round: u64, ready: &[Candidate]) -> Option<usize>;
+
+    /// Receipts for the envelope of `picked`; called right before it is stepped.
+    fn receipts(&mut self, _picked: &Candidate, _out: &mut Vec<Receipt>) {}
+}
+
+/// One step recorded by [`FrameGroup::with_schedule_log`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ScheduleEntry {
+    pub round: u64,
+    pub member: usize,
+}
+
+/// Boxed scheduler as held by [`FrameGroup`].
+pub type BoxedScheduler = Box<dyn Scheduler + Send + Sync>;
+
+/// A group of drivers whose frames share one memory handle.
+///
+/// Members are stepped in admission order, one step each per round, so backends
+/// that mutate the shared object see a fixed interleaving on every run.
+///
+/// A member whose frame sets [`FrameLimits::max_age_rounds`](crate::FrameLimits)
+/// is finished with [`StopReason::Expired`] once it has been offered that many
+/// rounds.
+///
+/// With [`FrameGroup::with_finish_boost`], members close to their token cap are
+/// stepped first in each round so nearly finished responses are not queued behind
+/// long generations on a shared backend.
+///
+/// With [`FrameGroup::with_yield_backoff`], a member whose envelope was yielded (by
+/// its arbiter, or by a law such as a pause) sits out the next rounds instead of
+/// yielding again in each of them.
+///
+/// With [`FrameGroup::with_starvation_limit`], a member that has gone that many rounds
+/// without a step is stepped regardless of backoff or scheduler.
+///
+/// [`FrameGroup::step_tick`] is the alternative to whole rounds: each call is a round
+/// in which a [`Scheduler`] picks the one member to step. A schedule worth
+/// reproducing can be recorded with [`FrameGroup::with_schedule_log`] and fed back
+/// through [`Replay`](crate::schedulers::Replay).
+pub struct FrameGroup<H, S, A = NoArbiter>
+where
+    S: FrameStepper<H>,
+    A: Arbiter<H>,
+{
+    shared: H,
+    members: Vec<Driver<H, S, A>>,
+    slots: Vec<Slot>,
+    round: u64,
+    finish_boost: Option<usize>,
+    yield_backoff: Option<YieldBackoff>,
+    starvation_limit: Option<u64>,
+    scheduler: Option<BoxedScheduler>,
+    schedule: Option<Vec<ScheduleEntry>>,
+}
+
+impl<H, S, A> FrameGroup<H, S, A>
+where
+    H: Clone,
+    S: FrameStepper<H>,
+    A: Arbiter<H>,
+{
+    pub fn new(shared: H) -> Self {
+        Self {
+            shared,
+            members: Vec::new(),
+            slots: Vec::new(),
+            round: 0,
+            finish_boost: None,
+            yield_backoff: None,
+            starvation_limit: None,
+            scheduler: None,
+            schedule: None,
+        }
+    }
+
+    /// Step members with at most `within` tokens left before the rest in each round.
+    ///
+    /// Boosted members keep their admission order among themselves, as do the others,
+    /// and each boosted envelope carries a [`GROUP_BOOST`] receipt.
+    pub fn with_finish_boost(mut self, within: usize) -> Self {
+        self.finish_boost = Some(within);
+        self
+    }
+
+    /// Let members that yield sit out rounds as `backoff` says. The first envelope
+    /// after a backoff carries a [`GROUP_BACKOFF`] receipt.
+    pub fn with_yield_backoff(mut self, backoff: YieldBackoff) -> Self {
+        self.yield_backoff = Some(backoff);
+        self
+    }
+
+    /// Step any member that has gone `rounds` rounds (at least one) without a step:
+    /// ahead of the scheduler in [`FrameGroup::step_tick`], and despite a pending
+    /// backoff in either kind of round. Its envelope carries a [`GROUP_STARVED`]
+    /// receipt.
+    pub fn with_starvation_limit(mut self, rounds: u64) -> Self {
+        self.starvation_limit = Some(rounds.max(1));
+        self
+    }
+
+    /// Pick members for [`FrameGroup::step_tick`] with `scheduler` instead of
+    /// [`RoundRobin`].
+    pub fn with_scheduler(mut self, scheduler: impl Scheduler + Send + Sync + 'static) -> Self {
+        self.scheduler = Some(Box::new(scheduler));
+        self
+    }
+
+    /// Record every step the group takes, in order, for [`FrameGroup::schedule`].
+    pub fn with_schedule_log(mut self) -> Self {
+        self.schedule = Some(Vec::new());
+        self
+    }
+
+    /// The steps recorded since [`FrameGroup::with_schedule_log`], including those
+    /// whose stepper failed; empty if the log is off.
+    pub fn schedule(&self) -> &[ScheduleEntry] {
+        self.schedule.as_deref().unwrap_or_default()
+    }
+
+    pub fn shared(&self) -> &H {
+        &self.shared
+    }
+
+    /// A new frame whose memory is a clone of the group's shared handle.
+    pub fn frame(&self, max_new_tokens: usize) -> Frame<H> {
+        Frame::new(self.shared.clone(), max_new_tokens)
+    }
+
+    pub fn frame_with_prompt(&self, max_new_tokens: usize, prompt_token_ids: Vec<u32>) -> Frame<H> {
+        Frame::with_prompt(self.shared.clone(), max_new_tokens, prompt_token_ids)
+    }
+
+    /// Admit a driver built on one of this group's frames. Returns its member index.
+    pub fn push(&mut self, driver: Driver<H, S, A>) -> usize {
+        self.members.push(driver);
+        self.slots.push(Slot {
+            admitted_at: self.round,
+            resume_at: self.round,
+            idle_since: self.round,
+            ..Slot::default()
+        });
+        self.members.len() - 1
+    }
+
+    pub fn members(&self) -> &[Driver<H, S, A>] {
+        &self.members
+    }
+
+    pub fn member_mut(&mut self, index: usize) -> Option<&mut Driver<H, S, A>> {
+        self.members.get_mut(index)
+    }
+
+    /// True once every member has finished or been cancelled.
+    pub fn is_finished(&self) -> bool {
+        self.members.iter().all(is_done)
+    }
+
+    /// Step each live member once, in admission order (boosted members first),
+    /// except those backing off after a yield and not yet starved.
+    ///
+    /// Returns `(member index, result)` for every member that was stepped. Stops at the
+    /// first stepper error, leaving later members unstepped for this round; the
+    /// [`RoundError`] still carries the envelopes of the members stepped before it.
+    pub fn step_round(&mut self) -> Result<Vec<(usize, StepResult)>, RoundError> {
+        let round = self.round;
+        self.round += 1;
+        let mut stepped = Vec::new();
+        for (i, boost) in self.round_order(round) {
+            match self.step_member(i, round, boost, Vec::new()) {
+                Ok(r) => stepped.push((i, r)),
+                Err(error) => {
+                    return Err(RoundError {
+                        stepped,
+                        member: i,
+                        error,
+                    })
+                }
+            }
+        }
+        Ok(stepped)
+    }
+
+    /// Step one live member as a round of its own and return its envelope, or `None`
+    /// if nobody was stepped.
+    ///
+    /// A member past [`FrameLimits::max_age_rounds`](crate::FrameLimits) is picked
+    /// first so it cannot linger unpicked, then the member longest past the
+    /// starvation limit, if any. Otherwise the group's [`Scheduler`] picks,
+    /// among the boosted members if any are (see [`FrameGroup::with_finish_boost`]),
+    /// else among all that are not backing off. A scheduler that picks a member it
+    /// was not offered fails the tick.
+    pub fn step_tick(&mut self) -> Result<Option<(usize, StepResult)>, RoundError> {
+        let round = self.round;
+        self.round += 1;
+        let mut order = self.round_order(round);
+        let expired = order.iter().find(|(i, _)| self.is_expired(*i, round));
+        let starved = order
+            .iter()
+            .filter(|(i, _)| self.is_starved(*i, round))
+            .max_by_key(|(i, _)| (round.saturating_sub(self.slots[*i].idle_since), Reverse(*i)));
+        let (i, boost, receipts) = match expired.or(starved) {
+            Some(&(i, boost)) => (i, boost, Vec::new()),
+            None => {
+                if order.iter().any(|(_, boost)| boost.is_some()) {
+                    order.retain(|(_, boost)| boost.is_some());
+                }
+                let ready: Vec<Candidate> = order
+                    .iter()
+                    .map(|&(i, _)| self.candidate(i, round))
+                    .collect();
+                let scheduler = self.scheduler.get_or_insert_with(|| Box::new(RoundRobin));
+                let Some(i) = (!ready.is_empty())
+                    .then(|| scheduler.pick(round, &ready))
+                    .flatten()
+                else {
+                    return Ok(None);
+                };
+                let Some(at) = ready.iter().position(|c| c.index == i) else {
+                    return Err(RoundError {
+                        stepped: Vec::new(),
+                        member: i,
+                        error: "scheduler picked a member that was not offered".to_string(),
+                    });
+                };
+                let mut receipts = Vec::new();
+                scheduler.receipts(&ready[at], &mut receipts);
+                (i, order[at].1, receipts)
+            }
+        };
+        match self.step_member(i, round, boost, receipts) {
+            Ok(r) => Ok(Some((i, r))),
+            Err(error) => Err(RoundError {
+                stepped: Vec::new(),
+                member: i,
+                error,
+            }),
+        }
+    }
+
+    /// Shut the group down: run live members for at most `max_rounds` more rounds,
+    /// then cancel whatever is still running.
+    ///
+    /// Consuming the group is what stops admission. A member whose stepper fails is
+    /// cancelled on the spot and reported in [`DrainReport::failed`]; the others keep
+    /// going. Members cancelled either way are stepped once more so their sinks see
+    /// the final envelope.
+    pub fn drain(mut self, max_rounds: u64) -> DrainReport<H, S, A> {
+        let live: Vec<usize> = (0..self.members.len())
+            .filter(|&i| !is_done(&self.members[i]))
+            .collect();
+        let mut failed = Vec::new();
+        let mut rounds = 0;
+        while rounds < max_rounds && !self.is_finished() {
+            let round = self.round;
+            self.round += 1;
+            rounds += 1;
+            for (i, boost) in self.round_order(round) {
+                if let Err(e) = self.step_member(i, round, boost, Vec::new()) {
+                    let d = &mut self.members[i];
+                    d.frame.cancel();
+                    // The cancelled arm never reaches the stepper, so this cannot fail.
+                    let _ = d.step();
+                    failed.push((i, e));
+                }
+            }
+        }
+        let mut cancelled = Vec::new();
+        for (i, d) in self.members.iter_mut().enumerate() {
+            if is_done(d) {
+                continue;
+            }
+            d.frame.cancel();
+            // The cancelled arm never reaches the stepper, so this cannot fail.
+            let _ = d.step();
+            cancelled.push(i);
+        }
+        let completed = live
+            .iter()
+            .filter(|&&i| {
+                let frame = &self.members[i].frame;
+                frame.state == FrameState::Finished
+                    && frame.stop_reason != Some(StopReason::Cancelled)
+            })
+            .count();
+        DrainReport {
+            rounds,
+            in_flight: live.len(),
+            completed,
+            cancelled,
+            failed,
+            members: self.members,
+        }
+    }
+
+    /// Live members in the order round `round` steps them, each with its remaining
+    /// token budget if it is boosted. Members still backing off are left out and
+    /// charged a skipped round.
+    fn round_order(&mut self, round: u64) -> Vec<(usize, Option<usize>)> {
+        let mut boosted = Vec::new();
+        let mut rest = Vec::new();
+        for (i, d) in self.members.iter().enumerate() {
+            if is_done(d) {
+                continue;
+            }
+            let starved = self.is_starved(i, round);
+            let slot = &mut self.slots[i];
+            if slot.resume_at > round && !starved {
+                slot.skipped += 1;
+                slot.waits += 1;
+                continue;
+            }
+            let remaining = d.frame.progress().remaining_tokens;
+            match (self.finish_boost, remaining) {
+                (Some(within), Some(left)) if left <= within => boosted.push((i, Some(left))),
+                _ => rest.push((i, None)),
+            }
+        }
+        boosted.extend(rest);
+        boosted
+    }
+
+    /// Step member `i` in round `round` with its boost and backoff receipts followed by
+    /// `queued`, and start a backoff if the envelope was yielded.
+    fn step_member(
+        &mut self,
+        i: usize,
+        round: u64,
+        boost: Option<usize>,
+        mut queued: Vec<Receipt>,
+    ) -> Result<StepResult, String> {
+        if let Some(log) = &mut self.schedule {
+            log.push(ScheduleEntry { round, member: i });
+        }
+        if self.is_starved(i, round) {
+            let idle = round.saturating_sub(self.slots[i].idle_since);
+            queued.push(Receipt::new(GROUP_STARVED, idle));
+        }
+        let slot = &mut self.slots[i];
+        if let Some(left) = boost {
+            queued.push(Receipt::new(GROUP_BOOST, left as u64));
+        }
+        if slot.skipped > 0 {
+            queued.push(Receipt::new(GROUP_BACKOFF, slot.skipped));
+        }
+        let r = step_member(&mut self.members[i], round - slot.admitted_at, queued)?;
+        slot.skipped = 0;
+        slot.idle_since = round + 1;
+        if r.outcome == StepOutcome::Yielded {
+            slot.yields += 1;
+        }
+        match (r.outcome, self.yield_backoff) {
+            (StepOutcome::Yielded, Some(backoff)) => {
+                slot.yield_streak = slot.yield_streak.saturating_add(1);
+                let wait = backoff.rounds(slot.yield_streak);
+                slot.resume_at = round.saturating_add(1).saturating_add(wait);
+            }
+            _ => slot.yield_streak = 0,
+        }
+        Ok(r)
+    }
+
+    /// Numbers for member `index`, or `None` if there is no such member.
+    pub fn frame_stats(&self, index: usize) -> Option<FrameStats> {
+        (index < self.members.len()).then(|| self.stats_at(index, self.round))
+    }
+
+    fn stats_at(&self, index: usize, round: u64) -> FrameStats {
+        let (frame, slot) = (&self.members[index].frame, &self.slots[index]);
+        FrameStats {
+            state: frame.state,
+            tokens_generated: frame.tokens_generated,
+            steps_taken: frame.steps_taken,
+            yields: slot.yields,
+            waits: slot.waits,
+            age_rounds: round - slot.admitted_at,
+            idle_rounds: round.saturating_sub(slot.idle_since),
+            stop_reason: frame.stop_reason,
+        }
+    }
+
+    /// Member `index` as offered to the scheduler in `round`.
+    fn candidate(&self, index: usize, round: u64) -> Candidate {
+        let frame = &self.members[index].frame;
+        Candidate {
+            index,
+            stats: self.stats_at(index, round),
+            progress: frame.progress(),
+            tags: frame.tags.clone(),
+        }
+    }
+
+    /// Whether member `index` has gone the starvation limit without a step by `round`.
+    fn is_starved(&self, index: usize, round: u64) -> bool {
+        let idle = round.saturating_sub(self.slots[index].idle_since);
+        self.starvation_limit.is_some_and(|limit| idle >= limit)
+    }
+
+    /// Whether member `index` has outlived its `max_age_rounds` in `round`.
+    fn is_expired(&self, index: usize, round: u64) -> bool {
+        let age = round - self.slots[index].admitted_at;
+        let limits = &self.members[index].frame.limits;
+        limits.max_age_rounds.is_some_and(|max| age >= max)
+    }
+
+    /// [`FrameGroup::frame_stats`] for every member, in admission order, taken between
+    /// rounds so the numbers are consistent with each other.
+    pub fn all_stats(&self) -> Vec<FrameStats> {
+        (0..self.members.len())
+            .filter_map(|i| self.frame_stats(i))
+            .collect()
+    }
+
+    /// Rounds stepped so far.
+    pub fn rounds(&self) -> u64 {
+        self.round
+    }
+
+    pub fn into_members(self) -> Vec<Driver<H, S, A>> {
+        self.members
+    }
+}
+
+/// A [`FrameGroup::step_round`] cut short by a stepper error.
+#[derive(Debug, Clone)]
+pub struct RoundError {
+    /// `(member index, result)` for the members stepped before the failure.
+    pub stepped: Vec<(usize, StepResult)>,
+    /// The member whose stepper failed.
+    pub member: usize,
+    pub error: String,
+}
+
+impl fmt::Display for RoundError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "group member {}: {}", self.member, self.error)
+    }
+}
+
+impl std::error::Error for RoundError {}
+
+/// Outcome of [`FrameGroup::drain`].
+pub struct DrainReport<H, S, A = NoArbiter>
+where
+    S: FrameStepper<H>,
+    A: Arbiter<H>,
+{
+    /// Rounds run while draining.
+    pub rounds: u64,
+    /// Members that were live when the drain began.
+    pub in_flight: usize,
+    /// Of those, the members that finished within the budget for a reason other than
+    /// a cancel. Members their arbiter refused are not among them.
+    pub completed: usize,
+    /// Members cut off when the budget ran out, by index.
+    pub cancelled: Vec<usize>,
+    /// Members cancelled after a stepper error, with the error.
+    pub failed: Vec<(usize, String)>,
+    /// Every member, in admission order.
+    pub members: Vec<Driver<H, S, A>>,
+}
+
+impl<H, S, A> DrainReport<H, S, A>
+where
+    S: FrameStepper<H>,
+    A: Arbiter<H>,
+{
+    /// True if every in-flight member finished on its own.
+    pub fn is_clean(&self) -> bool {
+        self.completed == self.in_flight
+    }
+}
+
+fn is_done<H, S, A>(d: &Driver<H, S, A>) -> bool
+where
+    S: FrameStepper<H>,
+    A: Arbiter<H>,
+{
+    matches!(d.frame.state, FrameState::Finished | FrameState::Cancelled)
+}
+
+/// Step one live member that has been in the group for `age` rounds, expiring it first
+/// if it has outlived `max_age_rounds`. `queued` receipts go on this step's envelope.
+fn step_member<H, S, A>(
+    d: &mut Driver<H, S, A>,
+    age: u64,
+    queued: Vec<Receipt>,
+) -> Result<StepResult, String>
+where
+    S: FrameStepper<H>,
+    A: Arbiter<H>,
+{
+    let mark = d.pending_receipts.len();
+    d.pending_receipts.extend(queued);
+    if d.frame.limits.max_age_rounds.is_some_and(|max| age >= max) {
+        d.frame.state = FrameState::Finished;
+        d.frame.paused_from = None;
+        d.frame.stop_reason = Some(StopReason::Expired);
+        d.pending_receipts.push(Receipt::new(GROUP_EXPIRED, age));
+    }
+    let r = d.step();
+    if r.is_err() {
+        // The queued receipts describe this step; a retry queues its own.
+        d.pending_receipts.truncate(mark);
+    }
+    r
+}
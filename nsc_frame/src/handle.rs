@@ -0,0 +1,98 @@
+//! Control plane for a driver running on another thread.
+//!
+//! A [`DriverHandle`] never touches the driver itself: commands go into a small
+//! inbox that the driver moves into its [`DriverCommand`] queue at the top of each
+//! step, and status is read from a [`DriverStatus`] the driver publishes after each
+//! step. Neither side waits on a step in progress.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use crate::command::{CancelMode, DriverCommand};
+use crate::shared::DriverStatus;
+use crate::{Arbiter, Driver, FrameState, FrameStepper, StopReason};
+
+/// Cloneable, `Send + Sync` handle for commanding and observing one driver.
+#[derive(Debug, Clone)]
+pub struct DriverHandle {
+    inbox: Arc<Mutex<VecDeque<DriverCommand>>>,
+    status: Arc<Mutex<DriverStatus>>,
+}
+
+impl DriverHandle {
+    /// Queue `command` for the driver's next step.
+    pub fn send(&self, command: DriverCommand) {
+        self.inbox
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push_back(command);
+    }
+
+    /// Shorthand for `send(DriverCommand::Cancel(CancelMode::Immediate))`.
+    pub fn cancel(&self) {
+        self.send(DriverCommand::Cancel(CancelMode::Immediate));
+    }
+
+    /// Shorthand for `send(DriverCommand::Abort(reason))`.
+    pub fn abort(&self, reason: StopReason) {
+        self.send(DriverCommand::Abort(reason));
+    }
+
+    /// Shorthand for `send(DriverCommand::GrantCredits(n))`: let the driver emit `n`
+    /// more tokens under flow control.
+    pub fn grant_credits(&self, n: u64) {
+        self.send(DriverCommand::GrantCredits(n));
+    }
+
+    /// Status as of the driver's latest step.
+    pub fn status(&self) -> DriverStatus {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn is_done(&self) -> bool {
+        matches!(
+            self.status().state,
+            FrameState::Finished | FrameState::Cancelled
+        )
+    }
+
+    /// Whether a clone of this handle lives outside the driver that owns it.
+    pub(crate) fn is_held_elsewhere(&self) -> bool {
+        Arc::strong_count(&self.inbox) > 1
+    }
+
+    fn take_commands(&self) -> VecDeque<DriverCommand> {
+        std::mem::take(&mut *self.inbox.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+impl<M, S, A> Driver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Handle for controlling this driver from other threads. Every call returns a
+    /// clone of the same handle.
+    pub fn handle(&mut self) -> DriverHandle {
+        let status = DriverStatus::of(self);
+        self.handle
+            .get_or_insert_with(|| DriverHandle {
+                inbox: Arc::default(),
+                status: Arc::new(Mutex::new(status)),
+            })
+            .clone()
+    }
+
+    /// Move commands sent through the handle into the driver's queue.
+    pub(crate) fn pull_handle_commands(&mut self) {
+        if let Some(h) = &self.handle {
+            self.commands.extend(h.take_commands());
+        }
+    }
+
+    pub(crate) fn publish_status(&self) {
+        if let Some(h) = &self.handle {
+            *h.status.lock().unwrap_or_else(|e| e.into_inner()) = DriverStatus::of(self);
+        }
+    }
+}
@@ -0,0 +1,55 @@
+//! Incremental checksums over emitted tokens.
+
+/// Streaming hash over token ids, fed one committed token at a time.
+///
+/// Producer and consumer can each run the same hasher over the stream they see and
+/// compare digests, without buffering the output.
+pub trait TokenHasher {
+    fn update(&mut self, token: u32);
+    fn digest(&self) -> u64;
+}
+
+/// 64-bit FNV-1a over the little-endian bytes of each token id.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fnv1a64 {
+    state: u64,
+}
+
+impl Fnv1a64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+
+    pub fn new() -> Self {
+        Self {
+            state: Self::OFFSET_BASIS,
+        }
+    }
+
+    /// Digest of a whole token sequence.
+    pub fn digest_of(tokens: impl IntoIterator<Item = u32>) -> u64 {
+        let mut h = Self::new();
+        for t in tokens {
+            h.update(t);
+        }
+        h.digest()
+    }
+}
+
+impl Default for Fnv1a64 {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TokenHasher for Fnv1a64 {
+    fn update(&mut self, token: u32) {
+        for b in token.to_le_bytes() {
+            self.state ^= b as u64;
+            self.state = self.state.wrapping_mul(Self::PRIME);
+        }
+    }
+
+    fn digest(&self) -> u64 {
+        self.state
+    }
+}
@@ -0,0 +1,93 @@
+//! Liveness signals from inside long backend steps.
+//!
+//! A backend that overrides [`FrameStepper::step_with`] receives a [`Progress`] and
+//! may call [`Progress::beat`] while it works, e.g. once per prefill sub-chunk with
+//! the tokens processed so far. Each beat reaches the driver's live hook
+//! ([`Driver::on_heartbeat`]) immediately, and the first [`MAX_HEARTBEATS`] become
+//! [`STEP_HEARTBEAT`] receipts on the step's result.
+
+use std::fmt;
+
+use crate::{Arbiter, Driver, FrameStepper, Receipt, StepResult};
+
+/// Receipt kind for one heartbeat; value is the backend's progress figure.
+pub const STEP_HEARTBEAT: &str = "step.heartbeat";
+
+/// Heartbeats per step kept as receipts; later ones only reach the live hook.
+pub const MAX_HEARTBEATS: usize = 16;
+
+/// Live heartbeat hook installed with [`Driver::on_heartbeat`].
+pub type HeartbeatHook = Box<dyn FnMut(u64) + Send + Sync>;
+
+/// Progress reporter handed to [`FrameStepper::step_with`].
+pub struct Progress<'a> {
+    beats: Vec<u64>,
+    hook: Option<&'a mut HeartbeatHook>,
+}
+
+impl<'a> Progress<'a> {
+    /// A reporter that only records beats.
+    pub fn new() -> Self {
+        Self {
+            beats: Vec::new(),
+            hook: None,
+        }
+    }
+
+    pub(crate) fn with_hook(hook: Option<&'a mut HeartbeatHook>) -> Self {
+        Self {
+            beats: Vec::new(),
+            hook,
+        }
+    }
+
+    /// Report that the step is still alive, with a backend-defined progress figure.
+    pub fn beat(&mut self, value: u64) {
+        if let Some(hook) = self.hook.as_mut() {
+            hook(value);
+        }
+        if self.beats.len() < MAX_HEARTBEATS {
+            self.beats.push(value);
+        }
+    }
+
+    /// Beats recorded so far (at most [`MAX_HEARTBEATS`]).
+    pub fn beats(&self) -> &[u64] {
+        &self.beats
+    }
+
+    pub(crate) fn attach(self, r: &mut StepResult) {
+        r.receipts.extend(
+            self.beats
+                .into_iter()
+                .map(|v| Receipt::new(STEP_HEARTBEAT, v)),
+        );
+    }
+}
+
+impl fmt::Debug for Progress<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("beats", &self.beats)
+            .field("hook", &self.hook.is_some())
+            .finish()
+    }
+}
+
+impl Default for Progress<'_> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<M, S, A> Driver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Call `hook` with every heartbeat as the backend reports it.
+    pub fn on_heartbeat(mut self, hook: impl FnMut(u64) + Send + Sync + 'static) -> Self {
+        self.heartbeat_hook = Some(Box::new(hook));
+        self
+    }
+}
@@ -0,0 +1,57 @@
+//! UTF-8 hold-back for streaming detokenizers.
+//!
+//! A byte-level vocabulary can split one character (or grapheme) across several
+//! tokens. [`Utf8HoldBack`] counts the trailing tokens emitted since the last
+//! backend boundary hint ([`EMIT_BOUNDARY`]); those may still be an incomplete
+//! character. [`Driver::safe_emit_upto`] is the prefix of `generated_token_ids` a
+//! frontend can detokenize and print without risking partial UTF-8.
+
+use crate::sink::EMIT_BOUNDARY;
+use crate::{Arbiter, Driver, FrameStepper, StepOutcome, StepResult};
+
+/// Trailing tokens not yet closed by a boundary hint.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Utf8HoldBack {
+    held: usize,
+}
+
+impl Utf8HoldBack {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tokens at the end of the output that may form an incomplete character.
+    pub fn held(&self) -> usize {
+        self.held
+    }
+
+    /// Fold one step. A finished frame releases everything: no more bytes will come.
+    pub fn observe(&mut self, r: &StepResult) {
+        self.held += r.tokens_committed as usize;
+        if r.outcome == StepOutcome::Finished || r.receipts.iter().any(|x| x.kind == EMIT_BOUNDARY)
+        {
+            self.held = 0;
+        }
+    }
+}
+
+impl<M, S, A> Driver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Track trailing tokens that may split a character, from boundary hints.
+    pub fn with_utf8_holdback(mut self) -> Self {
+        self.holdback = Some(Utf8HoldBack::new());
+        self
+    }
+
+    /// Number of leading `generated_token_ids` safe to detokenize and print.
+    ///
+    /// Without hold-back tracking every generated token counts as safe.
+    pub fn safe_emit_upto(&self) -> usize {
+        let generated = self.frame.generated_token_ids.len();
+        let held = self.holdback.map_or(0, |h| h.held());
+        generated.saturating_sub(held)
+    }
+}
@@ -0,0 +1,169 @@
+//! Per-frame receipt retention under a memory budget.
+//!
+//! [`ReceiptLedger`] keeps the receipts of each caller-assigned frame id. When a
+//! frame's retained receipts exceed its [`ReceiptBudget`], the oldest unprotected
+//! ones are folded into per-kind aggregates (count and saturating sum) so long runs
+//! with verbose middleware stay bounded. Receipts whose kind starts with a
+//! protected prefix are never folded; by default that is `law.`, `stop.` and `run.`.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{Receipt, StepResult};
+
+/// Limits on the receipts retained per frame. `None` means no limit.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReceiptBudget {
+    pub max_count: Option<usize>,
+    /// Bytes as encoded on the wire: `u32` kind length, kind, `u64` value.
+    pub max_bytes: Option<usize>,
+}
+
+impl ReceiptBudget {
+    pub fn unbounded() -> Self {
+        Self::default()
+    }
+
+    fn exceeded(&self, count: usize, bytes: usize) -> bool {
+        self.max_count.is_some_and(|m| count > m) || self.max_bytes.is_some_and(|m| bytes > m)
+    }
+}
+
+/// Receipts folded out of retention for one kind.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReceiptAggregate {
+    pub count: u64,
+    pub sum: u64,
+}
+
+/// One frame's receipts: retained in arrival order, plus aggregates of folded ones.
+#[derive(Debug, Clone, Default)]
+pub struct FrameReceipts {
+    retained: VecDeque<(u64, Receipt)>,
+    aggregated: BTreeMap<&'static str, ReceiptAggregate>,
+    bytes: usize,
+    steps: u64,
+}
+
+impl FrameReceipts {
+    /// Retained receipts as `(step index, receipt)`, oldest first.
+    pub fn retained(&self) -> impl Iterator<Item = (u64, &Receipt)> + '_ {
+        self.retained.iter().map(|(step, r)| (*step, r))
+    }
+
+    pub fn retained_len(&self) -> usize {
+        self.retained.len()
+    }
+
+    /// Encoded size of the retained receipts.
+    pub fn bytes(&self) -> usize {
+        self.bytes
+    }
+
+    /// Aggregates of folded receipts, by kind.
+    pub fn aggregated(&self) -> impl Iterator<Item = (&'static str, ReceiptAggregate)> + '_ {
+        self.aggregated.iter().map(|(k, a)| (*k, *a))
+    }
+
+    /// Receipts folded into aggregates so far.
+    pub fn folded(&self) -> u64 {
+        self.aggregated.values().map(|a| a.count).sum()
+    }
+
+    /// Envelopes recorded for this frame.
+    pub fn steps(&self) -> u64 {
+        self.steps
+    }
+
+    fn fold_oldest(&mut self, protected: &[&'static str]) -> bool {
+        let Some(pos) = self
+            .retained
+            .iter()
+            .position(|(_, r)| !protected.iter().any(|p| r.kind.starts_with(p)))
+        else {
+            return false;
+        };
+        let Some((_, r)) = self.retained.remove(pos) else {
+            return false;
+        };
+        self.bytes -= encoded_len(&r);
+        let agg = self.aggregated.entry(r.kind).or_default();
+        agg.count += 1;
+        agg.sum = agg.sum.saturating_add(r.value_u64);
+        true
+    }
+}
+
+/// Receipts per caller-assigned frame id, each frame held to the same budget.
+#[derive(Debug, Clone)]
+pub struct ReceiptLedger {
+    budget: ReceiptBudget,
+    protected: Vec<&'static str>,
+    frames: BTreeMap<u64, FrameReceipts>,
+}
+
+impl Default for ReceiptLedger {
+    fn default() -> Self {
+        Self::new(ReceiptBudget::unbounded())
+    }
+}
+
+impl ReceiptLedger {
+    pub fn new(budget: ReceiptBudget) -> Self {
+        Self {
+            budget,
+            protected: vec!["law.", "stop.", "run."],
+            frames: BTreeMap::new(),
+        }
+    }
+
+    /// Never fold receipts whose kind starts with `prefix`.
+    pub fn protect(mut self, prefix: &'static str) -> Self {
+        self.protected.push(prefix);
+        self
+    }
+
+    /// Replace the protected prefixes, including the defaults.
+    pub fn with_protected(mut self, prefixes: Vec<&'static str>) -> Self {
+        self.protected = prefixes;
+        self
+    }
+
+    pub fn budget(&self) -> ReceiptBudget {
+        self.budget
+    }
+
+    /// Retain `r`'s receipts under `frame_id`, folding the oldest unprotected ones
+    /// while the frame is over budget. Protected receipts may keep it over budget.
+    pub fn record(&mut self, frame_id: u64, r: &StepResult) {
+        let frame = self.frames.entry(frame_id).or_default();
+        let step = frame.steps;
+        frame.steps += 1;
+        for receipt in &r.receipts {
+            frame.bytes += encoded_len(receipt);
+            frame.retained.push_back((step, receipt.clone()));
+        }
+        while self.budget.exceeded(frame.retained.len(), frame.bytes) {
+            if !frame.fold_oldest(&self.protected) {
+                break;
+            }
+        }
+    }
+
+    pub fn frame(&self, frame_id: u64) -> Option<&FrameReceipts> {
+        self.frames.get(&frame_id)
+    }
+
+    /// Frames in ascending id order.
+    pub fn frames(&self) -> impl Iterator<Item = (u64, &FrameReceipts)> + '_ {
+        self.frames.iter().map(|(id, f)| (*id, f))
+    }
+
+    /// Forget a frame, returning what was held for it.
+    pub fn remove(&mut self, frame_id: u64) -> Option<FrameReceipts> {
+        self.frames.remove(&frame_id)
+    }
+}
+
+fn encoded_len(r: &Receipt) -> usize {
+    4 + r.kind.len() + 8
+}
@@ -8,42 +8,368 @@
 //!
 //! This crate intentionally contains **no I/O**, **no UI**, and **no model-specific logic**.
 //!
+//! # Thread safety
+//!
+//! Law types hold no interior mutability of their own, so auto traits follow their
+//! parameters: `Frame<M>` is `Send`/`Sync` when `M` is, and `Driver<M, S, A>` is
+//! `Send` when `M`, `S` and `A` are. [`DynamicLimits`], [`MemoryGauge`] and
+//! [`ArbiterContext`] are shared handles and always `Send + Sync`. To share one
+//! driver between threads use [`SharedDriver`].
+//!
+
+use std::fmt;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+
+pub mod analysis;
+pub mod arbiters;
+pub mod billing;
+pub mod boxed;
+pub mod channel;
+pub mod codec;
+pub mod command;
+pub mod compact;
+pub mod compute;
+pub mod debug;
+pub mod either;
+#[cfg(feature = "fuzz")]
+pub mod fuzz;
+pub mod group;
+pub mod handle;
+pub mod hash;
+pub mod heartbeat;
+pub mod holdback;
+pub mod ledger;
+pub mod machine;
+pub mod mem;
+pub mod migration;
+pub mod protocol;
+pub mod redact;
+pub mod rng;
+pub mod schedulers;
+pub mod shared;
+pub mod sink;
+pub mod snapshot;
+pub mod stop;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tokens;
+pub mod trace;
+pub mod tracefile;
+pub mod typed;
+pub mod validate;
+pub mod view;
+mod wire;
+#[cfg(feature = "zeroize")]
+pub mod zeroize;
+
+pub use analysis::{KindSummary, ReceiptStats};
+pub use arbiters::{BoxedArbiter, CachedArbiter, MemoryPressureArbiter, QuorumArbiter, QuorumRule};
+pub use billing::{Billing, Invoice};
+pub use boxed::{BoxedStepper, DynDriver};
+pub use channel::{token_channel, TokenEvent, TokenReceiver, TokenSender};
+pub use codec::SnapshotCodec;
+pub use command::{CancelMode, DriverCommand, COMMAND_REJECTED};
+pub use compact::CompactTrace;
+pub use compute::{ComputeLedger, ComputeTotals};
+pub use debug::DebugDriver;
+pub use either::EitherStepper;
+pub use group::{
+    BoxedScheduler, Candidate, DrainReport, FrameGroup, FrameStats, RoundError, ScheduleEntry,
+    Scheduler, YieldBackoff,
+};
+pub use handle::DriverHandle;
+pub use hash::{Fnv1a64, TokenHasher};
+pub use heartbeat::Progress;
+pub use holdback::Utf8HoldBack;
+pub use ledger::{ReceiptBudget, ReceiptLedger};
+pub use machine::{FrameMachine, MachineInput, MachineOutput};
+pub use mem::{
+    BlockId, DeepCloneMem, MemAccounting, MemHandle, MemTable, MemoryGauge, PagedMemory,
+};
+pub use migration::MigrationBundle;
+pub use redact::Redaction;
+pub use shared::{DriverStatus, SharedDriver};
+pub use sink::{FlushPolicy, ReceiptSink, SinkControl, TokenSink};
+pub use snapshot::FrameSnapshot;
+pub use stop::StopCondition;
+pub use tokens::TokenLog;
+pub use trace::{
+    bisect, Bisection, Keyframe, RecordingStepper, Trace, TraceEntry, VerifyingStepper,
+};
+pub use tracefile::{TraceFile, TraceHeader};
+pub use typed::TypedFrame;
+pub use validate::FrameError;
+pub use view::FrameView;
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FrameState {
     Prefill,
     Decode,
+    /// Temporarily not runnable; resumes into the state it was paused from.
+    Paused(PauseReason),
     Finished,
     Cancelled,
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PauseReason {
+    /// Memory was offloaded (e.g. swapped to host) and must be restored first.
+    Offloaded,
+    /// The backend needs external input (e.g. a tool result) before it can continue.
+    AwaitingInput,
+    /// An external controller asked for the pause (see [`DriverCommand::Pause`]).
+    Requested,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StepOutcome {
     Advanced,
+    /// Deferred by policy (arbiter or driver law); the backend was not asked.
     Yielded,
     Finished,
+    /// The backend ran but could not make progress, e.g. waiting on memory. May
+    /// carry a [`STEP_STALLED`] receipt with a backend-defined cause code.
+    Stalled,
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum StopReason {
+    /// Output reached `max_new_tokens`.
     MaxTokens,
     Eos,
     Cancelled,
     BackendError,
+    /// The frame's `deadline_ticks` elapsed.
+    DeadlineExceeded,
+    /// The frame spent its `max_cost` budget.
+    CostBudgetExhausted,
+    /// Prompt plus output reached `max_total_tokens`.
+    MaxTotalTokens,
+    /// Output ended with the stop sequence at this index.
+    StopSequence(u32),
+    /// A caller-defined [`stop::StopCondition`] fired with this code.
+    Custom(u32),
+    /// The backend emitted this id, which is outside `vocab_size`.
+    TokenOutOfVocab(u32),
+    /// The frame outlived its `max_age_rounds` in a group.
+    Expired,
 }
 
+impl StopReason {
+    /// Stable `(code, payload)` encoding used by the crate's wire formats.
+    pub(crate) fn to_parts(self) -> (u8, u32) {
+        match self {
+            StopReason::MaxTokens => (0, 0),
+            StopReason::Eos => (1, 0),
+            StopReason::Cancelled => (2, 0),
+            StopReason::BackendError => (3, 0),
+            StopReason::DeadlineExceeded => (4, 0),
+            StopReason::CostBudgetExhausted => (5, 0),
+            StopReason::StopSequence(i) => (6, i),
+            StopReason::Custom(code) => (7, code),
+            StopReason::MaxTotalTokens => (8, 0),
+            StopReason::TokenOutOfVocab(t) => (9, t),
+            StopReason::Expired => (10, 0),
+        }
+    }
+
+    pub(crate) fn from_parts(code: u8, payload: u32) -> Option<Self> {
+        match code {
+            0 => Some(StopReason::MaxTokens),
+            1 => Some(StopReason::Eos),
+            2 => Some(StopReason::Cancelled),
+            3 => Some(StopReason::BackendError),
+            4 => Some(StopReason::DeadlineExceeded),
+            5 => Some(StopReason::CostBudgetExhausted),
+            6 => Some(StopReason::StopSequence(payload)),
+            7 => Some(StopReason::Custom(payload)),
+            8 => Some(StopReason::MaxTotalTokens),
+            9 => Some(StopReason::TokenOutOfVocab(payload)),
+            10 => Some(StopReason::Expired),
+            _ => None,
+        }
+    }
+}
+
+/// Receipt kind for backend-reported step cost.
+///
+/// Steps are not equal: a 512-token prefill chunk costs far more than one decode
+/// step. Backends report a step's price in cost units with `step.cost` receipts; a
+/// backend step that reports nothing is charged one unit, and the driver adds that
+/// receipt to its envelope so consumers see every charge. Budgets
+/// ([`FrameLimits::max_cost`]) are expressed in the same units.
+pub const STEP_COST: &str = "step.cost";
+
+/// Receipt kind giving the cause of a [`StepOutcome::Stalled`] step, as a
+/// backend-defined code.
+pub const STEP_STALLED: &str = "step.stalled";
+
+/// Receipt kind carrying the frame's [`Frame::run_id`], stamped first on every step
+/// by drivers built [`with_run_id_receipts`](Driver::with_run_id_receipts).
+pub const RUN_ID: &str = "run.id";
+
 #[derive(Debug, Clone)]
 pub struct Receipt {
     pub kind: &'static str,
     pub value_u64: u64,
 }
 
+impl Receipt {
+    pub fn new(kind: &'static str, value_u64: u64) -> Self {
+        Self { kind, value_u64 }
+    }
+
+    /// `mem.blocks_alloc`: blocks allocated to the frame during this step.
+    pub fn mem_blocks_alloc(blocks: u64) -> Self {
+        Self::new(mem::MEM_BLOCKS_ALLOC, blocks)
+    }
+
+    /// `mem.blocks_free`: blocks released by the frame during this step.
+    pub fn mem_blocks_free(blocks: u64) -> Self {
+        Self::new(mem::MEM_BLOCKS_FREE, blocks)
+    }
+
+    /// `mem.bytes_resident`: bytes held by the frame after this step (a gauge, not a delta).
+    pub fn mem_bytes_resident(bytes: u64) -> Self {
+        Self::new(mem::MEM_BYTES_RESIDENT, bytes)
+    }
+
+    /// `prefill.tokens`: prompt tokens consumed by this step.
+    pub fn prefill_tokens(tokens: u64) -> Self {
+        Self::new(billing::PREFILL_TOKENS, tokens)
+    }
+
+    /// `prefill.reused`: prompt tokens served from a cached prefix.
+    pub fn prefill_reused(tokens: u64) -> Self {
+        Self::new(billing::PREFILL_REUSED, tokens)
+    }
+
+    /// `compute.flops_est`: estimated floating-point operations for this step.
+    pub fn compute_flops_est(flops: u64) -> Self {
+        Self::new(compute::COMPUTE_FLOPS_EST, flops)
+    }
+
+    /// `compute.energy_mj_est`: estimated energy for this step, in millijoules.
+    pub fn compute_energy_mj_est(millijoules: u64) -> Self {
+        Self::new(compute::COMPUTE_ENERGY_MJ_EST, millijoules)
+    }
+
+    /// `step.cost`: cost units consumed by this step. See [`StepResult::reported_cost`].
+    pub fn step_cost(units: u64) -> Self {
+        Self::new(STEP_COST, units)
+    }
+}
+
+/// Candidate continuations proposed in one step by a multi-head (Medusa-style) backend.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Proposal {
+    /// One token sequence per head / branch.
+    pub candidates: Vec<Vec<u32>>,
+    /// Index into `candidates` of the sequence that was committed, if any.
+    pub committed: Option<usize>,
+    /// Number of leading tokens of the committed candidate that were accepted.
+    pub accepted_len: usize,
+}
+
+impl Proposal {
+    /// Check that `committed` names a candidate and `accepted_len` fits inside it.
+    pub fn validate(&self) -> Result<(), String> {
+        let Some(i) = self.committed else {
+            return match self.accepted_len {
+                0 => Ok(()),
+                _ => Err("proposal: accepted_len without a committed candidate".to_string()),
+            };
+        };
+        let candidate = self
+            .candidates
+            .get(i)
+            .ok_or_else(|| format!("proposal: committed index {} out of range", i))?;
+        if self.accepted_len > candidate.len() {
+            return Err(format!(
+                "proposal: accepted_len {} exceeds candidate length {}",
+                self.accepted_len,
+                candidate.len()
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// Receipt kind for a step whose [`Proposal`] failed [`Proposal::validate`]; value is
+/// the number of output tokens the step had committed, which were discarded.
+pub const SPEC_MALFORMED: &str = "spec.malformed";
+
+/// Receipt kind for draft tokens verified in a speculative step: the length of the
+/// longest candidate in the step's [`Proposal`].
+pub const SPEC_PROPOSED: &str = "spec.proposed";
+
+/// Receipt kind for draft tokens accepted in a speculative step.
+pub const SPEC_ACCEPTED: &str = "spec.accepted";
+
+/// Running acceptance counters kept by the driver across steps that carried a [`Proposal`].
+#[derive(Debug, Clone, Default)]
+pub struct ProposalStats {
+    pub steps: u64,
+    pub candidates: u64,
+    pub commits: u64,
+    pub tokens_accepted: u64,
+    /// Draft tokens put up for verification (see [`SPEC_PROPOSED`]).
+    pub tokens_proposed: u64,
+}
+
+impl ProposalStats {
+    /// Accepted over proposed draft tokens; `None` before anything was proposed.
+    pub fn acceptance_rate(&self) -> Option<f64> {
+        if self.tokens_proposed == 0 {
+            return None;
+        }
+        Some(self.tokens_accepted as f64 / self.tokens_proposed as f64)
+    }
+}
+
+/// A backend's prediction of the next step, from [`FrameStepper::peek`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StepPreview {
+    pub outcome: StepOutcome,
+    pub token: Option<u32>,
+    pub stop_reason: Option<StopReason>,
+}
+
+impl StepPreview {
+    /// The next step would emit `token`.
+    pub fn token(token: u32) -> Self {
+        Self {
+            outcome: StepOutcome::Advanced,
+            token: Some(token),
+            stop_reason: None,
+        }
+    }
+
+    /// Whether `r` is the step this preview predicted.
+    pub fn matches(&self, r: &StepResult) -> bool {
+        self.outcome == r.outcome
+            && self.token == r.emitted_token
+            && self.stop_reason == r.stop_reason
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct StepResult {
     pub outcome: StepOutcome,
     pub emitted_token: Option<u32>,
     pub stop_reason: Option<StopReason>,
     pub receipts: Vec<Receipt>,
+    pub proposal: Option<Proposal>,
+    /// Tokens this step appended to the output, `emitted_token` first. Stamped by the
+    /// driver from the output log; backend-built envelopes count `emitted_token` only.
+    pub tokens_committed: u32,
+    /// Frame state once the step is done. Stamped by the driver; backend-built
+    /// envelopes carry `Finished` for finished steps and `Decode` otherwise.
+    pub state_after: FrameState,
+    /// Zero-based index of this envelope among those the driver has returned for the
+    /// frame. Stamped by the driver; `0` until then.
+    pub step_index: u64,
 }
 
 impl StepResult {
@@ -53,6 +379,10 @@ impl StepResult {
             emitted_token: token,
             stop_reason: None,
             receipts: Vec::new(),
+            proposal: None,
+            tokens_committed: token.is_some() as u32,
+            state_after: FrameState::Decode,
+            step_index: 0,
         }
     }
     pub fn finished(reason: StopReason) -> Self {
@@ -61,27 +391,338 @@ impl StepResult {
             emitted_token: None,
             stop_reason: Some(reason),
             receipts: Vec::new(),
+            proposal: None,
+            tokens_committed: 0,
+            state_after: FrameState::Finished,
+            step_index: 0,
+        }
+    }
+    /// A step that made no progress because it was deferred (e.g. by policy).
+    pub fn yielded() -> Self {
+        Self {
+            outcome: StepOutcome::Yielded,
+            emitted_token: None,
+            stop_reason: None,
+            receipts: Vec::new(),
+            proposal: None,
+            tokens_committed: 0,
+            state_after: FrameState::Decode,
+            step_index: 0,
+        }
+    }
+    /// A step the backend could not make progress on; see [`StepOutcome::Stalled`].
+    pub fn stalled() -> Self {
+        Self {
+            outcome: StepOutcome::Stalled,
+            ..Self::yielded()
+        }
+    }
+    /// A step refused by policy; the frame ends with `reason`.
+    pub fn refused(reason: StopReason) -> Self {
+        Self::finished(reason)
+    }
+    /// Set the emitted token. Envelopes carry at most one committed token; multi-token
+    /// steps describe the rest with a [`Proposal`].
+    pub fn with_token(mut self, token: u32) -> Self {
+        self.emitted_token = Some(token);
+        self.tokens_committed = self.tokens_committed.max(1);
+        self
+    }
+    pub fn with_receipt(mut self, kind: &'static str, value_u64: u64) -> Self {
+        self.receipts.push(Receipt::new(kind, value_u64));
+        self
+    }
+    pub fn with_proposal(mut self, proposal: Proposal) -> Self {
+        self.proposal = Some(proposal);
+        self
+    }
+    /// Report this step's cost in cost units (a `step.cost` receipt).
+    pub fn with_cost(self, units: u64) -> Self {
+        self.with_receipt(STEP_COST, units)
+    }
+    /// Sum of the step's `step.cost` receipts, or `None` if it reported no cost.
+    pub fn reported_cost(&self) -> Option<u64> {
+        self.receipts
+            .iter()
+            .filter(|r| r.kind == STEP_COST)
+            .map(|r| r.value_u64)
+            .reduce(u64::saturating_add)
+    }
+    /// The [`StepResult::tokens_committed`] tokens, read from the tail of the frame's
+    /// `output` log, or `emitted_token` alone for a single-token step.
+    pub fn committed_tokens(&self, output: &TokenLog) -> Vec<u32> {
+        match (self.tokens_committed, self.emitted_token) {
+            (1, Some(tok)) => vec![tok],
+            (n, _) => output.suffix(n as usize).collect(),
         }
     }
 }
 
-#[derive(Debug, Clone)]
+/// One-line summary, e.g. `Advanced tok=17 receipts=2` or `Finished(MaxTokens)`.
+impl fmt::Display for StepResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.outcome, self.stop_reason) {
+            (StepOutcome::Finished, Some(reason)) => write!(f, "Finished({:?})", reason)?,
+            (outcome, _) => write!(f, "{:?}", outcome)?,
+        }
+        if let Some(tok) = self.emitted_token {
+            write!(f, " tok={}", tok)?;
+        }
+        if let Some(p) = &self.proposal {
+            write!(f, " proposal={}/{}", p.accepted_len, p.candidates.len())?;
+        }
+        if !self.receipts.is_empty() {
+            write!(f, " receipts={}", self.receipts.len())?;
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
 pub struct FrameCursor {
     pub position: u32,
 }
 
-impl Default for FrameCursor {
-    fn default() -> Self {
-        Self { position: 0 }
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FrameLimits {
+    /// Output tokens the frame may generate, excluding the prompt. `None` generates
+    /// until something else stops the frame; see [`FrameLimits::unbounded`].
+    pub max_new_tokens: Option<usize>,
+    /// Cap on prompt plus output tokens. Once reached, the frame is finished with
+    /// [`StopReason::MaxTotalTokens`].
+    pub max_total_tokens: Option<usize>,
+    /// Ticks after the frame's first driver step at which it is finished with
+    /// [`StopReason::DeadlineExceeded`]. See [`Clock`] for what a tick is.
+    pub deadline_ticks: Option<u64>,
+    /// Budget in cost units (see [`STEP_COST`]). Once spent, the frame is finished
+    /// with [`StopReason::CostBudgetExhausted`].
+    pub max_cost: Option<u64>,
+    /// Token ids must be below this. Checked for the prompt by
+    /// [`Frame::with_vocab_size`] and for every emitted token by the driver; see
+    /// [`validate`].
+    pub vocab_size: Option<u32>,
+    /// Scheduling rounds after admission to a [`group::FrameGroup`] at which the
+    /// group finishes the frame with [`StopReason::Expired`].
+    pub max_age_rounds: Option<u64>,
+}
+
+impl FrameLimits {
+    pub fn new(max_new_tokens: usize) -> Self {
+        Self {
+            max_new_tokens: Some(max_new_tokens),
+            max_total_tokens: None,
+            deadline_ticks: None,
+            max_cost: None,
+            vocab_size: None,
+            max_age_rounds: None,
+        }
+    }
+
+    /// No output cap, for streams that run until a deadline, budget, stop condition
+    /// or external cancel ends them. [`FrameLimits::validate`] rejects these limits on
+    /// their own; [`Driver::step`] refuses them unless the driver has another way to
+    /// end the frame (see [`Driver::validate`]).
+    pub fn unbounded() -> Self {
+        Self {
+            max_new_tokens: None,
+            ..Self::new(0)
+        }
+    }
+
+    /// Whether `tokens_generated` has used up the output cap; never, when unbounded.
+    pub fn output_exhausted(&self, tokens_generated: usize) -> bool {
+        self.max_new_tokens
+            .is_some_and(|max| tokens_generated >= max)
+    }
+}
+
+/// Source of the driver's notion of time, in caller-defined ticks.
+///
+/// Ticks must never go backwards. A driver without a clock counts its own
+/// [`Driver::step`] calls instead, which keeps deadlines fully deterministic.
+pub trait Clock {
+    fn now_ticks(&self) -> u64;
+}
+
+/// A clock advanced explicitly by the caller. Clones share the same time.
+#[derive(Debug, Clone, Default)]
+pub struct ManualClock {
+    ticks: Arc<AtomicU64>,
+}
+
+impl ManualClock {
+    pub fn new(start: u64) -> Self {
+        Self {
+            ticks: Arc::new(AtomicU64::new(start)),
+        }
+    }
+
+    pub fn advance(&self, ticks: u64) {
+        self.ticks.fetch_add(ticks, Ordering::AcqRel);
+    }
+
+    pub fn set(&self, ticks: u64) {
+        self.ticks.fetch_max(ticks, Ordering::AcqRel);
+    }
+}
+
+impl Clock for ManualClock {
+    fn now_ticks(&self) -> u64 {
+        self.ticks.load(Ordering::Acquire)
     }
 }
 
+/// Latency marks recorded by the driver, in [`Driver::now_ticks`] ticks.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TimingMarks {
+    /// When the frame was handed to the driver (or the clock attached).
+    pub admitted_at: u64,
+    /// First backend step taken while the frame was in prefill.
+    pub first_prefill_at: Option<u64>,
+    /// First step that emitted a token.
+    pub first_token_at: Option<u64>,
+}
+
+impl TimingMarks {
+    /// Time to first token, measured from admission.
+    pub fn ttft(&self) -> Option<u64> {
+        Some(self.first_token_at?.saturating_sub(self.admitted_at))
+    }
+
+    /// Time spent queued before the first prefill step.
+    pub fn queue_time(&self) -> Option<u64> {
+        Some(self.first_prefill_at?.saturating_sub(self.admitted_at))
+    }
+}
+
+/// Traffic-class label attached to a frame, matched by arbiters and schedulers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Tag(pub &'static str);
+
+impl Tag {
+    pub const INTERACTIVE: Tag = Tag("interactive");
+    pub const BATCH: Tag = Tag("batch");
+    pub const EVALUATION: Tag = Tag("evaluation");
+
+    /// The built-in tag with this name, if any.
+    pub fn builtin(name: &str) -> Option<Tag> {
+        [Tag::INTERACTIVE, Tag::BATCH, Tag::EVALUATION]
+            .into_iter()
+            .find(|t| t.0 == name)
+    }
+}
+
+/// Limits shared with an external controller (e.g. a fleet-wide load shedder).
+///
+/// Clones share the same values. The driver reads them at every step and applies the
+/// minimum of these and the frame's own [`FrameLimits`]; the frame's limits are never
+/// rewritten. `max_tokens` here caps output tokens, like
+/// [`FrameLimits::max_new_tokens`].
 #[derive(Debug, Clone)]
-pub struct FrameLimits {
-    pub max_tokens: usize,
+pub struct DynamicLimits {
+    max_tokens: Arc<AtomicUsize>,
+}
+
+impl DynamicLimits {
+    pub fn new(max_tokens: usize) -> Self {
+        Self {
+            max_tokens: Arc::new(AtomicUsize::new(max_tokens)),
+        }
+    }
+
+    /// A handle that imposes no limit until tightened.
+    pub fn unlimited() -> Self {
+        Self::new(usize::MAX)
+    }
+
+    pub fn max_tokens(&self) -> usize {
+        self.max_tokens.load(Ordering::Acquire)
+    }
+
+    pub fn set_max_tokens(&self, max_tokens: usize) {
+        self.max_tokens.store(max_tokens, Ordering::Release);
+    }
+
+    /// Lower the limit to `max_tokens` if it is currently higher; never raises it.
+    pub fn tighten_max_tokens(&self, max_tokens: usize) {
+        self.max_tokens.fetch_min(max_tokens, Ordering::AcqRel);
+    }
+}
+
+impl Default for DynamicLimits {
+    fn default() -> Self {
+        Self::unlimited()
+    }
+}
+
+/// Signals from outside the frame (global load, kill switch, feature flags), updated
+/// by the caller and passed to every [`Arbiter::decide`].
+///
+/// Clones share the same values. Every setter bumps [`ArbiterContext::generation`],
+/// which lets arbiters that cache decisions notice a change.
+#[derive(Debug, Clone, Default)]
+pub struct ArbiterContext {
+    inner: Arc<ContextValues>,
+}
+
+#[derive(Debug, Default)]
+struct ContextValues {
+    load_permille: AtomicU64,
+    killed: AtomicBool,
+    flags: AtomicU64,
+    generation: AtomicU64,
+}
+
+impl ArbiterContext {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Caller-defined load figure, e.g. fleet utilization in permille.
+    pub fn load_permille(&self) -> u64 {
+        self.inner.load_permille.load(Ordering::Acquire)
+    }
+
+    pub fn set_load_permille(&self, permille: u64) {
+        self.inner.load_permille.store(permille, Ordering::Release);
+        self.bump();
+    }
+
+    /// Whether the kill switch is thrown. Arbiters decide what that means.
+    pub fn is_killed(&self) -> bool {
+        self.inner.killed.load(Ordering::Acquire)
+    }
+
+    pub fn set_killed(&self, killed: bool) {
+        self.inner.killed.store(killed, Ordering::Release);
+        self.bump();
+    }
+
+    /// Caller-defined feature flag `bit` (0..64).
+    pub fn flag(&self, bit: u32) -> bool {
+        self.inner.flags.load(Ordering::Acquire) & (1u64 << (bit % 64)) != 0
+    }
+
+    pub fn set_flag(&self, bit: u32, on: bool) {
+        let mask = 1u64 << (bit % 64);
+        if on {
+            self.inner.flags.fetch_or(mask, Ordering::AcqRel);
+        } else {
+            self.inner.flags.fetch_and(!mask, Ordering::AcqRel);
+        }
+        self.bump();
+    }
+
+    /// Number of updates made so far.
+    pub fn generation(&self) -> u64 {
+        self.inner.generation.load(Ordering::Acquire)
+    }
+
+    fn bump(&self) {
+        self.inner.generation.fetch_add(1, Ordering::AcqRel);
+    }
 }
 
-#[derive(Debug)]
 pub struct Frame<M> {
     pub state: FrameState,
     pub cursor: FrameCursor,
@@ -93,45 +734,273 @@ pub struct Frame<M> {
     pub prompt_index: usize,
 
     /// Output log (token ids). Keep in the law so tools can inspect generically.
-    pub generated_token_ids: Vec<u32>,
+    pub generated_token_ids: TokenLog,
     pub tokens_generated: usize,
+
+    /// Why the frame finished, recorded by the driver. `None` while running.
+    pub stop_reason: Option<StopReason>,
+
+    /// Backend steps executed on this frame, counted by the driver once the backend
+    /// returns (so a backend sees the count of the steps before its own).
+    pub steps_taken: u64,
+
+    /// Cost units charged by the driver for those steps, also after the backend returns.
+    pub cost_spent: u64,
+
+    /// Driver tick of the frame's first step; the origin for `deadline_ticks`.
+    pub started_at: Option<u64>,
+
+    /// Ticks the frame had already run when it was restored from a snapshot; they
+    /// count toward `deadline_ticks` on top of the time since `started_at`.
+    pub carried_ticks: u64,
+
+    /// Traffic-class tags for policy routing. Usually a handful at most.
+    pub tags: Vec<Tag>,
+
+    /// State to return to on [`Frame::resume`]. Set while paused.
+    pub paused_from: Option<FrameState>,
+
+    /// How prompt and output tokens appear in `Debug` output.
+    pub redaction: Redaction,
+
+    /// Caller-assigned correlation id, carried into views, status and snapshots and
+    /// optionally stamped on every step (see [`Driver::with_run_id_receipts`]).
+    pub run_id: Option<u64>,
+
+    /// Memory wipe hook installed by [`Frame::with_mem_zeroize`].
+    #[cfg(feature = "zeroize")]
+    mem_zeroize: Option<fn(&mut M)>,
+}
+
+impl<M: fmt::Debug> fmt::Debug for Frame<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let policy = self.redaction;
+        let generated = &self.generated_token_ids;
+        f.debug_struct("Frame")
+            .field("state", &self.state)
+            .field("cursor", &self.cursor)
+            .field("limits", &self.limits)
+            .field("mem", &self.mem)
+            .field(
+                "prompt_token_ids",
+                &redact::slice(policy, &self.prompt_token_ids),
+            )
+            .field("prompt_index", &self.prompt_index)
+            .field(
+                "generated_token_ids",
+                &redact::DebugWith(|f: &mut fmt::Formatter<'_>| {
+                    policy.fmt_tokens(f, generated.len(), generated.iter())
+                }),
+            )
+            .field("tokens_generated", &self.tokens_generated)
+            .field("stop_reason", &self.stop_reason)
+            .field("steps_taken", &self.steps_taken)
+            .field("cost_spent", &self.cost_spent)
+            .field("started_at", &self.started_at)
+            .field("carried_ticks", &self.carried_ticks)
+            .field("tags", &self.tags)
+            .field("paused_from", &self.paused_from)
+            .field("redaction", &self.redaction)
+            .field("run_id", &self.run_id)
+            .finish()
+    }
 }
 
 impl<M> Frame<M> {
-    pub fn new(mem: M, max_tokens: usize) -> Self {
+    pub fn new(mem: M, max_new_tokens: usize) -> Self {
         Self {
             state: FrameState::Prefill,
             cursor: FrameCursor::default(),
-            limits: FrameLimits { max_tokens },
+            limits: FrameLimits::new(max_new_tokens),
             mem,
             prompt_token_ids: Vec::new(),
             prompt_index: 0,
-            generated_token_ids: Vec::new(),
+            generated_token_ids: TokenLog::new(),
             tokens_generated: 0,
+            stop_reason: None,
+            steps_taken: 0,
+            cost_spent: 0,
+            started_at: None,
+            carried_ticks: 0,
+            tags: Vec::new(),
+            paused_from: None,
+            redaction: Redaction::Full,
+            run_id: None,
+            #[cfg(feature = "zeroize")]
+            mem_zeroize: None,
         }
     }
 
     pub fn cancel(&mut self) {
         self.state = FrameState::Cancelled;
     }
-    
-    pub fn with_prompt(mem: M, max_tokens: usize, prompt_token_ids: Vec<u32>) -> Self {
+
+    /// Pause a running (prefill/decode) frame. Returns `false` if the frame is not running.
+    pub fn pause(&mut self, reason: PauseReason) -> bool {
+        match self.state {
+            FrameState::Prefill | FrameState::Decode => {
+                self.paused_from = Some(self.state);
+                self.state = FrameState::Paused(reason);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Return a paused frame to the state it was paused from. Returns `false` if not paused.
+    pub fn resume(&mut self) -> bool {
+        match (self.state, self.paused_from.take()) {
+            (FrameState::Paused(_), Some(prev)) => {
+                self.state = prev;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    pub fn progress(&self) -> FrameProgress {
+        let max_new_tokens = self.limits.max_new_tokens;
+        let prompt_len = self.prompt_token_ids.len();
+        let mut remaining_tokens = max_new_tokens.map(|m| m.saturating_sub(self.tokens_generated));
+        if let Some(total) = self.limits.max_total_tokens {
+            let left = total.saturating_sub(prompt_len + self.tokens_generated);
+            remaining_tokens = Some(remaining_tokens.map_or(left, |r| r.min(left)));
+        }
+        FrameProgress {
+            prompt_consumed: self.prompt_index.min(prompt_len),
+            prompt_len,
+            tokens_generated: self.tokens_generated,
+            max_new_tokens,
+            max_total_tokens: self.limits.max_total_tokens,
+            remaining_tokens,
+            steps_taken: self.steps_taken,
+            cost_spent: self.cost_spent,
+        }
+    }
+
+    /// Add `tag` to the frame (no-op if already present).
+    pub fn with_tag(mut self, tag: Tag) -> Self {
+        self.add_tag(tag);
+        self
+    }
+
+    pub fn add_tag(&mut self, tag: Tag) {
+        if !self.has_tag(tag) {
+            self.tags.push(tag);
+        }
+    }
+
+    pub fn has_tag(&self, tag: Tag) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    /// Set how this frame's tokens appear in `Debug` output.
+    pub fn with_redaction(mut self, redaction: Redaction) -> Self {
+        self.redaction = redaction;
+        self
+    }
+
+    /// Tag this frame with a correlation id for joining its envelopes downstream.
+    pub fn with_run_id(mut self, run_id: u64) -> Self {
+        self.run_id = Some(run_id);
+        self
+    }
+
+    pub fn with_prompt(mem: M, max_new_tokens: usize, prompt_token_ids: Vec<u32>) -> Self {
         Self {
             state: FrameState::Prefill,
             cursor: FrameCursor::default(),
-            limits: FrameLimits { max_tokens },
+            limits: FrameLimits::new(max_new_tokens),
             mem,
             prompt_token_ids,
             prompt_index: 0,
-            generated_token_ids: Vec::new(),
+            generated_token_ids: TokenLog::new(),
             tokens_generated: 0,
+            stop_reason: None,
+            steps_taken: 0,
+            cost_spent: 0,
+            started_at: None,
+            carried_ticks: 0,
+            tags: Vec::new(),
+            paused_from: None,
+            redaction: Redaction::Full,
+            run_id: None,
+            #[cfg(feature = "zeroize")]
+            mem_zeroize: None,
         }
     }
 }
 
+/// Progress numbers for a frame, as returned by [`Frame::progress`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameProgress {
+    pub prompt_consumed: usize,
+    pub prompt_len: usize,
+    pub tokens_generated: usize,
+    pub max_new_tokens: Option<usize>,
+    pub max_total_tokens: Option<usize>,
+    /// Output tokens still allowed by the frame's limits; `None` when unbounded.
+    pub remaining_tokens: Option<usize>,
+    pub steps_taken: u64,
+    pub cost_spent: u64,
+}
+
+impl FrameProgress {
+    /// Fraction of the prompt consumed by prefill; 1.0 for an empty prompt.
+    pub fn prefill_fraction(&self) -> f64 {
+        if self.prompt_len == 0 {
+            return 1.0;
+        }
+        self.prompt_consumed as f64 / self.prompt_len as f64
+    }
+
+    /// Fraction of the output budget used; 1.0 for a zero budget, 0.0 when unbounded.
+    pub fn generation_fraction(&self) -> f64 {
+        match self.max_new_tokens {
+            None => 0.0,
+            Some(0) => 1.0,
+            Some(max) => (self.tokens_generated as f64 / max as f64).min(1.0),
+        }
+    }
+}
+
+/// One-line summary without token ids, e.g. `Decode pos=42 prompt=12/12 gen=17/256`.
+impl<M> fmt::Display for Frame<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{:?} pos={} prompt={}/{} gen={}",
+            self.state,
+            self.cursor.position,
+            self.prompt_index,
+            self.prompt_token_ids.len(),
+            self.tokens_generated,
+        )?;
+        if let Some(max) = self.limits.max_new_tokens {
+            write!(f, "/{}", max)?;
+        }
+        if let Some(total) = self.limits.max_total_tokens {
+            write!(f, " total<={}", total)?;
+        }
+        if let Some(reason) = self.stop_reason {
+            write!(f, " stop={:?}", reason)?;
+        }
+        Ok(())
+    }
+}
+
 /// Policy oracle. Must never execute. Called once per driver step.
+///
+/// Arbiters see the frame only through a [`FrameView`]; `M` names the memory type of
+/// the frames an arbiter is used with but is never exposed to it.
 pub trait Arbiter<M> {
-    fn decide(&mut self, frame: &Frame<M>) -> Decision;
+    fn decide(&mut self, frame: &FrameView<'_>, ctx: &ArbiterContext) -> Decision;
+
+    /// Move receipts explaining the last decision into `out`.
+    ///
+    /// Called by the driver right after [`Arbiter::decide`]; the receipts lead the
+    /// resulting step envelope.
+    fn drain_receipts(&mut self, _out: &mut Vec<Receipt>) {}
 }
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -139,20 +1008,135 @@ pub enum Decision {
     Allow,
     Yield,
     Refuse,
+    /// End the frame as finished with this reason, without stepping the backend.
+    Stop(StopReason),
+}
+
+impl Decision {
+    /// Stable code used in receipts: 0 allow, 1 yield, 2 refuse, 3 stop.
+    pub fn code(self) -> u64 {
+        match self {
+            Decision::Allow => 0,
+            Decision::Yield => 1,
+            Decision::Refuse => 2,
+            Decision::Stop(_) => 3,
+        }
+    }
+}
+
+/// Receipt kind recording the arbiter's decision ([`Decision::code`]) on every step
+/// decided by a driver built [`with_decision_audit`](Driver::with_decision_audit).
+pub const ARBITER_DECISION: &str = "arbiter.decision";
+
+/// Counts of arbiter decisions taken by a driver.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecisionStats {
+    pub allowed: u64,
+    pub yielded: u64,
+    pub refused: u64,
+    pub stopped: u64,
+}
+
+impl DecisionStats {
+    pub fn record(&mut self, decision: Decision) {
+        match decision {
+            Decision::Allow => self.allowed += 1,
+            Decision::Yield => self.yielded += 1,
+            Decision::Refuse => self.refused += 1,
+            Decision::Stop(_) => self.stopped += 1,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.allowed + self.yielded + self.refused + self.stopped
+    }
+
+    /// Refused over all decisions; `None` before the first decision.
+    pub fn refusal_rate(&self) -> Option<f64> {
+        match self.total() {
+            0 => None,
+            n => Some(self.refused as f64 / n as f64),
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone, Copy)]
 pub struct NoArbiter;
 
 impl<M> Arbiter<M> for NoArbiter {
-    fn decide(&mut self, _frame: &Frame<M>) -> Decision {
+    fn decide(&mut self, _frame: &FrameView<'_>, _ctx: &ArbiterContext) -> Decision {
         Decision::Allow
     }
 }
 
+/// Routes each decision to `tagged` when the frame carries `tag`, otherwise to `untagged`.
+#[derive(Debug, Clone)]
+pub struct TagRouter<T, U> {
+    pub tag: Tag,
+    pub tagged: T,
+    pub untagged: U,
+}
+
+impl<T, U> TagRouter<T, U> {
+    pub fn new(tag: Tag, tagged: T, untagged: U) -> Self {
+        Self {
+            tag,
+            tagged,
+            untagged,
+        }
+    }
+}
+
+impl<M, T, U> Arbiter<M> for TagRouter<T, U>
+where
+    T: Arbiter<M>,
+    U: Arbiter<M>,
+{
+    fn decide(&mut self, frame: &FrameView<'_>, ctx: &ArbiterContext) -> Decision {
+        if frame.has_tag(self.tag) {
+            self.tagged.decide(frame, ctx)
+        } else {
+            self.untagged.decide(frame, ctx)
+        }
+    }
+
+    fn drain_receipts(&mut self, out: &mut Vec<Receipt>) {
+        self.tagged.drain_receipts(out);
+        self.untagged.drain_receipts(out);
+    }
+}
+
 /// Backend stepper: does exactly one bounded semantic step.
 pub trait FrameStepper<M> {
     fn step(&mut self, frame: &mut Frame<M>) -> Result<StepResult, String>;
+
+    /// [`FrameStepper::step`] with a [`Progress`] reporter for heartbeats during long
+    /// steps. The driver always calls this; the default ignores `progress`.
+    fn step_with(
+        &mut self,
+        frame: &mut Frame<M>,
+        progress: &mut Progress<'_>,
+    ) -> Result<StepResult, String> {
+        let _ = progress;
+        self.step(frame)
+    }
+
+    /// Predict the next step without mutating the frame or its memory, for lookahead
+    /// by constraint checkers and UIs. `None` means the backend cannot tell cheaply.
+    fn peek(&mut self, _frame: &Frame<M>) -> Option<StepPreview> {
+        None
+    }
+
+    /// Export backend-private per-frame state so another stepper can take over the
+    /// frame (see [`Driver::replace_stepper`]). `None` means there is nothing to hand off.
+    fn export_state(&mut self, _frame: &Frame<M>) -> Option<Vec<u8>> {
+        None
+    }
+
+    /// Adopt state exported by the stepper this one is replacing.
+    fn import_state(&mut self, _frame: &mut Frame<M>, _state: Vec<u8>) -> Result<(), String> {
+        Err("import_state: not supported by this stepper".to_string())
+    }
 }
 
 /// Driver owns the loop (scheduling). Backend owns one-step execution.
@@ -164,6 +1148,88 @@ where
     pub frame: Frame<M>,
     pub stepper: S,
     pub arbiter: A,
+
+    /// Receipts produced outside a step (e.g. limit changes), attached to the next envelope.
+    pub pending_receipts: Vec<Receipt>,
+
+    /// Externally controlled limits, reconciled against the frame's limits each step.
+    pub dynamic_limits: Option<DynamicLimits>,
+
+    /// Acceptance counters over all proposals seen so far.
+    pub proposal_stats: ProposalStats,
+
+    /// Arbiter decisions taken for this frame.
+    pub decision_stats: DecisionStats,
+
+    /// External signals handed to the arbiter on every decision.
+    pub context: ArbiterContext,
+
+    /// Commands applied at the top of the next [`Driver::step`].
+    pub commands: command::CommandQueue,
+
+    /// Snapshot taken by the latest [`DriverCommand::Checkpoint`].
+    pub last_checkpoint: Option<FrameSnapshot>,
+
+    /// Control-plane handle, created on first [`Driver::handle`] call.
+    handle: Option<DriverHandle>,
+
+    /// Consumer of emitted tokens; see [`sink`].
+    pub sink: Option<Box<dyn TokenSink + Send + Sync>>,
+
+    /// Consumer of every returned envelope's receipts; see [`sink`].
+    pub receipt_sink: Option<Box<dyn ReceiptSink + Send + Sync>>,
+
+    /// Drop receipts from returned envelopes after `receipt_sink` has them.
+    pub strip_receipts: bool,
+
+    /// Tokens the sink has not accepted yet.
+    sink_backlog: sink::SinkBacklog,
+
+    /// When emitted tokens are handed to the sink.
+    pub flush_policy: FlushPolicy,
+
+    /// Emitted tokens held back by `flush_policy`.
+    emit_buffer: Vec<u32>,
+
+    /// Set once the finished frame's final emissions were released, so later
+    /// envelopes do not report [`sink::SINK_DROPPED`] again; cleared by a running step.
+    final_released: bool,
+
+    /// Trailing tokens that may split a character; `None` when not tracked.
+    pub holdback: Option<Utf8HoldBack>,
+
+    /// Token credits left under flow control; `None` when off.
+    flow_credits: Option<u64>,
+
+    /// Memory totals folded from the `mem.*` receipts of every step.
+    pub mem_accounting: MemAccounting,
+
+    /// Time source for deadlines. `None` means ticks are counted driver steps.
+    pub clock: Option<Box<dyn Clock + Send + Sync>>,
+
+    /// Number of [`Driver::step`] calls made on a live frame.
+    pub ticks: u64,
+
+    /// Envelopes returned so far; the next one's [`StepResult::step_index`].
+    envelopes: u64,
+
+    /// Admission, first-prefill and first-token ticks for this frame.
+    pub timing: TimingMarks,
+
+    /// Checksum updated with every token the driver sees committed.
+    pub token_hasher: Option<Box<dyn TokenHasher + Send + Sync>>,
+
+    /// Checked after every step that advanced the frame; finishes it when it fires.
+    pub stop_condition: Option<stop::BoxedStopCondition<M>>,
+
+    /// Lead every step envelope with a [`RUN_ID`] receipt when the frame has a run id.
+    pub stamp_run_id: bool,
+
+    /// Record every arbiter decision as an [`ARBITER_DECISION`] receipt.
+    pub audit_decisions: bool,
+
+    /// Live hook for backend heartbeats; see [`heartbeat`].
+    heartbeat_hook: Option<heartbeat::HeartbeatHook>,
 }
 
 impl<M, S> Driver<M, S, NoArbiter>
@@ -171,11 +1237,7 @@ where
     S: FrameStepper<M>,
 {
     pub fn new(frame: Frame<M>, stepper: S) -> Self {
-        Self {
-            frame,
-            stepper,
-            arbiter: NoArbiter,
-        }
+        Self::with_arbiter(frame, stepper, NoArbiter)
     }
 }
 
@@ -185,40 +1247,526 @@ where
     A: Arbiter<M>,
 {
     pub fn with_arbiter(frame: Frame<M>, stepper: S, arbiter: A) -> Self {
-        Self { frame, stepper, arbiter }
+        let pending_receipts = validate::reused_prefix_receipt(&frame)
+            .into_iter()
+            .collect();
+        Self {
+            frame,
+            stepper,
+            arbiter,
+            pending_receipts,
+            dynamic_limits: None,
+            proposal_stats: ProposalStats::default(),
+            decision_stats: DecisionStats::default(),
+            context: ArbiterContext::default(),
+            commands: command::CommandQueue::new(),
+            last_checkpoint: None,
+            handle: None,
+            sink: None,
+            receipt_sink: None,
+            strip_receipts: false,
+            sink_backlog: sink::SinkBacklog::new(),
+            flush_policy: FlushPolicy::Immediate,
+            emit_buffer: Vec::new(),
+            final_released: false,
+            holdback: None,
+            flow_credits: None,
+            mem_accounting: MemAccounting::default(),
+            clock: None,
+            ticks: 0,
+            envelopes: 0,
+            timing: TimingMarks::default(),
+            token_hasher: None,
+            stop_condition: None,
+            stamp_run_id: false,
+            audit_decisions: false,
+            heartbeat_hook: None,
+        }
+    }
+
+    /// Stamp the frame's run id as the first receipt of every step envelope.
+    pub fn with_run_id_receipts(mut self) -> Self {
+        self.stamp_run_id = true;
+        self
+    }
+
+    /// Record every arbiter decision, not just yields, in the step's receipts so the
+    /// policy path of a run can be rebuilt from its trace.
+    pub fn with_decision_audit(mut self) -> Self {
+        self.audit_decisions = true;
+        self
+    }
+
+    /// Hash every token emitted from now on with `hasher`.
+    pub fn with_token_hasher(mut self, hasher: impl TokenHasher + Send + Sync + 'static) -> Self {
+        self.token_hasher = Some(Box::new(hasher));
+        self
+    }
+
+    /// Finish the frame when `condition` fires after a step.
+    pub fn with_stop_condition(
+        mut self,
+        condition: impl StopCondition<M> + Send + Sync + 'static,
+    ) -> Self {
+        self.stop_condition = Some(Box::new(condition));
+        self
+    }
+
+    /// Digest of the tokens emitted so far, if a hasher is attached.
+    pub fn token_digest(&self) -> Option<u64> {
+        self.token_hasher.as_ref().map(|h| h.digest())
+    }
+
+    /// Measure time with `clock`; admission is re-marked at the clock's current reading.
+    pub fn with_clock(mut self, clock: impl Clock + Send + Sync + 'static) -> Self {
+        self.timing.admitted_at = clock.now_ticks();
+        self.clock = Some(Box::new(clock));
+        self
+    }
+
+    /// Current time in ticks: the clock's reading, or the driver's step count.
+    pub fn now_ticks(&self) -> u64 {
+        match &self.clock {
+            Some(c) => c.now_ticks(),
+            None => self.ticks,
+        }
+    }
+
+    /// Ticks left before the frame's deadline, or `None` if it has no deadline.
+    pub fn remaining_ticks(&self) -> Option<u64> {
+        let deadline = self.frame.limits.deadline_ticks?;
+        Some(deadline.saturating_sub(self.elapsed_ticks()))
+    }
+
+    /// Ticks the frame has run, including those carried over from a snapshot.
+    pub fn elapsed_ticks(&self) -> u64 {
+        let since_start = match self.frame.started_at {
+            Some(start) => self.now_ticks().saturating_sub(start),
+            None => 0,
+        };
+        self.frame.carried_ticks.saturating_add(since_start)
+    }
+
+    /// [`Frame::snapshot`] with the frame's elapsed ticks as of now, so a frame
+    /// restored from it resumes its deadline instead of starting a new one.
+    pub fn snapshot(&self) -> FrameSnapshot {
+        let mut snapshot = self.frame.snapshot();
+        snapshot.elapsed_ticks = self.elapsed_ticks();
+        snapshot
+    }
+
+    /// Attach a shared [`ArbiterContext`] handle.
+    pub fn with_context(mut self, context: ArbiterContext) -> Self {
+        self.context = context;
+        self
+    }
+
+    /// Attach a shared [`DynamicLimits`] handle.
+    pub fn with_dynamic_limits(mut self, limits: DynamicLimits) -> Self {
+        self.dynamic_limits = Some(limits);
+        self
+    }
+
+    /// Output budget in force right now: the minimum of the frame and dynamic limits.
+    /// `None` when neither caps the output.
+    pub fn effective_max_tokens(&self) -> Option<usize> {
+        let frame = self.frame.limits.max_new_tokens;
+        match &self.dynamic_limits {
+            Some(d) => Some(frame.map_or(d.max_tokens(), |m| m.min(d.max_tokens()))),
+            None => frame,
+        }
     }
 
     pub fn step(&mut self) -> Result<StepResult, String> {
+        self.pull_handle_commands();
+        self.drain_commands();
+        if matches!(self.frame.state, FrameState::Prefill | FrameState::Decode) {
+            self.validate().map_err(|e| e.to_string())?;
+        }
+        let mut r = self.step_frame();
+        if let Ok(r) = &mut r {
+            self.forward_receipts(r);
+        }
+        self.publish_status();
+        r
+    }
+
+    /// The stepper's [`FrameStepper::peek`] at the next step; `None` unless the frame
+    /// is in prefill or decode.
+    pub fn peek(&mut self) -> Option<StepPreview> {
         match self.frame.state {
-            FrameState::Finished => return Ok(StepResult::finished(StopReason::MaxTokens)),
-            FrameState::Cancelled => return Ok(StepResult::finished(StopReason::Cancelled)),
+            FrameState::Prefill | FrameState::Decode => self.stepper.peek(&self.frame),
+            _ => None,
+        }
+    }
+
+    fn step_frame(&mut self) -> Result<StepResult, String> {
+        match self.frame.state {
+            FrameState::Finished => {
+                let reason = self.frame.stop_reason.unwrap_or(StopReason::MaxTokens);
+                let mut r = StepResult::finished(reason);
+                self.release_final(&mut r);
+                return Ok(self.seal(r));
+            }
+            FrameState::Cancelled => {
+                let mut r = StepResult::finished(StopReason::Cancelled);
+                self.release_final(&mut r);
+                return Ok(self.seal(r));
+            }
             _ => {}
         }
 
-        match self.arbiter.decide(&self.frame) {
-            Decision::Allow => self.stepper.step(&mut self.frame),
-            Decision::Yield => Ok(StepResult {
-                outcome: StepOutcome::Yielded,
-                emitted_token: None,
-                stop_reason: None,
-                receipts: vec![Receipt { kind: "arbiter.yield", value_u64: 1 }],
-            }),
+        self.final_released = false;
+        self.ticks += 1;
+        if self.frame.started_at.is_none() {
+            self.frame.started_at = Some(self.now_ticks());
+        }
+        let output_before = self.frame.generated_token_ids.len();
+
+        let law = self
+            .enforce_deadline()
+            .or_else(|| self.paused_envelope())
+            .or_else(|| self.enforce_sink_backpressure())
+            .or_else(|| self.enforce_flow_control())
+            .or_else(|| self.enforce_dynamic_limits())
+            .or_else(|| self.enforce_total_tokens())
+            .or_else(|| self.enforce_cost_budget());
+        let mut r = match law {
+            Some(r) => r,
+            None => self.decide_and_step()?,
+        };
+        self.audit_proposal(output_before, &mut r);
+        self.enforce_vocab(output_before, &mut r);
+        let committed = self.committed_tokens(output_before, &r);
+        r.tokens_committed = committed.len() as u32;
+        self.check_stop_condition(&mut r);
+
+        if r.outcome == StepOutcome::Finished {
+            self.frame.stop_reason = r.stop_reason;
+        }
+        if !committed.is_empty() && self.timing.first_token_at.is_none() {
+            self.timing.first_token_at = Some(self.now_ticks());
+        }
+        if let Some(h) = &mut self.token_hasher {
+            for &tok in &committed {
+                h.update(tok);
+            }
+        }
+        if let Some(h) = &mut self.holdback {
+            h.observe(&r);
+        }
+        self.emit_to_sink(&mut r, &committed);
+        let r = self.seal(r);
+        self.mem_accounting.observe(&r);
+        Ok(r)
+    }
+
+    /// Tokens the step committed: whatever it appended to the output log past
+    /// `output_before`, or the envelope's token when the backend appended none.
+    fn committed_tokens(&self, output_before: usize, r: &StepResult) -> Vec<u32> {
+        let appended: Vec<u32> = self
+            .frame
+            .generated_token_ids
+            .since(output_before)
+            .collect();
+        match (appended.is_empty(), r.emitted_token) {
+            (true, Some(tok)) => vec![tok],
+            _ => appended,
+        }
+    }
+
+    fn decide_and_step(&mut self) -> Result<StepResult, String> {
+        let decision = self.arbiter.decide(&self.frame.view(), &self.context);
+        self.decision_stats.record(decision);
+        let mut receipts = Vec::new();
+        if self.audit_decisions {
+            receipts.push(Receipt::new(ARBITER_DECISION, decision.code()));
+        }
+        self.arbiter.drain_receipts(&mut receipts);
+
+        let mut r = match decision {
+            Decision::Allow => {
+                if self.frame.state == FrameState::Prefill && self.timing.first_prefill_at.is_none()
+                {
+                    self.timing.first_prefill_at = Some(self.now_ticks());
+                }
+                let mut progress = Progress::with_hook(self.heartbeat_hook.as_mut());
+                let stepped = self.stepper.step_with(&mut self.frame, &mut progress);
+                self.frame.steps_taken += 1;
+                let mut r = stepped?;
+                progress.attach(&mut r);
+                let cost = match r.reported_cost() {
+                    Some(cost) => cost,
+                    None => {
+                        r.receipts.push(Receipt::step_cost(1));
+                        1
+                    }
+                };
+                self.frame.cost_spent = self.frame.cost_spent.saturating_add(cost);
+                r
+            }
+            Decision::Yield => StepResult::yielded().with_receipt("arbiter.yield", 1),
             Decision::Refuse => {
                 self.frame.cancel();
-                Ok(StepResult::finished(StopReason::Cancelled))
+                StepResult::refused(StopReason::Cancelled)
+            }
+            Decision::Stop(reason) => {
+                self.frame.state = FrameState::Finished;
+                self.frame.paused_from = None;
+                self.frame.stop_reason = Some(reason);
+                StepResult::finished(reason)
             }
+        };
+        prepend_receipts(&mut r, receipts);
+        Ok(r)
+    }
+
+    /// Turn an advanced step into a finished one when the stop condition fires.
+    fn check_stop_condition(&mut self, r: &mut StepResult) {
+        if r.outcome != StepOutcome::Advanced {
+            return;
+        }
+        let Some(cond) = self.stop_condition.as_mut() else {
+            return;
+        };
+        if let Some(reason) = cond.check(&self.frame, r) {
+            self.frame.state = FrameState::Finished;
+            r.outcome = StepOutcome::Finished;
+            r.stop_reason = Some(reason);
+            r.receipts.push(Receipt::new(
+                stop::STOP_CONDITION,
+                reason.to_parts().0 as u64,
+            ));
         }
     }
 
+    /// Finish the frame once its deadline has passed (paused frames included).
+    fn enforce_deadline(&mut self) -> Option<StepResult> {
+        if self.remaining_ticks()? > 0 {
+            return None;
+        }
+        self.frame.state = FrameState::Finished;
+        self.frame.paused_from = None;
+        Some(StepResult::finished(StopReason::DeadlineExceeded))
+    }
+
+    /// Finish a running frame whose prompt plus output reached `max_total_tokens`.
+    fn enforce_total_tokens(&mut self) -> Option<StepResult> {
+        let total = self.frame.limits.max_total_tokens?;
+        if self.frame.prompt_token_ids.len() + self.frame.tokens_generated < total {
+            return None;
+        }
+        self.frame.state = FrameState::Finished;
+        Some(StepResult::finished(StopReason::MaxTotalTokens))
+    }
+
+    /// Finish a running frame that has spent its cost budget.
+    fn enforce_cost_budget(&mut self) -> Option<StepResult> {
+        if self.frame.cost_spent < self.frame.limits.max_cost? {
+            return None;
+        }
+        self.frame.state = FrameState::Finished;
+        Some(StepResult::finished(StopReason::CostBudgetExhausted))
+    }
+
+    fn paused_envelope(&self) -> Option<StepResult> {
+        match self.frame.state {
+            FrameState::Paused(_) => Some(StepResult::yielded().with_receipt("frame.paused", 1)),
+            _ => None,
+        }
+    }
+
+    /// Finish a decoding frame whose budget was cut below its output by a dynamic limit.
+    fn enforce_dynamic_limits(&mut self) -> Option<StepResult> {
+        let limit = self.dynamic_limits.as_ref()?.max_tokens();
+        if self.frame.state != FrameState::Decode
+            || self.frame.limits.max_new_tokens.is_some_and(|m| limit >= m)
+            || self.frame.tokens_generated < limit
+        {
+            return None;
+        }
+        self.frame.state = FrameState::Finished;
+        Some(
+            StepResult::finished(StopReason::MaxTokens)
+                .with_receipt("limits.dynamic", limit as u64),
+        )
+    }
+
+    /// Validate a step's proposal, fold it into [`ProposalStats`] and receipt it.
+    ///
+    /// The backend has already committed the step by the time its proposal can be
+    /// checked, so a malformed one cannot simply be rejected. Instead the step's output
+    /// is cut back to `output_before` and the frame is finished with
+    /// [`StopReason::BackendError`] and a [`SPEC_MALFORMED`] receipt; the proposal is
+    /// left on the envelope for inspection but not counted.
+    fn audit_proposal(&mut self, output_before: usize, r: &mut StepResult) {
+        let Some(p) = &r.proposal else {
+            return;
+        };
+        if p.validate().is_err() {
+            let removed = self.truncate_output(output_before);
+            self.frame.state = FrameState::Finished;
+            r.emitted_token = None;
+            r.outcome = StepOutcome::Finished;
+            r.stop_reason = Some(StopReason::BackendError);
+            r.receipts
+                .push(Receipt::new(SPEC_MALFORMED, removed as u64));
+            return;
+        }
+
+        let proposed = p.candidates.iter().map(Vec::len).max().unwrap_or(0) as u64;
+        let stats = &mut self.proposal_stats;
+        stats.steps += 1;
+        stats.candidates += p.candidates.len() as u64;
+        stats.commits += p.committed.is_some() as u64;
+        stats.tokens_accepted += p.accepted_len as u64;
+        stats.tokens_proposed += proposed;
+
+        let (candidates, accepted) = (p.candidates.len() as u64, p.accepted_len as u64);
+        r.receipts.push(Receipt {
+            kind: "proposal.candidates",
+            value_u64: candidates,
+        });
+        r.receipts.push(Receipt {
+            kind: "proposal.accepted",
+            value_u64: accepted,
+        });
+        r.receipts.push(Receipt::new(SPEC_PROPOSED, proposed));
+        r.receipts.push(Receipt::new(SPEC_ACCEPTED, accepted));
+    }
+
     pub fn run_to_completion(&mut self) -> Result<(), String> {
         loop {
             let r = self.step()?;
-            match r.outcome {
-                StepOutcome::Finished => return Ok(()),
-                _ => {}
+            if r.outcome == StepOutcome::Finished {
+                return Ok(());
             }
         }
     }
+
+    /// Hand the frame over to `stepper` between steps and return the previous one.
+    ///
+    /// State exported by the current stepper is imported into the new one first; if
+    /// the import fails the swap does not happen. The swap is recorded as a
+    /// `stepper.swap` receipt carrying the frame's step count.
+    pub fn replace_stepper(&mut self, mut stepper: S) -> Result<S, String> {
+        if let Some(state) = self.stepper.export_state(&self.frame) {
+            stepper.import_state(&mut self.frame, state)?;
+        }
+        self.pending_receipts
+            .push(Receipt::new("stepper.swap", self.frame.steps_taken));
+        Ok(std::mem::replace(&mut self.stepper, stepper))
+    }
+
+    /// Split the driver back into its components.
+    ///
+    /// Receipts still queued for the next step are dropped.
+    pub fn into_parts(self) -> (Frame<M>, S, A) {
+        (self.frame, self.stepper, self.arbiter)
+    }
+
+    /// Run the frame to completion and hand it back.
+    pub fn finish_and_take_frame(mut self) -> Result<Frame<M>, String> {
+        self.run_to_completion()?;
+        Ok(self.frame)
+    }
+
+    /// Grant `additional_tokens` more output budget to a live frame.
+    ///
+    /// Valid while decoding, or after finishing on [`StopReason::MaxTokens`]; in the
+    /// latter case the frame goes back to `Decode`. The extension is recorded as a
+    /// `limits.extend` receipt on the next step envelope.
+    ///
+    /// Only the frame's own cap is raised. When an attached [`DynamicLimits`] would
+    /// still hold the frame below the extended cap, this fails and changes nothing;
+    /// raise the dynamic limit instead.
+    pub fn extend_limit(&mut self, additional_tokens: usize) -> Result<(), String> {
+        match (self.frame.state, self.frame.stop_reason) {
+            (FrameState::Decode, _) => {}
+            (FrameState::Finished, Some(StopReason::MaxTokens)) => {}
+            (state, _) => return Err(format!("extend_limit: not allowed in state {:?}", state)),
+        }
+
+        let max_new_tokens = self
+            .frame
+            .limits
+            .max_new_tokens
+            .ok_or_else(|| "extend_limit: frame has no output cap".to_string())?
+            .checked_add(additional_tokens)
+            .ok_or_else(|| "extend_limit: max_new_tokens overflow".to_string())?;
+        if let Some(d) = &self.dynamic_limits {
+            if d.max_tokens() < max_new_tokens {
+                return Err(format!(
+                    "extend_limit: dynamic limit of {} tokens is lower",
+                    d.max_tokens()
+                ));
+            }
+        }
+
+        self.frame.limits.max_new_tokens = Some(max_new_tokens);
+        self.frame.state = FrameState::Decode;
+        self.frame.stop_reason = None;
+        self.pending_receipts.push(Receipt {
+            kind: "limits.extend",
+            value_u64: additional_tokens as u64,
+        });
+        Ok(())
+    }
+
+    /// Attach receipts queued outside of a step to the outgoing envelope.
+    fn seal(&mut self, mut r: StepResult) -> StepResult {
+        r.state_after = self.frame.state;
+        r.step_index = self.envelopes;
+        self.envelopes += 1;
+        prepend_receipts(&mut r, std::mem::take(&mut self.pending_receipts));
+        if let (true, Some(id)) = (self.stamp_run_id, self.frame.run_id) {
+            r.receipts.insert(0, Receipt::new(RUN_ID, id));
+        }
+        r
+    }
+}
+
+impl<M, S, A> Driver<M, S, A>
+where
+    M: PagedMemory,
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Release the frame's memory via [`PagedMemory::offload`] and pause it as
+    /// `Paused(Offloaded)`. Steps yield until [`Driver::restore`] is called.
+    pub fn offload(&mut self) -> Result<(), String> {
+        if !matches!(self.frame.state, FrameState::Prefill | FrameState::Decode) {
+            return Err(format!(
+                "offload: not allowed in state {:?}",
+                self.frame.state
+            ));
+        }
+        self.frame.mem.offload()?;
+        self.frame.pause(PauseReason::Offloaded);
+        self.pending_receipts.push(Receipt::new("mem.offload", 1));
+        Ok(())
+    }
+
+    /// Bring offloaded memory back via [`PagedMemory::restore`] and resume the frame.
+    pub fn restore(&mut self) -> Result<(), String> {
+        if self.frame.state != FrameState::Paused(PauseReason::Offloaded) {
+            return Err(format!(
+                "restore: frame is not offloaded ({:?})",
+                self.frame.state
+            ));
+        }
+        self.frame.mem.restore()?;
+        self.frame.resume();
+        self.pending_receipts.push(Receipt::new("mem.restore", 1));
+        Ok(())
+    }
+}
+
+fn prepend_receipts(r: &mut StepResult, mut receipts: Vec<Receipt>) {
+    if !receipts.is_empty() {
+        receipts.append(&mut r.receipts);
+        r.receipts = receipts;
+    }
 }
 
 /// A tiny noop backend (public-friendly): proves the law compiles and runs.
@@ -236,19 +1784,55 @@ impl FrameStepper<NoopMem> for NoopStepper {
                 Ok(StepResult::advanced(None))
             }
             FrameState::Decode => {
-                if frame.tokens_generated >= frame.limits.max_tokens {
+                if frame.limits.output_exhausted(frame.tokens_generated) {
                     frame.state = FrameState::Finished;
                     return Ok(StepResult::finished(StopReason::MaxTokens));
                 }
                 // “Generate” a deterministic token id (toy).
-                let tok = (frame.cursor.position % 256) as u32;
+                let tok = frame.cursor.position % 256;
                 frame.generated_token_ids.push(tok);
                 frame.tokens_generated += 1;
                 frame.cursor.position = frame.cursor.position.saturating_add(1);
                 Ok(StepResult::advanced(Some(tok)))
             }
+            FrameState::Paused(_) => Err("noop: frame is paused".to_string()),
             FrameState::Finished => Ok(StepResult::finished(StopReason::MaxTokens)),
             FrameState::Cancelled => Ok(StepResult::finished(StopReason::Cancelled)),
         }
     }
-}
\ No newline at end of file
+
+    fn peek(&mut self, frame: &Frame<NoopMem>) -> Option<StepPreview> {
+        match frame.state {
+            FrameState::Prefill => Some(StepPreview {
+                outcome: StepOutcome::Advanced,
+                token: None,
+                stop_reason: None,
+            }),
+            FrameState::Decode if frame.limits.output_exhausted(frame.tokens_generated) => {
+                Some(StepPreview {
+                    outcome: StepOutcome::Finished,
+                    token: None,
+                    stop_reason: Some(StopReason::MaxTokens),
+                })
+            }
+            FrameState::Decode => Some(StepPreview::token(frame.cursor.position % 256)),
+            _ => None,
+        }
+    }
+}
+
+// Thread-safety guarantees documented at the crate root.
+const _: () = {
+    fn send_sync<T: Send + Sync>() {}
+    fn assert_all() {
+        send_sync::<Frame<NoopMem>>();
+        send_sync::<Driver<NoopMem, NoopStepper>>();
+        send_sync::<StepResult>();
+        send_sync::<DynamicLimits>();
+        send_sync::<MemoryGauge>();
+        send_sync::<SharedDriver<NoopMem, NoopStepper>>();
+        send_sync::<TokenSender>();
+        send_sync::<TokenReceiver>();
+    }
+    let _ = assert_all;
+};
@@ -0,0 +1,150 @@
+//! Sans-IO embedding of the law.
+//!
+//! A [`FrameMachine`] is driven entirely by plain values: the host feeds
+//! [`MachineInput`]s and polls [`MachineOutput`]s. There are no callbacks, threads
+//! or timers, so the machine can sit inside any event loop (or behind an FFI
+//! boundary).
+
+use std::collections::VecDeque;
+
+use crate::validate::{FrameError, LAW_VOCAB};
+use crate::{
+    Arbiter, Driver, FrameState, FrameStepper, NoArbiter, PauseReason, Receipt, StepOutcome,
+    StopReason,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineInput {
+    /// Perform one driver step.
+    Tick,
+    /// Cancel the frame.
+    Cancel,
+    /// Append tokens (e.g. a tool result) to the prompt. A decoding or
+    /// input-awaiting frame goes back to prefill so the backend consumes them.
+    ///
+    /// Tokens outside the frame's vocabulary are refused as a whole: the machine
+    /// outputs an [`MachineOutput::Error`] and the next envelope carries a
+    /// [`LAW_VOCAB`] receipt with the first offending id.
+    ProvideTokens(Vec<u32>),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MachineOutput {
+    EmitToken(u32),
+    Yielded,
+    /// The backend made no progress this step.
+    Stalled,
+    /// The frame is paused awaiting [`MachineInput::ProvideTokens`].
+    NeedInput,
+    Finished(StopReason),
+    Error(String),
+}
+
+pub struct FrameMachine<M, S, A = NoArbiter>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    driver: Driver<M, S, A>,
+    outputs: VecDeque<MachineOutput>,
+    finished_reported: bool,
+}
+
+impl<M, S, A> FrameMachine<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    pub fn new(driver: Driver<M, S, A>) -> Self {
+        Self {
+            driver,
+            outputs: VecDeque::new(),
+            finished_reported: false,
+        }
+    }
+
+    pub fn handle_input(&mut self, input: MachineInput) {
+        match input {
+            MachineInput::Tick => self.tick(),
+            MachineInput::Cancel => {
+                if !matches!(
+                    self.driver.frame.state,
+                    FrameState::Finished | FrameState::Cancelled
+                ) {
+                    self.driver.frame.cancel();
+                    self.driver.frame.stop_reason = Some(StopReason::Cancelled);
+                }
+            }
+            MachineInput::ProvideTokens(tokens) => {
+                if let Err(e) = self.driver.frame.limits.check_prompt(&tokens) {
+                    if let FrameError::TokenOutOfVocab { token, .. } = e {
+                        let receipt = Receipt::new(LAW_VOCAB, token as u64);
+                        self.driver.pending_receipts.push(receipt);
+                    }
+                    self.outputs.push_back(MachineOutput::Error(e.to_string()));
+                    return;
+                }
+                let frame = &mut self.driver.frame;
+                match frame.state {
+                    FrameState::Finished | FrameState::Cancelled => {
+                        self.outputs
+                            .push_back(MachineOutput::Error("input after frame ended".to_string()));
+                        return;
+                    }
+                    FrameState::Paused(PauseReason::AwaitingInput) => {
+                        frame.resume();
+                        frame.state = FrameState::Prefill;
+                    }
+                    FrameState::Decode => frame.state = FrameState::Prefill,
+                    _ => {}
+                }
+                frame.prompt_token_ids.extend(tokens);
+            }
+        }
+    }
+
+    pub fn poll_output(&mut self) -> Option<MachineOutput> {
+        self.outputs.pop_front()
+    }
+
+    pub fn driver(&self) -> &Driver<M, S, A> {
+        &self.driver
+    }
+
+    pub fn into_driver(self) -> Driver<M, S, A> {
+        self.driver
+    }
+
+    fn tick(&mut self) {
+        if self.driver.frame.state == FrameState::Paused(PauseReason::AwaitingInput) {
+            self.outputs.push_back(MachineOutput::NeedInput);
+            return;
+        }
+        if self.finished_reported {
+            return;
+        }
+        let r = match self.driver.step() {
+            Ok(r) => r,
+            Err(e) => {
+                self.outputs.push_back(MachineOutput::Error(e));
+                return;
+            }
+        };
+        for tok in r.committed_tokens(&self.driver.frame.generated_token_ids) {
+            self.outputs.push_back(MachineOutput::EmitToken(tok));
+        }
+        match r.outcome {
+            StepOutcome::Advanced => {}
+            StepOutcome::Yielded => self.outputs.push_back(MachineOutput::Yielded),
+            StepOutcome::Stalled => self.outputs.push_back(MachineOutput::Stalled),
+            StepOutcome::Finished => {
+                let reason = r.stop_reason.unwrap_or(StopReason::Cancelled);
+                self.outputs.push_back(MachineOutput::Finished(reason));
+                self.finished_reported = true;
+            }
+        }
+        if self.driver.frame.state == FrameState::Paused(PauseReason::AwaitingInput) {
+            self.outputs.push_back(MachineOutput::NeedInput);
+        }
+    }
+}
@@ -0,0 +1,340 @@
+//! Memory-side contracts a frame's `M` can opt into.
+//!
+//! The law never looks inside `mem`; these traits give schedulers and tooling a
+//! backend-neutral vocabulary for the parts of memory they need to reason about.
+//!
+//! # Memory receipts
+//!
+//! Backends report memory use with these receipt kinds (see the constructors on
+//! [`Receipt`]):
+//!
+//! - `mem.blocks_alloc` — blocks allocated during the step (delta)
+//! - `mem.blocks_free` — blocks released during the step (delta)
+//! - `mem.bytes_resident` — bytes held after the step (gauge)
+//!
+//! [`MemAccounting`] folds them into running totals.
+//!
+//! # Backend-owned memory
+//!
+//! A frame need not own its memory. `Frame<&mut M>` works directly (and
+//! [`PagedMemory`] is implemented for `&mut M`), or the frame can carry a
+//! [`MemHandle`] that the stepper maps to storage it keeps itself, e.g. in a
+//! [`MemTable`].
+
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
+use crate::{Frame, Receipt, StepResult};
+
+pub const MEM_BLOCKS_ALLOC: &str = "mem.blocks_alloc";
+pub const MEM_BLOCKS_FREE: &str = "mem.blocks_free";
+pub const MEM_BYTES_RESIDENT: &str = "mem.bytes_resident";
+
+/// Identifier of one fixed-size block in a backend's paged memory.
+pub type BlockId = u32;
+
+/// Block-granular (paged) memory, e.g. a paged KV cache.
+///
+/// One implementor value belongs to one frame; the block pool it draws from may be
+/// shared with other frames behind the implementor.
+pub trait PagedMemory {
+    /// Tokens stored per block.
+    fn block_size(&self) -> usize;
+
+    /// Total blocks in the underlying pool.
+    fn total_blocks(&self) -> usize;
+
+    /// Blocks in the underlying pool not currently allocated to any frame.
+    fn free_blocks(&self) -> usize;
+
+    /// Allocate `n` blocks to this frame, appending them to its block table.
+    /// All-or-nothing: on error no blocks are taken.
+    fn allocate(&mut self, n: usize) -> Result<Vec<BlockId>, String>;
+
+    /// Return `blocks` to the pool and drop them from this frame's block table.
+    fn free(&mut self, blocks: &[BlockId]);
+
+    /// This frame's blocks, in logical token order.
+    fn block_table(&self) -> &[BlockId];
+
+    /// Blocks required to hold `tokens` tokens.
+    fn blocks_for_tokens(&self, tokens: usize) -> usize {
+        tokens.div_ceil(self.block_size().max(1))
+    }
+
+    /// Additional blocks this frame needs to hold `total_tokens` tokens.
+    fn blocks_needed(&self, total_tokens: usize) -> usize {
+        self.blocks_for_tokens(total_tokens)
+            .saturating_sub(self.block_table().len())
+    }
+
+    /// Whether growing this frame to `total_tokens` tokens fits in the free pool.
+    fn fits(&self, total_tokens: usize) -> bool {
+        self.blocks_needed(total_tokens) <= self.free_blocks()
+    }
+
+    /// Move this frame's blocks out of device memory (e.g. to host) so the pool can
+    /// reuse them. Optional; the default reports it as unsupported.
+    fn offload(&mut self) -> Result<(), String> {
+        Err("offload: not supported by this memory".to_string())
+    }
+
+    /// Undo [`PagedMemory::offload`], reinstating the block table.
+    fn restore(&mut self) -> Result<(), String> {
+        Err("restore: not supported by this memory".to_string())
+    }
+}
+
+impl<T: PagedMemory + ?Sized> PagedMemory for &mut T {
+    fn block_size(&self) -> usize {
+        (**self).block_size()
+    }
+
+    fn total_blocks(&self) -> usize {
+        (**self).total_blocks()
+    }
+
+    fn free_blocks(&self) -> usize {
+        (**self).free_blocks()
+    }
+
+    fn allocate(&mut self, n: usize) -> Result<Vec<BlockId>, String> {
+        (**self).allocate(n)
+    }
+
+    fn free(&mut self, blocks: &[BlockId]) {
+        (**self).free(blocks)
+    }
+
+    fn block_table(&self) -> &[BlockId] {
+        (**self).block_table()
+    }
+
+    fn offload(&mut self) -> Result<(), String> {
+        (**self).offload()
+    }
+
+    fn restore(&mut self) -> Result<(), String> {
+        (**self).restore()
+    }
+}
+
+/// Memory that can be duplicated in full, so the copy shares nothing mutable with
+/// the original.
+///
+/// Deliberately separate from `Clone`: a handle such as `Rc<RefCell<Kv>>` clones
+/// shallowly, which is what [`crate::FrameGroup`] wants and what a duplicate must not do.
+pub trait DeepCloneMem {
+    fn deep_clone(&self) -> Self;
+}
+
+impl DeepCloneMem for () {
+    fn deep_clone(&self) -> Self {}
+}
+
+impl<T: Clone> DeepCloneMem for Vec<T> {
+    fn deep_clone(&self) -> Self {
+        self.clone()
+    }
+}
+
+impl<M: DeepCloneMem> Frame<M> {
+    /// An independent copy of this frame, memory included, for offline inspection.
+    ///
+    /// The copy is not a fork: it carries the same run id and counters, and nothing
+    /// about the original's driver or schedule.
+    pub fn deep_clone(&self) -> Self {
+        Frame {
+            state: self.state,
+            cursor: self.cursor.clone(),
+            limits: self.limits.clone(),
+            mem: self.mem.deep_clone(),
+            prompt_token_ids: self.prompt_token_ids.clone(),
+            prompt_index: self.prompt_index,
+            generated_token_ids: self.generated_token_ids.clone(),
+            tokens_generated: self.tokens_generated,
+            stop_reason: self.stop_reason,
+            steps_taken: self.steps_taken,
+            cost_spent: self.cost_spent,
+            started_at: self.started_at,
+            carried_ticks: self.carried_ticks,
+            tags: self.tags.clone(),
+            paused_from: self.paused_from,
+            redaction: self.redaction,
+            run_id: self.run_id,
+            #[cfg(feature = "zeroize")]
+            mem_zeroize: self.mem_zeroize,
+        }
+    }
+}
+
+/// Opaque reference to memory the backend owns. Use `Frame<MemHandle>` and have the
+/// stepper resolve the handle against its own storage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct MemHandle(pub u64);
+
+/// Backend-side storage addressed by [`MemHandle`]. Handles are never reused, so a
+/// stale handle resolves to `None` rather than another frame's memory.
+#[derive(Debug, Clone)]
+pub struct MemTable<T> {
+    next: u64,
+    slots: BTreeMap<MemHandle, T>,
+}
+
+impl<T> Default for MemTable<T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<T> MemTable<T> {
+    pub fn new() -> Self {
+        Self {
+            next: 0,
+            slots: BTreeMap::new(),
+        }
+    }
+
+    /// Store `mem` and return the handle to give the frame.
+    pub fn insert(&mut self, mem: T) -> MemHandle {
+        let handle = MemHandle(self.next);
+        self.next += 1;
+        self.slots.insert(handle, mem);
+        handle
+    }
+
+    pub fn get(&self, handle: MemHandle) -> Option<&T> {
+        self.slots.get(&handle)
+    }
+
+    pub fn get_mut(&mut self, handle: MemHandle) -> Option<&mut T> {
+        self.slots.get_mut(&handle)
+    }
+
+    /// Like [`MemTable::get_mut`], with the error steppers return for a stale handle.
+    pub fn resolve(&mut self, handle: MemHandle) -> Result<&mut T, String> {
+        self.get_mut(handle)
+            .ok_or_else(|| format!("mem: unknown handle {}", handle.0))
+    }
+
+    pub fn remove(&mut self, handle: MemHandle) -> Option<T> {
+        self.slots.remove(&handle)
+    }
+
+    pub fn len(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.slots.is_empty()
+    }
+}
+
+/// Running memory totals folded from `mem.*` receipts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MemAccounting {
+    pub blocks_allocated: u64,
+    pub blocks_freed: u64,
+    /// Blocks held right now (`blocks_allocated - blocks_freed`).
+    pub blocks_held: u64,
+    pub peak_blocks_held: u64,
+    /// Sum of `blocks_held` over every observed step.
+    pub block_steps: u64,
+    /// Latest reported `mem.bytes_resident`.
+    pub bytes_resident: u64,
+    pub peak_bytes_resident: u64,
+}
+
+impl MemAccounting {
+    pub fn observe_receipt(&mut self, r: &Receipt) {
+        match r.kind {
+            MEM_BLOCKS_ALLOC => {
+                self.blocks_allocated = self.blocks_allocated.saturating_add(r.value_u64);
+                self.blocks_held = self.blocks_held.saturating_add(r.value_u64);
+                self.peak_blocks_held = self.peak_blocks_held.max(self.blocks_held);
+            }
+            MEM_BLOCKS_FREE => {
+                self.blocks_freed = self.blocks_freed.saturating_add(r.value_u64);
+                self.blocks_held = self.blocks_held.saturating_sub(r.value_u64);
+            }
+            MEM_BYTES_RESIDENT => {
+                self.bytes_resident = r.value_u64;
+                self.peak_bytes_resident = self.peak_bytes_resident.max(r.value_u64);
+            }
+            _ => {}
+        }
+    }
+
+    /// Fold one step envelope: apply its receipts, then charge one step of held blocks.
+    pub fn observe(&mut self, r: &StepResult) {
+        for receipt in &r.receipts {
+            self.observe_receipt(receipt);
+        }
+        self.block_steps = self.block_steps.saturating_add(self.blocks_held);
+    }
+}
+
+/// Shared memory utilization gauge, read by [`crate::MemoryPressureArbiter`].
+///
+/// Units are the caller's choice (blocks, bytes) as long as `used` and `capacity`
+/// agree. Clones share the same values, so a controller can update the gauge while
+/// drivers read it.
+#[derive(Debug, Clone)]
+pub struct MemoryGauge {
+    used: Arc<AtomicU64>,
+    capacity: Arc<AtomicU64>,
+}
+
+impl MemoryGauge {
+    pub fn new(capacity: u64) -> Self {
+        Self {
+            used: Arc::new(AtomicU64::new(0)),
+            capacity: Arc::new(AtomicU64::new(capacity)),
+        }
+    }
+
+    pub fn used(&self) -> u64 {
+        self.used.load(Ordering::Acquire)
+    }
+
+    pub fn capacity(&self) -> u64 {
+        self.capacity.load(Ordering::Acquire)
+    }
+
+    pub fn set_used(&self, used: u64) {
+        self.used.store(used, Ordering::Release);
+    }
+
+    pub fn set_capacity(&self, capacity: u64) {
+        self.capacity.store(capacity, Ordering::Release);
+    }
+
+    /// Utilization in thousandths, saturating at 1000. A zero capacity reads as full.
+    pub fn utilization_permille(&self) -> u64 {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return 1000;
+        }
+        (self.used().saturating_mul(1000) / capacity).min(1000)
+    }
+
+    /// Track usage in blocks from a step's `mem.blocks_alloc` / `mem.blocks_free` receipts.
+    pub fn observe(&self, r: &StepResult) {
+        for receipt in &r.receipts {
+            match receipt.kind {
+                MEM_BLOCKS_ALLOC => {
+                    self.used.fetch_add(receipt.value_u64, Ordering::AcqRel);
+                }
+                MEM_BLOCKS_FREE => {
+                    let _ = self
+                        .used
+                        .fetch_update(Ordering::AcqRel, Ordering::Acquire, |u| {
+                            Some(u.saturating_sub(receipt.value_u64))
+                        });
+                }
+                _ => {}
+            }
+        }
+    }
+}
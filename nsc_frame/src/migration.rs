@@ -0,0 +1,118 @@
+//! Moving an in-flight frame between hosts.
+//!
+//! The source exports a [`MigrationBundle`] (frame snapshot plus the stepper's
+//! exported state), ships its bytes however it likes, and the destination resumes
+//! the frame with fresh memory and a compatible stepper.
+
+use crate::codec::{self, SnapshotCodec};
+use crate::snapshot::FrameSnapshot;
+use crate::wire::{DecodeError, Reader, Writer};
+use crate::{Arbiter, Driver, FrameStepper, Receipt};
+
+pub const MIGRATION_MAGIC: [u8; 4] = *b"NSCM";
+pub const MIGRATION_VERSION: u8 = 2;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MigrationBundle {
+    pub version: u8,
+    pub snapshot: FrameSnapshot,
+    /// Output of [`FrameStepper::export_state`] on the source host.
+    pub backend_state: Option<Vec<u8>>,
+}
+
+impl MigrationBundle {
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.buf.extend_from_slice(&MIGRATION_MAGIC);
+        w.u8(self.version);
+        self.snapshot.write(&mut w);
+        w.bool(self.backend_state.is_some());
+        if let Some(state) = &self.backend_state {
+            w.bytes(state);
+        }
+        w.buf
+    }
+
+    /// [`MigrationBundle::encode`], sealed with `codec`.
+    pub fn encode_sealed(&self, codec: &dyn SnapshotCodec) -> Result<Vec<u8>, String> {
+        codec::seal(codec, &self.encode())
+    }
+
+    /// Open a buffer from [`MigrationBundle::encode_sealed`] and decode it.
+    pub fn decode_sealed(codec: &dyn SnapshotCodec, buf: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode(&codec::open(codec, buf)?)
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        let mut r = Reader::new(buf);
+        if r.take(4)? != MIGRATION_MAGIC {
+            return Err(DecodeError::Malformed("migration magic"));
+        }
+        let version = r.u8()?;
+        if version != MIGRATION_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let snapshot = FrameSnapshot::read(&mut r)?;
+        let backend_state = if r.bool()? {
+            Some(r.bytes()?.to_vec())
+        } else {
+            None
+        };
+        if !r.is_empty() {
+            return Err(DecodeError::Malformed("trailing bytes in migration bundle"));
+        }
+        Ok(Self {
+            version,
+            snapshot,
+            backend_state,
+        })
+    }
+}
+
+impl<M, S, A> Driver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Capture the frame and the stepper's exported state for migration.
+    ///
+    /// Call between steps. The source driver is left untouched; the caller decides
+    /// when to stop stepping it. The snapshot carries the frame's elapsed ticks, so
+    /// the destination keeps counting toward the same deadline on its own clock.
+    pub fn export_for_migration(&mut self) -> MigrationBundle {
+        MigrationBundle {
+            version: MIGRATION_VERSION,
+            snapshot: self.snapshot(),
+            backend_state: self.stepper.export_state(&self.frame),
+        }
+    }
+
+    /// Rebuild a driver from a bundle on the destination host.
+    ///
+    /// `mem` is the destination's memory for the frame; the stepper receives the
+    /// exported backend state through [`FrameStepper::import_state`]. The first step
+    /// carries a `frame.migrated` receipt.
+    pub fn resume_from_migration(
+        bundle: MigrationBundle,
+        mem: M,
+        mut stepper: S,
+        arbiter: A,
+    ) -> Result<Self, String> {
+        if bundle.version != MIGRATION_VERSION {
+            return Err(format!(
+                "migration: unsupported bundle version {}",
+                bundle.version
+            ));
+        }
+        let steps = bundle.snapshot.steps_taken;
+        let mut frame = bundle.snapshot.into_frame(mem);
+        if let Some(state) = bundle.backend_state {
+            stepper.import_state(&mut frame, state)?;
+        }
+        let mut driver = Self::with_arbiter(frame, stepper, arbiter);
+        driver
+            .pending_receipts
+            .push(Receipt::new("frame.migrated", steps));
+        Ok(driver)
+    }
+}
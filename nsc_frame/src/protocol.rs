@@ -0,0 +1,306 @@
+//! Command/response messages for driving frames across a process boundary.
+//!
+//! A supervisor sends [`Command`]s to a worker that owns the frames; the worker
+//! answers with [`Response`]s. This module only encodes and decodes bytes: no
+//! sockets, no framing beyond what is described here.
+//!
+//! Every message is `len: u32 LE | version: u8 | tag: u8 | payload`, where `len`
+//! counts the bytes after itself. Integers are little-endian.
+
+use crate::snapshot::FrameSnapshot;
+use crate::wire::{Reader, Writer};
+use crate::{FrameLimits, FrameState, Proposal, StepOutcome, StepResult, StopReason};
+
+pub use crate::wire::DecodeError;
+
+pub const PROTOCOL_VERSION: u8 = 1;
+
+/// Caller-assigned identifier of a frame living in the worker.
+pub type FrameId = u64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    SubmitFrame {
+        frame_id: FrameId,
+        limits: FrameLimits,
+        prompt_token_ids: Vec<u32>,
+    },
+    Step {
+        frame_id: FrameId,
+    },
+    Cancel {
+        frame_id: FrameId,
+    },
+    Snapshot {
+        frame_id: FrameId,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Response {
+    /// A `SubmitFrame` or `Cancel` was applied.
+    Accepted {
+        frame_id: FrameId,
+    },
+    Step {
+        frame_id: FrameId,
+        envelope: StepEnvelope,
+    },
+    Snapshot {
+        frame_id: FrameId,
+        snapshot: FrameSnapshot,
+    },
+    Error {
+        frame_id: FrameId,
+        message: String,
+    },
+}
+
+/// Owned form of a [`StepResult`] as it crosses the wire (receipt kinds are owned strings).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StepEnvelope {
+    pub outcome: StepOutcome,
+    pub emitted_token: Option<u32>,
+    pub stop_reason: Option<StopReason>,
+    pub receipts: Vec<(String, u64)>,
+    pub proposal: Option<Proposal>,
+    pub tokens_committed: u32,
+    pub state_after: FrameState,
+    pub step_index: u64,
+}
+
+impl From<&StepResult> for StepEnvelope {
+    fn from(r: &StepResult) -> Self {
+        Self {
+            outcome: r.outcome,
+            emitted_token: r.emitted_token,
+            stop_reason: r.stop_reason,
+            receipts: r
+                .receipts
+                .iter()
+                .map(|x| (x.kind.to_string(), x.value_u64))
+                .collect(),
+            proposal: r.proposal.clone(),
+            tokens_committed: r.tokens_committed,
+            state_after: r.state_after,
+            step_index: r.step_index,
+        }
+    }
+}
+
+impl StepEnvelope {
+    pub(crate) fn write(&self, w: &mut Writer) {
+        w.outcome(self.outcome);
+        w.opt_u32(self.emitted_token);
+        w.opt_stop_reason(self.stop_reason);
+        w.u32(self.receipts.len() as u32);
+        for (kind, value) in &self.receipts {
+            w.str(kind);
+            w.u64(*value);
+        }
+        w.bool(self.proposal.is_some());
+        if let Some(p) = &self.proposal {
+            w.u32(p.candidates.len() as u32);
+            for c in &p.candidates {
+                w.u32s(c);
+            }
+            w.bool(p.committed.is_some());
+            w.u64(p.committed.unwrap_or(0) as u64);
+            w.u64(p.accepted_len as u64);
+        }
+        w.u32(self.tokens_committed);
+        w.state(self.state_after);
+        w.u64(self.step_index);
+    }
+
+    pub(crate) fn read(r: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let outcome = r.outcome()?;
+        let emitted_token = r.opt_u32()?;
+        let stop_reason = r.opt_stop_reason()?;
+        let n = r.u32()?;
+        let receipts = (0..n)
+            .map(|_| Ok((r.string()?, r.u64()?)))
+            .collect::<Result<_, DecodeError>>()?;
+        let proposal = if r.bool()? {
+            let n = r.u32()?;
+            let candidates = (0..n).map(|_| r.u32s()).collect::<Result<_, _>>()?;
+            let has_commit = r.bool()?;
+            let committed = r.usize()?;
+            Some(Proposal {
+                candidates,
+                committed: has_commit.then_some(committed),
+                accepted_len: r.usize()?,
+            })
+        } else {
+            None
+        };
+        Ok(Self {
+            outcome,
+            emitted_token,
+            stop_reason,
+            receipts,
+            proposal,
+            tokens_committed: r.u32()?,
+            state_after: r.state()?,
+            step_index: r.u64()?,
+        })
+    }
+}
+
+impl Command {
+    /// Append the framed encoding of `self` to `out`.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let mut w = Writer::default();
+        match self {
+            Command::SubmitFrame {
+                frame_id,
+                limits,
+                prompt_token_ids,
+            } => {
+                w.u8(0);
+                w.u64(*frame_id);
+                w.limits(limits);
+                w.u32s(prompt_token_ids);
+            }
+            Command::Step { frame_id } => {
+                w.u8(1);
+                w.u64(*frame_id);
+            }
+            Command::Cancel { frame_id } => {
+                w.u8(2);
+                w.u64(*frame_id);
+            }
+            Command::Snapshot { frame_id } => {
+                w.u8(3);
+                w.u64(*frame_id);
+            }
+        }
+        frame_message(out, &w.buf);
+    }
+
+    /// Decode one command from the front of `buf`, returning it and the bytes consumed.
+    pub fn decode(buf: &[u8]) -> Result<(Self, usize), DecodeError> {
+        let (body, used) = unframe_message(buf)?;
+        let mut r = Reader::new(body);
+        let cmd = Self::read(&mut r).map_err(truncated_body)?;
+        finish(r, cmd, used)
+    }
+
+    fn read(r: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(match r.u8()? {
+            0 => Command::SubmitFrame {
+                frame_id: r.u64()?,
+                limits: r.limits()?,
+                prompt_token_ids: r.u32s()?,
+            },
+            1 => Command::Step { frame_id: r.u64()? },
+            2 => Command::Cancel { frame_id: r.u64()? },
+            3 => Command::Snapshot { frame_id: r.u64()? },
+            tag => {
+                return Err(DecodeError::UnknownTag {
+                    what: "command",
+                    tag,
+                })
+            }
+        })
+    }
+}
+
+impl Response {
+    /// Append the framed encoding of `self` to `out`.
+    pub fn encode(&self, out: &mut Vec<u8>) {
+        let mut w = Writer::default();
+        match self {
+            Response::Accepted { frame_id } => {
+                w.u8(0);
+                w.u64(*frame_id);
+            }
+            Response::Step { frame_id, envelope } => {
+                w.u8(1);
+                w.u64(*frame_id);
+                envelope.write(&mut w);
+            }
+            Response::Snapshot { frame_id, snapshot } => {
+                w.u8(2);
+                w.u64(*frame_id);
+                snapshot.write(&mut w);
+            }
+            Response::Error { frame_id, message } => {
+                w.u8(3);
+                w.u64(*frame_id);
+                w.str(message);
+            }
+        }
+        frame_message(out, &w.buf);
+    }
+
+    /// Decode one response from the front of `buf`, returning it and the bytes consumed.
+    pub fn decode(buf: &[u8]) -> Result<(Self, usize), DecodeError> {
+        let (body, used) = unframe_message(buf)?;
+        let mut r = Reader::new(body);
+        let resp = Self::read(&mut r).map_err(truncated_body)?;
+        finish(r, resp, used)
+    }
+
+    fn read(r: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        Ok(match r.u8()? {
+            0 => Response::Accepted { frame_id: r.u64()? },
+            1 => Response::Step {
+                frame_id: r.u64()?,
+                envelope: StepEnvelope::read(r)?,
+            },
+            2 => Response::Snapshot {
+                frame_id: r.u64()?,
+                snapshot: FrameSnapshot::read(r)?,
+            },
+            3 => Response::Error {
+                frame_id: r.u64()?,
+                message: r.string()?,
+            },
+            tag => {
+                return Err(DecodeError::UnknownTag {
+                    what: "response",
+                    tag,
+                })
+            }
+        })
+    }
+}
+
+fn frame_message(out: &mut Vec<u8>, body: &[u8]) {
+    out.extend_from_slice(&(body.len() as u32 + 1).to_le_bytes());
+    out.push(PROTOCOL_VERSION);
+    out.extend_from_slice(body);
+}
+
+/// Split one framed message off `buf`: returns its body (after the version byte)
+/// and the total length consumed.
+fn unframe_message(buf: &[u8]) -> Result<(&[u8], usize), DecodeError> {
+    let mut r = Reader::new(buf);
+    let len = r.u32()? as usize;
+    if len == 0 {
+        return Err(DecodeError::Malformed("empty message"));
+    }
+    let msg = r.take(len)?;
+    if msg[0] != PROTOCOL_VERSION {
+        return Err(DecodeError::UnsupportedVersion(msg[0]));
+    }
+    Ok((&msg[1..], 4 + len))
+}
+
+/// The whole message is present once [`unframe_message`] succeeds, so running out of
+/// body bytes is a malformed message, not a reason to wait for more input.
+fn truncated_body(e: DecodeError) -> DecodeError {
+    match e {
+        DecodeError::Incomplete => DecodeError::Malformed("truncated message body"),
+        e => e,
+    }
+}
+
+fn finish<T>(r: Reader<'_>, value: T, used: usize) -> Result<(T, usize), DecodeError> {
+    // A complete frame with leftover bytes means sender and receiver disagree on layout.
+    if !r.is_empty() {
+        return Err(DecodeError::Malformed("trailing bytes in message"));
+    }
+    Ok((value, used))
+}
@@ -0,0 +1,79 @@
+//! Redaction of token content in diagnostic output.
+//!
+//! Prompt and output token ids are user content. A frame's [`Redaction`] decides how
+//! they appear in its `Debug` output and that of its [`FrameSnapshot`]s; `Display`
+//! never prints token ids at all. Redaction only affects formatting: wire encodings
+//! carry the real tokens, since they are needed to resume the frame.
+//!
+//! [`FrameSnapshot`]: crate::FrameSnapshot
+
+use std::fmt;
+
+use crate::hash::{Fnv1a64, TokenHasher};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum Redaction {
+    /// Token ids are printed as they are.
+    #[default]
+    Full,
+    /// Only the length and an FNV-1a digest, enough to tell sequences apart.
+    HashOnly,
+    /// Only the length.
+    LengthsOnly,
+}
+
+impl Redaction {
+    pub(crate) fn to_u8(self) -> u8 {
+        match self {
+            Redaction::Full => 0,
+            Redaction::HashOnly => 1,
+            Redaction::LengthsOnly => 2,
+        }
+    }
+
+    pub(crate) fn from_u8(v: u8) -> Option<Self> {
+        match v {
+            0 => Some(Redaction::Full),
+            1 => Some(Redaction::HashOnly),
+            2 => Some(Redaction::LengthsOnly),
+            _ => None,
+        }
+    }
+
+    /// Format `tokens` (of length `len`) under this policy.
+    pub fn fmt_tokens(
+        self,
+        f: &mut fmt::Formatter<'_>,
+        len: usize,
+        tokens: impl Iterator<Item = u32>,
+    ) -> fmt::Result {
+        match self {
+            Redaction::Full => f.debug_list().entries(tokens).finish(),
+            Redaction::HashOnly => {
+                let mut h = Fnv1a64::new();
+                tokens.for_each(|t| h.update(t));
+                write!(f, "<{} tokens, fnv1a {:016x}>", len, h.digest())
+            }
+            Redaction::LengthsOnly => write!(f, "<{} tokens>", len),
+        }
+    }
+}
+
+/// `Debug` for a token slice under `policy`.
+pub(crate) fn slice(policy: Redaction, tokens: &[u32]) -> impl fmt::Debug + '_ {
+    DebugWith(move |f: &mut fmt::Formatter<'_>| {
+        policy.fmt_tokens(f, tokens.len(), tokens.iter().copied())
+    })
+}
+
+/// `Debug` adapter over a formatting closure, for redacted struct fields.
+pub(crate) struct DebugWith<F>(pub(crate) F);
+
+impl<F> fmt::Debug for DebugWith<F>
+where
+    F: Fn(&mut fmt::Formatter<'_>) -> fmt::Result,
+{
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        (self.0)(f)
+    }
+}
@@ -0,0 +1,93 @@
+//! Deterministic seed derivation and small PRNGs.
+//!
+//! One root seed fans out into per-frame seeds ([`frame_seed`]) and per-step seeds
+//! ([`step_seed`]), so backends sampling with their own RNG and tests replaying a run
+//! all derive the same values. Outputs are stable across platforms and releases.
+
+const GOLDEN_GAMMA: u64 = 0x9e37_79b9_7f4a_7c15;
+
+/// SplitMix64 output function: a bijective 64-bit mix.
+pub fn mix64(mut z: u64) -> u64 {
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    z ^ (z >> 31)
+}
+
+/// Seed for the frame `frame_id` under `root`.
+pub fn frame_seed(root: u64, frame_id: u64) -> u64 {
+    mix64(root ^ mix64(frame_id.wrapping_add(GOLDEN_GAMMA)))
+}
+
+/// Seed for step `step_index` of a frame seeded with `frame_seed`.
+pub fn step_seed(frame_seed: u64, step_index: u64) -> u64 {
+    mix64(frame_seed ^ mix64(step_index.wrapping_mul(GOLDEN_GAMMA)))
+}
+
+/// SplitMix64: small, seedable and stable across platforms.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SplitMix64 {
+    state: u64,
+}
+
+impl SplitMix64 {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(GOLDEN_GAMMA);
+        mix64(self.state)
+    }
+
+    /// Uniform-ish value in `0..n`; 0 when `n` is 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        below(self.next_u64(), n)
+    }
+}
+
+/// xoshiro256**: longer period and better statistics than [`SplitMix64`], for
+/// backends that draw many samples per step.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Xoshiro256 {
+    s: [u64; 4],
+}
+
+impl Xoshiro256 {
+    /// State expanded from `seed` with [`SplitMix64`], as the reference recommends.
+    pub fn new(seed: u64) -> Self {
+        let mut sm = SplitMix64::new(seed);
+        Self {
+            s: [sm.next_u64(), sm.next_u64(), sm.next_u64(), sm.next_u64()],
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        let s = &mut self.s;
+        let result = s[1].wrapping_mul(5).rotate_left(7).wrapping_mul(9);
+        let t = s[1] << 17;
+        s[2] ^= s[0];
+        s[3] ^= s[1];
+        s[1] ^= s[2];
+        s[0] ^= s[3];
+        s[2] ^= t;
+        s[3] = s[3].rotate_left(45);
+        result
+    }
+
+    /// Uniform-ish value in `0..n`; 0 when `n` is 0.
+    pub fn below(&mut self, n: u64) -> u64 {
+        below(self.next_u64(), n)
+    }
+
+    /// Uniform value in `[0, 1)` with 53 bits of precision.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 * (1.0 / (1u64 << 53) as f64)
+    }
+}
+
+fn below(x: u64, n: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    x % n
+}
@@ -0,0 +1,260 @@
+//! Built-in [`Scheduler`]s for [`FrameGroup::step_tick`](crate::FrameGroup::step_tick).
+
+use std::cmp::Reverse;
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::group::{Candidate, ScheduleEntry, Scheduler};
+use crate::{Receipt, Tag};
+
+/// Receipt on the envelope of a member with a [`LatencyTarget`] rate; value is the
+/// rate it has attained so far, in permille of its target.
+pub const SLA_ATTAINMENT: &str = "sla.attainment";
+
+/// Steps the member that has waited longest since its last step; ties go to the
+/// earliest admitted. The default for a group without a scheduler.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RoundRobin;
+
+impl Scheduler for RoundRobin {
+    fn pick(&mut self, _round: u64, ready: &[Candidate]) -> Option<usize> {
+        ready
+            .iter()
+            .max_by_key(|c| (c.stats.idle_rounds, Reverse(c.index)))
+            .map(|c| c.index)
+    }
+}
+
+/// Steps the members a recorded schedule says, round by round, so a run ticked with
+/// [`FrameGroup::with_schedule_log`](crate::FrameGroup::with_schedule_log) can be
+/// reproduced exactly from the same starting members.
+///
+/// In a round with no entry nobody is stepped. An entry naming a member that is not
+/// ready fails the tick, which is where a replay has diverged from the recording.
+#[derive(Debug, Clone, Default)]
+pub struct Replay {
+    schedule: Vec<ScheduleEntry>,
+    next: usize,
+}
+
+impl Replay {
+    /// Replay `schedule` as returned by [`FrameGroup::schedule`](crate::FrameGroup::schedule).
+    pub fn new(schedule: Vec<ScheduleEntry>) -> Self {
+        Self { schedule, next: 0 }
+    }
+
+    /// Entries not replayed yet.
+    pub fn remaining(&self) -> &[ScheduleEntry] {
+        &self.schedule[self.next..]
+    }
+}
+
+impl Scheduler for Replay {
+    fn pick(&mut self, round: u64, _ready: &[Candidate]) -> Option<usize> {
+        while self
+            .schedule
+            .get(self.next)
+            .is_some_and(|e| e.round < round)
+        {
+            self.next += 1;
+        }
+        let entry = self.schedule.get(self.next).filter(|e| e.round == round)?;
+        self.next += 1;
+        Some(entry.member)
+    }
+}
+
+/// Shortest job first: steps the member with the fewest output tokens left, adding the
+/// prompt tokens it has yet to prefill when `count_prefill` is set. Members without
+/// an output cap are the longest jobs; ties go to the earliest admitted.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShortestJobFirst {
+    pub count_prefill: bool,
+}
+
+impl ShortestJobFirst {
+    /// The size estimate this policy ranks `c` by.
+    pub fn job_size(&self, c: &Candidate) -> usize {
+        let p = &c.progress;
+        let output = p.remaining_tokens.unwrap_or(usize::MAX);
+        match self.count_prefill {
+            true => output.saturating_add(p.prompt_len - p.prompt_consumed),
+            false => output,
+        }
+    }
+}
+
+impl Scheduler for ShortestJobFirst {
+    fn pick(&mut self, _round: u64, ready: &[Candidate]) -> Option<usize> {
+        ready
+            .iter()
+            .min_by_key(|c| (self.job_size(c), c.index))
+            .map(|c| c.index)
+    }
+}
+
+/// Latency-target (SLA) aware scheduling.
+///
+/// Frames carry a target output rate, in tokens per round, through their tags. A
+/// member behind its target (fewer tokens than `rate * age_rounds`) is stepped first,
+/// the furthest behind before the others. Members without a target come next, round
+/// robin, and members ahead of their target are throttled: they are stepped only when
+/// nobody else is ready. Every targeted envelope carries an [`SLA_ATTAINMENT`]
+/// receipt.
+#[derive(Debug, Clone, Default)]
+pub struct LatencyTarget {
+    pub targets: Vec<(Tag, f64)>,
+}
+
+impl LatencyTarget {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Aim for `tokens_per_round` for frames tagged `tag`. A frame with several
+    /// targeted tags takes the first one set.
+    pub fn target(mut self, tag: Tag, tokens_per_round: f64) -> Self {
+        self.targets.push((tag, tokens_per_round));
+        self
+    }
+
+    /// Generated over expected tokens for `c`, or `None` if it has no target.
+    pub fn attainment(&self, c: &Candidate) -> Option<f64> {
+        let &(_, rate) = self.targets.iter().find(|(tag, _)| c.tags.contains(tag))?;
+        let expected = rate * c.stats.age_rounds.max(1) as f64;
+        Some(c.stats.tokens_generated as f64 / expected)
+    }
+}
+
+impl Scheduler for LatencyTarget {
+    fn pick(&mut self, round: u64, ready: &[Candidate]) -> Option<usize> {
+        let lowest = |behind_only: bool| {
+            ready
+                .iter()
+                .filter_map(|c| self.attainment(c).map(|a| (c.index, a)))
+                .filter(|&(_, a)| !behind_only || a < 1.0)
+                .min_by(|(i, a), (j, b)| a.total_cmp(b).then(i.cmp(j)))
+                .map(|(i, _)| i)
+        };
+        let untargeted = || {
+            let rest: Vec<Candidate> = ready
+                .iter()
+                .filter(|c| self.attainment(c).is_none())
+                .cloned()
+                .collect();
+            RoundRobin.pick(round, &rest)
+        };
+        lowest(true).or_else(untargeted).or_else(|| lowest(false))
+    }
+
+    fn receipts(&mut self, picked: &Candidate, out: &mut Vec<Receipt>) {
+        if let Some(a) = self.attainment(picked) {
+            out.push(Receipt::new(SLA_ATTAINMENT, (a * 1000.0).round() as u64));
+        }
+    }
+}
+
+/// Ticks delivered to one tenant of a [`WeightedFair`] scheduler.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TenantShare {
+    /// The tenant's tag, or `None` for members carrying no tenant tag.
+    pub tenant: Option<Tag>,
+    pub weight: u64,
+    /// Ticks in which one of the tenant's members was stepped.
+    pub served: u64,
+}
+
+/// Weighted fair queueing across tenants.
+///
+/// A member belongs to the first tenant whose tag it carries; members carrying none
+/// share a default tenant of weight 1. Each tick goes to the ready tenant that has
+/// been served least for its weight, so tenants that stay busy receive ticks in
+/// proportion to their weights. Ties go to the tenant configured first, the default
+/// last. Within a tenant members take turns as under [`RoundRobin`].
+///
+/// Clones share their accounting: keep one to read [`WeightedFair::shares`] while the
+/// group owns the other.
+#[derive(Debug, Clone)]
+pub struct WeightedFair {
+    shares: Arc<Mutex<Vec<TenantShare>>>,
+}
+
+impl Default for WeightedFair {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeightedFair {
+    pub fn new() -> Self {
+        let default = TenantShare {
+            tenant: None,
+            weight: 1,
+            served: 0,
+        };
+        Self {
+            shares: Arc::new(Mutex::new(vec![default])),
+        }
+    }
+
+    /// Treat frames tagged `tag` as one tenant with `weight` (at least 1).
+    pub fn tenant(self, tag: Tag, weight: u64) -> Self {
+        {
+            let mut shares = self.lock();
+            let at = shares.len() - 1;
+            let share = TenantShare {
+                tenant: Some(tag),
+                weight: weight.max(1),
+                served: 0,
+            };
+            shares.insert(at, share);
+        }
+        self
+    }
+
+    /// Every tenant with what it has been delivered so far, the default last.
+    pub fn shares(&self) -> Vec<TenantShare> {
+        self.lock().clone()
+    }
+
+    fn lock(&self) -> MutexGuard<'_, Vec<TenantShare>> {
+        self.shares.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Index into `shares` of the tenant `c` belongs to.
+fn tenant_of(shares: &[TenantShare], c: &Candidate) -> usize {
+    let configured = shares.len() - 1;
+    shares[..configured]
+        .iter()
+        .position(|s| s.tenant.is_some_and(|tag| c.tags.contains(&tag)))
+        .unwrap_or(configured)
+}
+
+impl Scheduler for WeightedFair {
+    fn pick(&mut self, round: u64, ready: &[Candidate]) -> Option<usize> {
+        let shares = self.lock();
+        // (served + 1) / weight, compared without division.
+        let owed = |t: usize, u: usize| {
+            let (a, b) = (&shares[t], &shares[u]);
+            let lhs = (a.served as u128 + 1) * b.weight as u128;
+            let rhs = (b.served as u128 + 1) * a.weight as u128;
+            lhs.cmp(&rhs).then(t.cmp(&u))
+        };
+        let tenant = ready
+            .iter()
+            .map(|c| tenant_of(&shares, c))
+            .min_by(|&t, &u| owed(t, u))?;
+        let members: Vec<Candidate> = ready
+            .iter()
+            .filter(|c| tenant_of(&shares, c) == tenant)
+            .cloned()
+            .collect();
+        RoundRobin.pick(round, &members)
+    }
+
+    fn receipts(&mut self, picked: &Candidate, _out: &mut Vec<Receipt>) {
+        let mut shares = self.lock();
+        let tenant = tenant_of(&shares, picked);
+        shares[tenant].served += 1;
+    }
+}
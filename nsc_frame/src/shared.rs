@@ -0,0 +1,109 @@
+//! Sharing a driver across threads.
+//!
+//! Locking discipline: stepping (and any other mutation) happens under the driver
+//! lock; [`SharedDriver::status`] reads a separately published [`DriverStatus`]
+//! snapshot and never waits on a step in progress.
+
+use std::sync::{Arc, Mutex, MutexGuard};
+
+use crate::{Arbiter, Driver, FrameState, FrameStepper, NoArbiter, StepResult, StopReason};
+
+/// Point-in-time view of a driver, published after every locked operation.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DriverStatus {
+    pub state: FrameState,
+    pub position: u32,
+    pub tokens_generated: usize,
+    pub stop_reason: Option<StopReason>,
+    pub run_id: Option<u64>,
+}
+
+impl DriverStatus {
+    pub(crate) fn of<M, S, A>(d: &Driver<M, S, A>) -> Self
+    where
+        S: FrameStepper<M>,
+        A: Arbiter<M>,
+    {
+        Self {
+            state: d.frame.state,
+            position: d.frame.cursor.position,
+            tokens_generated: d.frame.tokens_generated,
+            stop_reason: d.frame.stop_reason,
+            run_id: d.frame.run_id,
+        }
+    }
+}
+
+/// Cloneable, thread-safe handle to a [`Driver`] (e.g. for web server state).
+///
+/// `SharedDriver` is `Send + Sync` when `M`, `S` and `A` are `Send`.
+pub struct SharedDriver<M, S, A = NoArbiter>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    driver: Arc<Mutex<Driver<M, S, A>>>,
+    status: Arc<Mutex<DriverStatus>>,
+}
+
+impl<M, S, A> Clone for SharedDriver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    fn clone(&self) -> Self {
+        Self {
+            driver: Arc::clone(&self.driver),
+            status: Arc::clone(&self.status),
+        }
+    }
+}
+
+impl<M, S, A> SharedDriver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    pub fn new(driver: Driver<M, S, A>) -> Self {
+        let status = DriverStatus::of(&driver);
+        Self {
+            driver: Arc::new(Mutex::new(driver)),
+            status: Arc::new(Mutex::new(status)),
+        }
+    }
+
+    /// Step once under the driver lock.
+    pub fn step(&self) -> Result<StepResult, String> {
+        self.with_driver(|d| d.step())?
+    }
+
+    /// Run `f` with exclusive access to the driver, then publish a fresh status.
+    pub fn with_driver<R>(&self, f: impl FnOnce(&mut Driver<M, S, A>) -> R) -> Result<R, String> {
+        let mut d = self.lock()?;
+        let out = f(&mut d);
+        let status = DriverStatus::of(&d);
+        drop(d);
+        *self.status.lock().unwrap_or_else(|e| e.into_inner()) = status;
+        Ok(out)
+    }
+
+    /// Latest published status. Does not take the driver lock.
+    pub fn status(&self) -> DriverStatus {
+        *self.status.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Recover the driver if this is the last handle.
+    pub fn try_into_inner(self) -> Result<Driver<M, S, A>, Self> {
+        let status = self.status;
+        match Arc::try_unwrap(self.driver) {
+            Ok(m) => Ok(m.into_inner().unwrap_or_else(|e| e.into_inner())),
+            Err(driver) => Err(Self { driver, status }),
+        }
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, Driver<M, S, A>>, String> {
+        self.driver
+            .lock()
+            .map_err(|_| "shared driver: lock poisoned by a panicking step".to_string())
+    }
+}
@@ -0,0 +1,242 @@
+//! Delivering emitted tokens to a consumer, with backpressure.
+//!
+//! A driver with a [`TokenSink`] offers it every committed token. When the sink
+//! answers [`SinkControl::Backpressure`] the driver keeps the token in a backlog and
+//! stops advancing the frame: each following step offers the backlog again and, while
+//! the sink still refuses, yields with a `sink.backpressure` receipt instead of
+//! stepping the backend. Output is never buffered beyond what one step produced.
+//!
+//! A [`FlushPolicy`] decides when emitted tokens are handed over at all: one at a
+//! time, in groups, or at backend-marked boundaries. Tokens held back by the policy
+//! are visible through [`Driver::buffered_emissions`].
+//!
+//! Flow control ([`Driver::with_flow_control`]) is the consumer-driven counterpart:
+//! the consumer grants credits ahead of time instead of refusing tokens.
+//!
+//! Receipts can be collected the same way: a [`ReceiptSink`] is handed every
+//! envelope's receipts after the driver is done with them, and with
+//! [`Driver::strip_receipts`] the returned envelopes no longer carry them.
+
+use std::collections::VecDeque;
+
+use crate::channel::{TokenEvent, TokenSender, TrySendError};
+use crate::{Arbiter, Driver, FrameState, FrameStepper, Receipt, StepOutcome, StepResult};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SinkControl {
+    /// The token was taken.
+    Continue,
+    /// The sink is full; the token was not taken and must be offered again later.
+    Backpressure,
+}
+
+/// Consumer of a frame's emitted tokens, fed by the driver in emission order.
+pub trait TokenSink {
+    fn offer(&mut self, token: u32) -> SinkControl;
+}
+
+/// Collects every token; never applies backpressure.
+impl TokenSink for Vec<u32> {
+    fn offer(&mut self, token: u32) -> SinkControl {
+        self.push(token);
+        SinkControl::Continue
+    }
+}
+
+/// A full channel applies backpressure. Tokens sent after the receiver is gone are
+/// dropped.
+impl TokenSink for TokenSender {
+    fn offer(&mut self, token: u32) -> SinkControl {
+        match self.try_send(TokenEvent::Token(token)) {
+            Err(TrySendError::Full(_)) => SinkControl::Backpressure,
+            Ok(()) | Err(TrySendError::Disconnected(_)) => SinkControl::Continue,
+        }
+    }
+}
+
+/// Consumer of the receipts of every envelope a driver returns, in step order.
+pub trait ReceiptSink {
+    fn accept(&mut self, receipts: &[Receipt]);
+}
+
+/// Collects every receipt.
+impl ReceiptSink for Vec<Receipt> {
+    fn accept(&mut self, receipts: &[Receipt]) {
+        self.extend_from_slice(receipts);
+    }
+}
+
+/// Tokens emitted but not yet taken by the sink, oldest first.
+pub type SinkBacklog = VecDeque<u32>;
+
+/// Receipt kind a backend attaches to a step whose token ends a detokenization
+/// boundary (e.g. a complete word or character). Value is ignored.
+pub const EMIT_BOUNDARY: &str = "emit.boundary";
+
+/// Receipt kind on the envelope that finishes a frame when the sink refused part of
+/// the output; value is the number of tokens left in [`Driver::sink_backlog`].
+/// Envelopes from stepping the finished frame again do not repeat it. They are lost
+/// unless the caller drains them with [`Driver::flush_sink`].
+pub const SINK_DROPPED: &str = "sink.dropped";
+
+/// When buffered emissions are handed to the sink. Whatever is buffered is always
+/// offered when the frame finishes; see [`SINK_DROPPED`] for a sink that refuses.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum FlushPolicy {
+    /// Every token as soon as it is emitted.
+    #[default]
+    Immediate,
+    /// Groups of this many tokens.
+    EveryN(usize),
+    /// Up to and including the latest step carrying an [`EMIT_BOUNDARY`] receipt.
+    AtBoundary,
+}
+
+impl FlushPolicy {
+    fn releases(self, buffered: usize, r: &StepResult) -> bool {
+        match self {
+            FlushPolicy::Immediate => true,
+            FlushPolicy::EveryN(n) => buffered >= n.max(1),
+            FlushPolicy::AtBoundary => r.receipts.iter().any(|x| x.kind == EMIT_BOUNDARY),
+        }
+    }
+}
+
+impl<M, S, A> Driver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Deliver emitted tokens to `sink`.
+    pub fn with_sink(mut self, sink: impl TokenSink + Send + Sync + 'static) -> Self {
+        self.sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Hand every returned envelope's receipts to `sink`.
+    pub fn with_receipt_sink(mut self, sink: impl ReceiptSink + Send + Sync + 'static) -> Self {
+        self.receipt_sink = Some(Box::new(sink));
+        self
+    }
+
+    /// Return envelopes without receipts once the receipt sink has them. Has no effect
+    /// without a receipt sink.
+    pub fn strip_receipts(mut self) -> Self {
+        self.strip_receipts = true;
+        self
+    }
+
+    /// Forward `r`'s receipts to the receipt sink, if any, and strip them if asked.
+    pub(crate) fn forward_receipts(&mut self, r: &mut StepResult) {
+        let Some(sink) = self.receipt_sink.as_mut() else {
+            return;
+        };
+        sink.accept(&r.receipts);
+        if self.strip_receipts {
+            r.receipts = Vec::new();
+        }
+    }
+
+    /// Group emissions according to `policy` before handing them to the sink.
+    pub fn with_flush_policy(mut self, policy: FlushPolicy) -> Self {
+        self.flush_policy = policy;
+        self
+    }
+
+    /// Tokens emitted but not yet accepted by the sink.
+    pub fn sink_backlog(&self) -> &SinkBacklog {
+        &self.sink_backlog
+    }
+
+    /// Tokens emitted but held back by the [`FlushPolicy`].
+    pub fn buffered_emissions(&self) -> &[u32] {
+        &self.emit_buffer
+    }
+
+    /// Hand everything held back by the flush policy to the sink.
+    pub(crate) fn release_emissions(&mut self) {
+        self.sink_backlog.extend(self.emit_buffer.drain(..));
+        self.flush_sink();
+    }
+
+    /// Hand everything buffered to the sink as the frame ends, noting on the finishing
+    /// envelope `r` whatever the sink still refused, unless an earlier envelope of the
+    /// finished frame already did.
+    pub(crate) fn release_final(&mut self, r: &mut StepResult) {
+        self.release_emissions();
+        if std::mem::replace(&mut self.final_released, true) {
+            return;
+        }
+        if self.sink.is_some() && !self.sink_backlog.is_empty() {
+            let left = self.sink_backlog.len() as u64;
+            r.receipts.push(Receipt::new(SINK_DROPPED, left));
+        }
+    }
+
+    /// Offer the backlog to the sink. Returns `true` once the backlog is empty.
+    pub fn flush_sink(&mut self) -> bool {
+        let Some(sink) = self.sink.as_mut() else {
+            return true;
+        };
+        while let Some(&token) = self.sink_backlog.front() {
+            if sink.offer(token) == SinkControl::Backpressure {
+                return false;
+            }
+            self.sink_backlog.pop_front();
+        }
+        true
+    }
+
+    /// Buffer a step's committed tokens for the sink and release them when the policy
+    /// says so.
+    pub(crate) fn emit_to_sink(&mut self, r: &mut StepResult, committed: &[u32]) {
+        if let Some(c) = &mut self.flow_credits {
+            *c = c.saturating_sub(committed.len() as u64);
+        }
+        if self.sink.is_none() {
+            return;
+        }
+        self.emit_buffer.extend_from_slice(committed);
+        if r.outcome == StepOutcome::Finished {
+            self.release_final(r);
+        } else if self.flush_policy.releases(self.emit_buffer.len(), r) {
+            self.release_emissions();
+        }
+    }
+
+    /// Turn on credit-based flow control with `initial_credits`.
+    ///
+    /// Every committed token spends one credit; consumers grant more with
+    /// [`DriverCommand::GrantCredits`] (e.g. through [`DriverHandle::grant_credits`]).
+    /// With no credits left a decoding frame is not stepped: the driver yields with a
+    /// `flow.blocked` receipt until credits arrive. Prefill is never blocked.
+    ///
+    /// [`DriverCommand::GrantCredits`]: crate::DriverCommand::GrantCredits
+    /// [`DriverHandle::grant_credits`]: crate::DriverHandle::grant_credits
+    pub fn with_flow_control(mut self, initial_credits: u64) -> Self {
+        self.flow_credits = Some(initial_credits);
+        self
+    }
+
+    /// Credits left, or `None` without flow control.
+    pub fn flow_credits(&self) -> Option<u64> {
+        self.flow_credits
+    }
+
+    /// Yield instead of decoding while flow control has no credits.
+    pub(crate) fn enforce_flow_control(&self) -> Option<StepResult> {
+        if self.flow_credits? > 0 || self.frame.state != FrameState::Decode {
+            return None;
+        }
+        Some(StepResult::yielded().with_receipt("flow.blocked", 1))
+    }
+
+    /// Yield instead of stepping while the sink refuses the backlog.
+    pub(crate) fn enforce_sink_backpressure(&mut self) -> Option<StepResult> {
+        if self.flush_sink() {
+            return None;
+        }
+        let backlog = self.sink_backlog.len() as u64;
+        Some(StepResult::yielded().with_receipt("sink.backpressure", backlog))
+    }
+}
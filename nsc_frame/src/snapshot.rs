@@ -0,0 +1,232 @@
+//! Plain-data snapshots of a frame's law-visible state.
+//!
+//! A snapshot covers everything the law owns (state, cursor, limits, prompt and
+//! output logs) and deliberately excludes `mem`, which belongs to the backend.
+
+use std::fmt;
+
+use crate::codec::{self, SnapshotCodec};
+use crate::redact::{self, Redaction};
+use crate::wire::{DecodeError, Reader, Writer};
+use crate::{Frame, FrameLimits, FrameState, StopReason, Tag};
+
+pub const SNAPSHOT_MAGIC: [u8; 4] = *b"NSCS";
+pub const SNAPSHOT_VERSION: u8 = 2;
+
+#[derive(Clone, PartialEq, Eq)]
+pub struct FrameSnapshot {
+    pub state: FrameState,
+    pub paused_from: Option<FrameState>,
+    pub position: u32,
+    pub limits: FrameLimits,
+    pub prompt_token_ids: Vec<u32>,
+    pub prompt_index: usize,
+    pub generated_token_ids: Vec<u32>,
+    pub tokens_generated: usize,
+    pub stop_reason: Option<StopReason>,
+    pub steps_taken: u64,
+    pub cost_spent: u64,
+    /// Ticks the frame had run toward `deadline_ticks`. [`Frame::snapshot`] can only
+    /// report ticks carried from an earlier restore, since a frame has no clock;
+    /// [`Driver::snapshot`](crate::Driver::snapshot) adds the time since it started.
+    pub elapsed_ticks: u64,
+    /// Tag names. [`FrameSnapshot::into_frame`] restores the built-in ones;
+    /// [`FrameSnapshot::restore_into`] keeps the target frame's tags.
+    pub tags: Vec<String>,
+    /// The frame's redaction policy; also applied to this snapshot's `Debug` output.
+    pub redaction: Redaction,
+    pub run_id: Option<u64>,
+}
+
+impl fmt::Debug for FrameSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("FrameSnapshot")
+            .field("state", &self.state)
+            .field("paused_from", &self.paused_from)
+            .field("position", &self.position)
+            .field("limits", &self.limits)
+            .field(
+                "prompt_token_ids",
+                &redact::slice(self.redaction, &self.prompt_token_ids),
+            )
+            .field("prompt_index", &self.prompt_index)
+            .field(
+                "generated_token_ids",
+                &redact::slice(self.redaction, &self.generated_token_ids),
+            )
+            .field("tokens_generated", &self.tokens_generated)
+            .field("stop_reason", &self.stop_reason)
+            .field("steps_taken", &self.steps_taken)
+            .field("cost_spent", &self.cost_spent)
+            .field("elapsed_ticks", &self.elapsed_ticks)
+            .field("tags", &self.tags)
+            .field("redaction", &self.redaction)
+            .field("run_id", &self.run_id)
+            .finish()
+    }
+}
+
+impl<M> Frame<M> {
+    pub fn snapshot(&self) -> FrameSnapshot {
+        FrameSnapshot {
+            state: self.state,
+            paused_from: self.paused_from,
+            position: self.cursor.position,
+            limits: self.limits.clone(),
+            prompt_token_ids: self.prompt_token_ids.clone(),
+            prompt_index: self.prompt_index,
+            generated_token_ids: self.generated_token_ids.to_vec(),
+            tokens_generated: self.tokens_generated,
+            stop_reason: self.stop_reason,
+            steps_taken: self.steps_taken,
+            cost_spent: self.cost_spent,
+            elapsed_ticks: self.carried_ticks,
+            tags: self.tags.iter().map(|t| t.0.to_string()).collect(),
+            redaction: self.redaction,
+            run_id: self.run_id,
+        }
+    }
+}
+
+impl FrameSnapshot {
+    /// Standalone encoding: `magic "NSCS" | version: u8 | snapshot`.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.buf.extend_from_slice(&SNAPSHOT_MAGIC);
+        w.u8(SNAPSHOT_VERSION);
+        self.write(&mut w);
+        w.buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        let mut r = Reader::new(buf);
+        if r.take(4)? != SNAPSHOT_MAGIC {
+            return Err(DecodeError::Malformed("snapshot magic"));
+        }
+        let version = r.u8()?;
+        if version != SNAPSHOT_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let snapshot = Self::read(&mut r)?;
+        if !r.is_empty() {
+            return Err(DecodeError::Malformed("trailing bytes in snapshot"));
+        }
+        Ok(snapshot)
+    }
+
+    /// [`FrameSnapshot::encode`], sealed with `codec`.
+    pub fn encode_sealed(&self, codec: &dyn SnapshotCodec) -> Result<Vec<u8>, String> {
+        codec::seal(codec, &self.encode())
+    }
+
+    /// Open a buffer from [`FrameSnapshot::encode_sealed`] and decode it.
+    pub fn decode_sealed(codec: &dyn SnapshotCodec, buf: &[u8]) -> Result<Self, DecodeError> {
+        Self::decode(&codec::open(codec, buf)?)
+    }
+
+    pub(crate) fn write(&self, w: &mut Writer) {
+        w.state(self.state);
+        w.bool(self.paused_from.is_some());
+        if let Some(prev) = self.paused_from {
+            w.state(prev);
+        }
+        w.u32(self.position);
+        w.limits(&self.limits);
+        w.u32s(&self.prompt_token_ids);
+        w.u64(self.prompt_index as u64);
+        w.u32s(&self.generated_token_ids);
+        w.u64(self.tokens_generated as u64);
+        w.opt_stop_reason(self.stop_reason);
+        w.u64(self.steps_taken);
+        w.u64(self.cost_spent);
+        w.u64(self.elapsed_ticks);
+        w.u32(self.tags.len() as u32);
+        for t in &self.tags {
+            w.str(t);
+        }
+        w.u8(self.redaction.to_u8());
+        w.opt_u64(self.run_id);
+    }
+
+    pub(crate) fn read(r: &mut Reader<'_>) -> Result<Self, DecodeError> {
+        let state = r.state()?;
+        let paused_from = if r.bool()? { Some(r.state()?) } else { None };
+        let position = r.u32()?;
+        let limits = r.limits()?;
+        let prompt_token_ids = r.u32s()?;
+        let prompt_index = r.usize()?;
+        let generated_token_ids = r.u32s()?;
+        let tokens_generated = r.usize()?;
+        let stop_reason = r.opt_stop_reason()?;
+        let steps_taken = r.u64()?;
+        let cost_spent = r.u64()?;
+        let elapsed_ticks = r.u64()?;
+        let n = r.u32()?;
+        let tags = (0..n).map(|_| r.string()).collect::<Result<_, _>>()?;
+        let tag = r.u8()?;
+        let redaction = Redaction::from_u8(tag).ok_or(DecodeError::UnknownTag {
+            what: "redaction",
+            tag,
+        })?;
+        let run_id = r.opt_u64()?;
+        Ok(Self {
+            state,
+            paused_from,
+            position,
+            limits,
+            prompt_token_ids,
+            prompt_index,
+            generated_token_ids,
+            tokens_generated,
+            stop_reason,
+            steps_taken,
+            cost_spent,
+            elapsed_ticks,
+            tags,
+            redaction,
+            run_id,
+        })
+    }
+}
+
+impl FrameSnapshot {
+    /// Rebuild a frame around `mem` from this snapshot.
+    ///
+    /// Built-in tags ([`Tag::INTERACTIVE`], [`Tag::BATCH`], [`Tag::EVALUATION`]) are
+    /// restored; custom tags cannot be recovered from their names and must be re-added.
+    pub fn into_frame<M>(self, mem: M) -> Frame<M> {
+        let mut frame = Frame::new(mem, 0);
+        self.restore_into(&mut frame);
+        for name in &self.tags {
+            if let Some(tag) = Tag::builtin(name) {
+                frame.add_tag(tag);
+            }
+        }
+        frame
+    }
+
+    /// Overwrite `frame`'s progress with this snapshot, keeping its memory and tags.
+    ///
+    /// The elapsed ticks become the frame's `carried_ticks` and `started_at` is
+    /// cleared, so the deadline resumes from the next step on whichever clock drives
+    /// the frame.
+    pub fn restore_into<M>(&self, frame: &mut Frame<M>) {
+        frame.state = self.state;
+        frame.paused_from = self.paused_from;
+        frame.cursor.position = self.position;
+        frame.limits = self.limits.clone();
+        #[cfg(feature = "zeroize")]
+        crate::zeroize::wipe_vec(&mut frame.prompt_token_ids);
+        frame.prompt_token_ids = self.prompt_token_ids.clone();
+        frame.prompt_index = self.prompt_index;
+        frame.generated_token_ids = self.generated_token_ids.clone().into();
+        frame.tokens_generated = self.tokens_generated;
+        frame.stop_reason = self.stop_reason;
+        frame.steps_taken = self.steps_taken;
+        frame.cost_spent = self.cost_spent;
+        frame.started_at = None;
+        frame.carried_ticks = self.elapsed_ticks;
+        frame.redaction = self.redaction;
+        frame.run_id = self.run_id;
+    }
+}
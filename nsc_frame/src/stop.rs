@@ -0,0 +1,282 @@
+//! Stop conditions evaluated by the driver after each step.
+//!
+//! Backends may still finish a frame themselves; a [`StopCondition`] lets the caller
+//! decide when output is complete (EOS, stop sequences, grammar acceptance, custom
+//! predicates) without teaching every backend about it.
+
+use std::collections::{BTreeMap, VecDeque};
+
+use crate::{Frame, StepResult, StopReason};
+
+/// Receipt kind recorded when a stop condition finishes a frame. The value is the
+/// stop reason's wire code.
+pub const STOP_CONDITION: &str = "stop.condition";
+
+/// Boxed condition as stored by the driver and the combinators.
+pub type BoxedStopCondition<M> = Box<dyn StopCondition<M> + Send + Sync>;
+
+/// Decides, after a step has advanced the frame, whether generation is complete.
+pub trait StopCondition<M> {
+    /// Called once per advanced step with the frame already updated by `step`.
+    /// Returning a reason finishes the frame with it.
+    ///
+    /// A step may commit several tokens; [`StepResult::committed_tokens`] lists them.
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason>;
+
+    /// Called when the frame was rewound (e.g. by
+    /// [`DebugDriver::back`](crate::debug::DebugDriver::back)), so conditions that
+    /// track the output can resynchronise with it.
+    fn rewind(&mut self, _frame: &Frame<M>) {}
+}
+
+/// Fires once the frame has generated `limits.max_new_tokens` tokens.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MaxTokens;
+
+impl<M> StopCondition<M> for MaxTokens {
+    fn check(&mut self, frame: &Frame<M>, _step: &StepResult) -> Option<StopReason> {
+        frame
+            .limits
+            .output_exhausted(frame.tokens_generated)
+            .then_some(StopReason::MaxTokens)
+    }
+}
+
+/// Fires when the step committed the given end-of-sequence token.
+#[derive(Debug, Clone, Copy)]
+pub struct EosToken(pub u32);
+
+impl<M> StopCondition<M> for EosToken {
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason> {
+        step.committed_tokens(&frame.generated_token_ids)
+            .contains(&self.0)
+            .then_some(StopReason::Eos)
+    }
+}
+
+/// Fires when any of the given token sequences ends on a token committed by the step.
+/// The stop reason carries the index of the sequence that matched.
+#[derive(Debug, Clone, Default)]
+pub struct StopSequences {
+    pub sequences: Vec<Vec<u32>>,
+}
+
+impl StopSequences {
+    pub fn new(sequences: Vec<Vec<u32>>) -> Self {
+        Self { sequences }
+    }
+}
+
+impl<M> StopCondition<M> for StopSequences {
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason> {
+        let out = &frame.generated_token_ids;
+        let committed = (step.tokens_committed as usize).min(out.len());
+        // Earliest end position first, as if the tokens had come one step at a time.
+        (out.len() - committed + 1..=out.len()).find_map(|end| {
+            self.sequences
+                .iter()
+                .position(|seq| {
+                    !seq.is_empty()
+                        && seq.len() <= end
+                        && out
+                            .since(end - seq.len())
+                            .take(seq.len())
+                            .eq(seq.iter().copied())
+                })
+                .map(|i| StopReason::StopSequence(i as u32))
+        })
+    }
+}
+
+/// Multi-pattern stop sequence matcher (Aho-Corasick over token ids).
+///
+/// The automaton is built once up front; each committed token then advances a single
+/// state, so per-token cost does not grow with the number or length of sequences as
+/// it does for [`StopSequences`]. Fires at the first committed token that ends a
+/// sequence, with the lowest index among the sequences ending there.
+///
+/// The matcher only sees tokens committed while it is attached; call [`reset`] before
+/// reusing it for another frame. A rewound frame replays its output into the matcher.
+///
+/// [`reset`]: StopMatcher::reset
+#[derive(Debug, Clone)]
+pub struct StopMatcher {
+    nodes: Vec<MatchNode>,
+    state: usize,
+}
+
+#[derive(Debug, Clone, Default)]
+struct MatchNode {
+    next: BTreeMap<u32, usize>,
+    fail: usize,
+    /// Lowest pattern index ending here, following fail links.
+    out: Option<u32>,
+}
+
+impl StopMatcher {
+    pub fn new(sequences: &[Vec<u32>]) -> Self {
+        let mut nodes = vec![MatchNode::default()];
+        for (i, seq) in sequences.iter().enumerate() {
+            if seq.is_empty() {
+                continue;
+            }
+            let mut at = 0;
+            for &t in seq {
+                at = match nodes[at].next.get(&t) {
+                    Some(&n) => n,
+                    None => {
+                        nodes.push(MatchNode::default());
+                        let n = nodes.len() - 1;
+                        nodes[at].next.insert(t, n);
+                        n
+                    }
+                };
+            }
+            let out = &mut nodes[at].out;
+            *out = Some(out.map_or(i as u32, |o| o.min(i as u32)));
+        }
+
+        // Breadth-first so every fail target is finished before it is used.
+        let mut queue: VecDeque<usize> = nodes[0].next.values().copied().collect();
+        while let Some(at) = queue.pop_front() {
+            let edges: Vec<(u32, usize)> = nodes[at].next.iter().map(|(&t, &n)| (t, n)).collect();
+            for (t, child) in edges {
+                let mut f = nodes[at].fail;
+                let fail = loop {
+                    if let Some(&n) = nodes[f].next.get(&t) {
+                        break n;
+                    }
+                    if f == 0 {
+                        break 0;
+                    }
+                    f = nodes[f].fail;
+                };
+                nodes[child].fail = fail;
+                nodes[child].out = match (nodes[child].out, nodes[fail].out) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                queue.push_back(child);
+            }
+        }
+        Self { nodes, state: 0 }
+    }
+
+    /// Forget all tokens seen so far.
+    pub fn reset(&mut self) {
+        self.state = 0;
+    }
+
+    /// Advance by one token; returns the index of a sequence ending here.
+    pub fn advance(&mut self, token: u32) -> Option<u32> {
+        let mut at = self.state;
+        self.state = loop {
+            if let Some(&n) = self.nodes[at].next.get(&token) {
+                break n;
+            }
+            if at == 0 {
+                break 0;
+            }
+            at = self.nodes[at].fail;
+        };
+        self.nodes[self.state].out
+    }
+}
+
+impl<M> StopCondition<M> for StopMatcher {
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason> {
+        step.committed_tokens(&frame.generated_token_ids)
+            .into_iter()
+            .find_map(|tok| self.advance(tok))
+            .map(StopReason::StopSequence)
+    }
+
+    fn rewind(&mut self, frame: &Frame<M>) {
+        self.reset();
+        for tok in frame.generated_token_ids.iter() {
+            self.advance(tok);
+        }
+    }
+}
+
+/// Caller predicate over the frame; fires with [`StopReason::Custom`] and `code`.
+///
+/// Grammar completion and other backend-independent checks go here.
+pub struct Predicate<F> {
+    pub code: u32,
+    pub f: F,
+}
+
+impl<F> Predicate<F> {
+    pub fn new(code: u32, f: F) -> Self {
+        Self { code, f }
+    }
+}
+
+impl<M, F> StopCondition<M> for Predicate<F>
+where
+    F: FnMut(&Frame<M>, &StepResult) -> bool,
+{
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason> {
+        (self.f)(frame, step).then_some(StopReason::Custom(self.code))
+    }
+}
+
+/// Fires when any member fires, with the first firing member's reason.
+///
+/// Every member is checked on every step, so stateful members stay in sync.
+pub struct Any<M> {
+    pub conditions: Vec<BoxedStopCondition<M>>,
+}
+
+/// Fires when every member fires on the same step, with the first member's reason.
+///
+/// Every member is checked on every step, so stateful members stay in sync.
+pub struct All<M> {
+    pub conditions: Vec<BoxedStopCondition<M>>,
+}
+
+pub fn any<M>(conditions: Vec<BoxedStopCondition<M>>) -> Any<M> {
+    Any { conditions }
+}
+
+pub fn all<M>(conditions: Vec<BoxedStopCondition<M>>) -> All<M> {
+    All { conditions }
+}
+
+impl<M> StopCondition<M> for Any<M> {
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason> {
+        let mut fired = None;
+        for c in &mut self.conditions {
+            let r = c.check(frame, step);
+            fired = fired.or(r);
+        }
+        fired
+    }
+
+    fn rewind(&mut self, frame: &Frame<M>) {
+        for c in &mut self.conditions {
+            c.rewind(frame);
+        }
+    }
+}
+
+impl<M> StopCondition<M> for All<M> {
+    fn check(&mut self, frame: &Frame<M>, step: &StepResult) -> Option<StopReason> {
+        let mut first = None;
+        let mut all_fired = !self.conditions.is_empty();
+        for c in &mut self.conditions {
+            match c.check(frame, step) {
+                Some(r) => first = first.or(Some(r)),
+                None => all_fired = false,
+            }
+        }
+        first.filter(|_| all_fired)
+    }
+
+    fn rewind(&mut self, frame: &Frame<M>) {
+        for c in &mut self.conditions {
+            c.rewind(frame);
+        }
+    }
+}
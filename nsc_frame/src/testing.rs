@@ -0,0 +1,426 @@
+//! Fixtures for tests over the law (feature `testing`).
+//!
+//! Downstream crates enable this feature in `[dev-dependencies]` instead of copying
+//! frame setup into every test:
+//!
+//! ```ignore
+//! let frame = nsc_frame::testing::frame().prompt_len(100).max_new_tokens(8).build();
+//! let mut driver = Driver::new(frame, my_stepper);
+//! nsc_frame::assert_run_matches!(driver, [Advanced(none), Advanced(tok 3), Yielded, Finished(MaxTokens)]);
+//! ```
+
+use std::collections::VecDeque;
+use std::fmt;
+
+use crate::mem::{BlockId, PagedMemory};
+use crate::{
+    Arbiter, ArbiterContext, Decision, Driver, Frame, FrameError, FrameLimits, FrameProgress,
+    FrameState, FrameStepper, FrameView, NoopMem, StepOutcome, StepResult, StopReason, Tag,
+};
+
+/// Start building a [`Frame`] with an empty prompt and eight output tokens.
+pub fn frame() -> FrameBuilder {
+    FrameBuilder::default()
+}
+
+/// Fluent fixture for frames and their limits.
+#[derive(Debug, Clone)]
+pub struct FrameBuilder {
+    prompt_token_ids: Vec<u32>,
+    limits: FrameLimits,
+    tags: Vec<Tag>,
+}
+
+impl Default for FrameBuilder {
+    fn default() -> Self {
+        Self {
+            prompt_token_ids: Vec::new(),
+            limits: FrameLimits::new(8),
+            tags: Vec::new(),
+        }
+    }
+}
+
+impl FrameBuilder {
+    /// Use [`counting_prompt`] of length `len`.
+    pub fn prompt_len(mut self, len: usize) -> Self {
+        self.prompt_token_ids = counting_prompt(len);
+        self
+    }
+
+    pub fn prompt(mut self, prompt_token_ids: impl Into<Vec<u32>>) -> Self {
+        self.prompt_token_ids = prompt_token_ids.into();
+        self
+    }
+
+    pub fn max_new_tokens(mut self, n: usize) -> Self {
+        self.limits.max_new_tokens = Some(n);
+        self
+    }
+
+    pub fn max_total_tokens(mut self, n: usize) -> Self {
+        self.limits.max_total_tokens = Some(n);
+        self
+    }
+
+    pub fn deadline_ticks(mut self, ticks: u64) -> Self {
+        self.limits.deadline_ticks = Some(ticks);
+        self
+    }
+
+    pub fn max_cost(mut self, units: u64) -> Self {
+        self.limits.max_cost = Some(units);
+        self
+    }
+
+    pub fn vocab_size(mut self, vocab_size: u32) -> Self {
+        self.limits.vocab_size = Some(vocab_size);
+        self
+    }
+
+    pub fn limits(mut self, limits: FrameLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    pub fn tag(mut self, tag: Tag) -> Self {
+        self.tags.push(tag);
+        self
+    }
+
+    /// Build over [`NoopMem`].
+    ///
+    /// Panics if the limits do not [validate](FrameLimits::validate); use
+    /// [`FrameBuilder::try_build_with`] to exercise invalid frames.
+    #[track_caller]
+    pub fn build(self) -> Frame<NoopMem> {
+        self.build_with(NoopMem)
+    }
+
+    #[track_caller]
+    pub fn build_with<M>(self, mem: M) -> Frame<M> {
+        self.try_build_with(mem)
+            .unwrap_or_else(|e| panic!("invalid fixture frame: {}", e))
+    }
+
+    pub fn try_build_with<M>(self, mem: M) -> Result<Frame<M>, FrameError> {
+        let mut frame = Frame::try_with_prompt(mem, self.limits, self.prompt_token_ids)?;
+        for tag in self.tags {
+            frame.add_tag(tag);
+        }
+        Ok(frame)
+    }
+}
+
+/// Canned prompt: a short greeting-sized prompt.
+pub const SHORT_PROMPT: &[u32] = &[1, 15043, 29892, 3186, 29991];
+
+/// Canned prompt `0, 1, .., len - 1`.
+pub fn counting_prompt(len: usize) -> Vec<u32> {
+    (0..len as u32).collect()
+}
+
+/// Paged memory over a private pool of `total_blocks` blocks, with offload support.
+#[derive(Debug, Clone)]
+pub struct TestMem {
+    block_size: usize,
+    total_blocks: usize,
+    next_block: BlockId,
+    table: Vec<BlockId>,
+    offloaded: Option<Vec<BlockId>>,
+}
+
+impl TestMem {
+    pub fn new(block_size: usize, total_blocks: usize) -> Self {
+        Self {
+            block_size,
+            total_blocks,
+            next_block: 0,
+            table: Vec::new(),
+            offloaded: None,
+        }
+    }
+
+    pub fn is_offloaded(&self) -> bool {
+        self.offloaded.is_some()
+    }
+}
+
+impl Default for TestMem {
+    /// 16-token blocks, 64 of them.
+    fn default() -> Self {
+        Self::new(16, 64)
+    }
+}
+
+impl PagedMemory for TestMem {
+    fn block_size(&self) -> usize {
+        self.block_size
+    }
+
+    fn total_blocks(&self) -> usize {
+        self.total_blocks
+    }
+
+    fn free_blocks(&self) -> usize {
+        self.total_blocks - self.table.len()
+    }
+
+    fn allocate(&mut self, n: usize) -> Result<Vec<BlockId>, String> {
+        if self.offloaded.is_some() {
+            return Err("allocate: memory is offloaded".to_string());
+        }
+        if n > self.free_blocks() {
+            return Err(format!(
+                "allocate: {} blocks requested, {} free",
+                n,
+                self.free_blocks()
+            ));
+        }
+        let blocks: Vec<BlockId> = (0..n as BlockId).map(|i| self.next_block + i).collect();
+        self.next_block += n as BlockId;
+        self.table.extend_from_slice(&blocks);
+        Ok(blocks)
+    }
+
+    fn free(&mut self, blocks: &[BlockId]) {
+        self.table.retain(|b| !blocks.contains(b));
+    }
+
+    fn block_table(&self) -> &[BlockId] {
+        &self.table
+    }
+
+    fn offload(&mut self) -> Result<(), String> {
+        if self.offloaded.is_some() {
+            return Err("offload: already offloaded".to_string());
+        }
+        self.offloaded = Some(std::mem::take(&mut self.table));
+        Ok(())
+    }
+
+    fn restore(&mut self) -> Result<(), String> {
+        self.table = self
+            .offloaded
+            .take()
+            .ok_or_else(|| "restore: not offloaded".to_string())?;
+        Ok(())
+    }
+}
+
+/// Arbiter that replays a scripted sequence of decisions and records every frame it
+/// was asked about.
+///
+/// Once the script runs out it answers with `then` ([`Decision::Allow`] by default).
+#[derive(Debug, Clone)]
+pub struct MockArbiter {
+    script: VecDeque<Decision>,
+    pub then: Decision,
+    /// What the arbiter was shown at each `decide` call, in order.
+    pub seen: Vec<SeenFrame>,
+}
+
+/// Owned copy of a [`FrameView`], as recorded by [`MockArbiter`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SeenFrame {
+    pub state: FrameState,
+    pub position: u32,
+    pub progress: FrameProgress,
+    pub tags: Vec<Tag>,
+    pub recent_tokens: Vec<u32>,
+}
+
+impl SeenFrame {
+    pub fn of(view: &FrameView<'_>) -> Self {
+        Self {
+            state: view.state,
+            position: view.position,
+            progress: view.progress(),
+            tags: view.tags.to_vec(),
+            recent_tokens: view.recent_tokens().collect(),
+        }
+    }
+}
+
+impl MockArbiter {
+    pub fn new(script: impl IntoIterator<Item = Decision>) -> Self {
+        Self {
+            script: script.into_iter().collect(),
+            then: Decision::Allow,
+            seen: Vec::new(),
+        }
+    }
+
+    pub fn then(mut self, decision: Decision) -> Self {
+        self.then = decision;
+        self
+    }
+
+    /// Scripted decisions not yet handed out.
+    pub fn remaining(&self) -> usize {
+        self.script.len()
+    }
+
+    pub fn calls(&self) -> usize {
+        self.seen.len()
+    }
+}
+
+impl<M> Arbiter<M> for MockArbiter {
+    fn decide(&mut self, frame: &FrameView<'_>, _ctx: &ArbiterContext) -> Decision {
+        self.seen.push(SeenFrame::of(frame));
+        self.script.pop_front().unwrap_or(self.then)
+    }
+}
+
+/// Expected shape of one driver step, as written in
+/// [`assert_run_matches!`](macro@crate::assert_run_matches).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StepPattern {
+    /// `Advanced`: advanced, with or without a token.
+    AdvancedAny,
+    /// `Advanced(none)` / `Advanced(tok N)`.
+    Advanced(Option<u32>),
+    Yielded,
+    Stalled,
+    Finished(StopReason),
+    /// The step returned an error (only ever produced by the actual run).
+    Error(String),
+}
+
+impl StepPattern {
+    pub fn of(r: &StepResult) -> Self {
+        match r.outcome {
+            StepOutcome::Advanced => StepPattern::Advanced(r.emitted_token),
+            StepOutcome::Yielded => StepPattern::Yielded,
+            StepOutcome::Stalled => StepPattern::Stalled,
+            StepOutcome::Finished => {
+                StepPattern::Finished(r.stop_reason.unwrap_or(StopReason::MaxTokens))
+            }
+        }
+    }
+
+    pub fn matches(&self, actual: &StepPattern) -> bool {
+        match (self, actual) {
+            (StepPattern::AdvancedAny, StepPattern::Advanced(_)) => true,
+            (e, a) => e == a,
+        }
+    }
+}
+
+impl fmt::Display for StepPattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StepPattern::AdvancedAny => write!(f, "Advanced"),
+            StepPattern::Advanced(None) => write!(f, "Advanced(none)"),
+            StepPattern::Advanced(Some(t)) => write!(f, "Advanced(tok {})", t),
+            StepPattern::Yielded => write!(f, "Yielded"),
+            StepPattern::Stalled => write!(f, "Stalled"),
+            StepPattern::Finished(reason) => write!(f, "Finished({:?})", reason),
+            StepPattern::Error(e) => write!(f, "Error({})", e),
+        }
+    }
+}
+
+/// Step `driver` and panic with a side-by-side diff unless the steps match `expected`.
+///
+/// The run stops at the first finished or failed step, or one step past the end of
+/// `expected`. Usually called through
+/// [`assert_run_matches!`](macro@crate::assert_run_matches).
+#[track_caller]
+pub fn assert_run_matches<M, S, A>(driver: &mut Driver<M, S, A>, expected: &[StepPattern])
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    let mut actual = Vec::new();
+    while actual.len() <= expected.len() {
+        let step = match driver.step() {
+            Ok(r) => StepPattern::of(&r),
+            Err(e) => StepPattern::Error(e),
+        };
+        let done = matches!(step, StepPattern::Finished(_) | StepPattern::Error(_));
+        actual.push(step);
+        if done {
+            break;
+        }
+    }
+
+    let ok =
+        actual.len() == expected.len() && expected.iter().zip(&actual).all(|(e, a)| e.matches(a));
+    if ok {
+        return;
+    }
+
+    let mut diff = format!(
+        "driver run did not match
+{:6}{:<31}actual
+",
+        "", "expected"
+    );
+    for i in 0..expected.len().max(actual.len()) {
+        let e = expected
+            .get(i)
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "-".into());
+        let a = actual
+            .get(i)
+            .map(|p| p.to_string())
+            .unwrap_or_else(|| "-".into());
+        let same = matches!((expected.get(i), actual.get(i)), (Some(e), Some(a)) if e.matches(a));
+        let mark = if same { ' ' } else { '>' };
+        diff.push_str(&format!("{} {:>3} {:<30} {}\n", mark, i, e, a));
+    }
+    panic!("{}", diff);
+}
+
+/// Run a driver and assert the sequence of step outcomes.
+///
+/// ```ignore
+/// assert_run_matches!(driver, [Advanced(none), Advanced(tok 3), Advanced, Yielded, Finished(MaxTokens)]);
+/// ```
+///
+/// `Advanced` matches any advanced step; `Finished(..)` takes a [`StopReason`] variant.
+#[macro_export]
+macro_rules! assert_run_matches {
+    ($driver:expr, [$($steps:tt)*]) => {
+        $crate::testing::assert_run_matches(
+            &mut $driver,
+            &$crate::__step_patterns!([] $($steps)*),
+        )
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __step_patterns {
+    ([$($out:expr),*]) => {
+        [$($out),*]
+    };
+    ([$($out:expr),*] , $($rest:tt)*) => {
+        $crate::__step_patterns!([$($out),*] $($rest)*)
+    };
+    ([$($out:expr),*] Advanced(tok $tok:expr) $($rest:tt)*) => {
+        $crate::__step_patterns!(
+            [$($out,)* $crate::testing::StepPattern::Advanced(Some($tok))] $($rest)*
+        )
+    };
+    ([$($out:expr),*] Advanced(none) $($rest:tt)*) => {
+        $crate::__step_patterns!([$($out,)* $crate::testing::StepPattern::Advanced(None)] $($rest)*)
+    };
+    ([$($out:expr),*] Advanced $($rest:tt)*) => {
+        $crate::__step_patterns!([$($out,)* $crate::testing::StepPattern::AdvancedAny] $($rest)*)
+    };
+    ([$($out:expr),*] Yielded $($rest:tt)*) => {
+        $crate::__step_patterns!([$($out,)* $crate::testing::StepPattern::Yielded] $($rest)*)
+    };
+    ([$($out:expr),*] Stalled $($rest:tt)*) => {
+        $crate::__step_patterns!([$($out,)* $crate::testing::StepPattern::Stalled] $($rest)*)
+    };
+    ([$($out:expr),*] Finished($($reason:tt)+) $($rest:tt)*) => {
+        $crate::__step_patterns!(
+            [$($out,)* $crate::testing::StepPattern::Finished($crate::StopReason::$($reason)+)]
+            $($rest)*
+        )
+    };
+}
@@ -0,0 +1,231 @@
+//! Chunked storage for generated token ids.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::ops::Index;
+use std::sync::Arc;
+
+/// Tokens per sealed chunk.
+pub const TOKEN_CHUNK_LEN: usize = 1024;
+
+/// Append-only log of token ids stored in fixed-size chunks.
+///
+/// Growing never copies more than one chunk, however long the output gets. Sealed
+/// chunks are shared (`Arc`), so cloning a log — e.g. to fork a frame — shares its
+/// prefix instead of copying it.
+///
+/// Migrating from `Vec<u32>`: the log is not one contiguous buffer, so it does not
+/// deref to `[u32]`. Indexing, `get`, `iter` and comparisons with vectors and slices
+/// work as before; code that needs a `&[u32]` takes one from [`TokenLog::as_slice`],
+/// or walks [`TokenLog::chunks`] to avoid the copy for long outputs.
+#[derive(Clone, Default)]
+pub struct TokenLog {
+    sealed: Vec<Arc<[u32]>>,
+    tail: Vec<u32>,
+}
+
+impl TokenLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.sealed.len() * TOKEN_CHUNK_LEN + self.tail.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sealed.is_empty() && self.tail.is_empty()
+    }
+
+    pub fn push(&mut self, token: u32) {
+        if self.tail.capacity() == 0 {
+            self.tail.reserve_exact(TOKEN_CHUNK_LEN);
+        }
+        self.tail.push(token);
+        if self.tail.len() == TOKEN_CHUNK_LEN {
+            let chunk = std::mem::take(&mut self.tail);
+            self.sealed.push(Arc::from(&chunk[..]));
+            #[cfg(feature = "zeroize")]
+            crate::zeroize::wipe_vec(&mut { chunk });
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<u32> {
+        let (chunk, offset) = (index / TOKEN_CHUNK_LEN, index % TOKEN_CHUNK_LEN);
+        match self.sealed.get(chunk) {
+            Some(c) => Some(c[offset]),
+            None if chunk == self.sealed.len() => self.tail.get(offset).copied(),
+            None => None,
+        }
+    }
+
+    pub fn last(&self) -> Option<u32> {
+        match self.tail.last() {
+            Some(t) => Some(*t),
+            None => self.sealed.last().and_then(|c| c.last().copied()),
+        }
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = u32> + '_ {
+        self.chunks().flat_map(|c| c.iter().copied())
+    }
+
+    /// The underlying chunks in order; all but the last are `TOKEN_CHUNK_LEN` long.
+    pub fn chunks(&self) -> impl Iterator<Item = &[u32]> + '_ {
+        self.sealed
+            .iter()
+            .map(|c| &c[..])
+            .chain(std::iter::once(&self.tail[..]).filter(|t| !t.is_empty()))
+    }
+
+    /// Up to the last `n` tokens, oldest first.
+    pub fn suffix(&self, n: usize) -> impl Iterator<Item = u32> + '_ {
+        self.since(self.len().saturating_sub(n))
+    }
+
+    /// Tokens from index `start` on, without walking the chunks before it.
+    pub fn since(&self, start: usize) -> impl Iterator<Item = u32> + '_ {
+        let (chunk, offset) = (start / TOKEN_CHUNK_LEN, start % TOKEN_CHUNK_LEN);
+        self.chunks()
+            .skip(chunk)
+            .enumerate()
+            .flat_map(move |(i, c)| {
+                c[if i == 0 { offset.min(c.len()) } else { 0 }..]
+                    .iter()
+                    .copied()
+            })
+    }
+
+    /// Keep only the first `len` tokens.
+    pub fn truncate(&mut self, len: usize) {
+        if len >= self.len() {
+            return;
+        }
+        let keep_sealed = len / TOKEN_CHUNK_LEN;
+        if keep_sealed < self.sealed.len() {
+            let reopened = self.sealed[keep_sealed].clone();
+            #[cfg(feature = "zeroize")]
+            {
+                crate::zeroize::wipe_vec(&mut self.tail);
+                for chunk in self.sealed.drain(keep_sealed..) {
+                    crate::zeroize::wipe_chunk(chunk);
+                }
+            }
+            self.sealed.truncate(keep_sealed);
+            self.tail = Vec::with_capacity(TOKEN_CHUNK_LEN);
+            self.tail.extend_from_slice(&reopened);
+            #[cfg(feature = "zeroize")]
+            crate::zeroize::wipe_chunk(reopened);
+        }
+        let keep = len % TOKEN_CHUNK_LEN;
+        #[cfg(feature = "zeroize")]
+        crate::zeroize::wipe_slice(&mut self.tail[keep..]);
+        self.tail.truncate(keep);
+    }
+
+    pub fn clear(&mut self) {
+        #[cfg(feature = "zeroize")]
+        self.zeroize();
+        self.sealed.clear();
+        self.tail.clear();
+    }
+
+    /// Wipe every chunk this log is the last owner of, then empty it.
+    #[cfg(feature = "zeroize")]
+    pub fn zeroize(&mut self) {
+        crate::zeroize::wipe_vec(&mut self.tail);
+        for chunk in self.sealed.drain(..) {
+            crate::zeroize::wipe_chunk(chunk);
+        }
+    }
+
+    /// The tokens as one slice: borrowed while they fit in a single chunk, copied
+    /// once the log spans several.
+    pub fn as_slice(&self) -> Cow<'_, [u32]> {
+        match (self.sealed.as_slice(), self.tail.is_empty()) {
+            ([], _) => Cow::Borrowed(&self.tail),
+            ([chunk], true) => Cow::Borrowed(chunk),
+            _ => Cow::Owned(self.to_vec()),
+        }
+    }
+
+    pub fn to_vec(&self) -> Vec<u32> {
+        let mut v = Vec::with_capacity(self.len());
+        for c in self.chunks() {
+            v.extend_from_slice(c);
+        }
+        v
+    }
+}
+
+#[cfg(feature = "zeroize")]
+impl Drop for TokenLog {
+    fn drop(&mut self) {
+        self.zeroize();
+    }
+}
+
+impl Index<usize> for TokenLog {
+    type Output = u32;
+
+    fn index(&self, index: usize) -> &u32 {
+        let (chunk, offset) = (index / TOKEN_CHUNK_LEN, index % TOKEN_CHUNK_LEN);
+        match self.sealed.get(chunk) {
+            Some(c) => &c[offset],
+            None if chunk == self.sealed.len() && offset < self.tail.len() => &self.tail[offset],
+            None => panic!(
+                "token index {} out of range for length {}",
+                index,
+                self.len()
+            ),
+        }
+    }
+}
+
+impl fmt::Debug for TokenLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_list().entries(self.iter()).finish()
+    }
+}
+
+impl PartialEq for TokenLog {
+    fn eq(&self, other: &Self) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter())
+    }
+}
+
+impl Eq for TokenLog {}
+
+impl PartialEq<[u32]> for TokenLog {
+    fn eq(&self, other: &[u32]) -> bool {
+        self.len() == other.len() && self.iter().eq(other.iter().copied())
+    }
+}
+
+impl PartialEq<Vec<u32>> for TokenLog {
+    fn eq(&self, other: &Vec<u32>) -> bool {
+        *self == other[..]
+    }
+}
+
+impl Extend<u32> for TokenLog {
+    fn extend<I: IntoIterator<Item = u32>>(&mut self, iter: I) {
+        for t in iter {
+            self.push(t);
+        }
+    }
+}
+
+impl FromIterator<u32> for TokenLog {
+    fn from_iter<I: IntoIterator<Item = u32>>(iter: I) -> Self {
+        let mut log = TokenLog::new();
+        log.extend(iter);
+        log
+    }
+}
+
+impl From<Vec<u32>> for TokenLog {
+    fn from(v: Vec<u32>) -> Self {
+        v.into_iter().collect()
+    }
+}
@@ -0,0 +1,389 @@
+//! Recorded step traces and replay verification.
+//!
+//! A [`Trace`] is the sequence of results a backend produced for one frame, one
+//! [`TraceEntry`] per backend step. [`RecordingStepper`] captures a trace while
+//! running; [`VerifyingStepper`] replays a run against a recorded trace and stops at
+//! the first step that differs.
+//!
+//! [`Trace::seek`] rebuilds the frame as it was after any step by replaying entries
+//! on top of the nearest [`Keyframe`]; [`RecordingStepper::with_keyframes`] embeds
+//! periodic ones while recording.
+
+use std::fmt;
+
+use crate::{
+    Frame, FrameSnapshot, FrameState, FrameStepper, Progress, StepOutcome, StepPreview, StepResult,
+    StopReason,
+};
+
+/// One backend step as observed from outside the backend.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TraceEntry {
+    /// Zero-based index of the backend step within the frame.
+    pub step_index: u64,
+    /// Cursor position after the step.
+    pub position: u32,
+    /// Prompt tokens consumed after the step.
+    pub prompt_index: usize,
+    /// The frame's step counter when the backend returned. Drivers count a step after
+    /// the backend returns, so under a driver this excludes the step itself.
+    pub steps_taken: u64,
+    /// The frame's cost counter when the backend returned, before this step's charge.
+    pub cost_spent: u64,
+    /// Frame state after the step.
+    pub state_after: FrameState,
+    pub outcome: StepOutcome,
+    pub emitted_token: Option<u32>,
+    /// Tokens the step appended to the output log, in order; several for a
+    /// multi-token step.
+    pub committed: Vec<u32>,
+    pub stop_reason: Option<StopReason>,
+    /// Receipts the backend returned, in order.
+    pub receipts: Vec<(String, u64)>,
+}
+
+impl TraceEntry {
+    /// The entry for a step that returned `r`, leaving `frame` with an output log that
+    /// was `output_before` tokens long before the step.
+    pub fn observe<M>(
+        step_index: u64,
+        output_before: usize,
+        frame: &Frame<M>,
+        r: &StepResult,
+    ) -> Self {
+        Self {
+            step_index,
+            position: frame.cursor.position,
+            prompt_index: frame.prompt_index,
+            steps_taken: frame.steps_taken,
+            cost_spent: frame.cost_spent,
+            state_after: frame.state,
+            outcome: r.outcome,
+            emitted_token: r.emitted_token,
+            committed: frame.generated_token_ids.since(output_before).collect(),
+            stop_reason: r.stop_reason,
+            receipts: r
+                .receipts
+                .iter()
+                .map(|x| (x.kind.to_string(), x.value_u64))
+                .collect(),
+        }
+    }
+}
+
+/// Backend steps of one frame, in order.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Trace {
+    pub entries: Vec<TraceEntry>,
+}
+
+impl Trace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    pub fn push(&mut self, entry: TraceEntry) {
+        self.entries.push(entry);
+    }
+
+    pub fn get(&self, step_index: usize) -> Option<&TraceEntry> {
+        self.entries.get(step_index)
+    }
+
+    /// The frame after the first `n` steps of this trace, replayed from the latest
+    /// keyframe at or before `n`.
+    ///
+    /// Replay sees only what backends reported, so it tracks position, prompt progress,
+    /// state, output and stop reason. Step count and cost are taken from the entry
+    /// after the last one replayed, which saw them before its own step; seeking to the
+    /// very end of a trace has no such entry and assumes a driver counted and charged
+    /// the last step. Changes the driver made outside backend steps (commands, limit
+    /// changes) are only as of the keyframe.
+    pub fn seek(&self, keyframes: &[Keyframe], n: usize) -> Result<FrameSnapshot, String> {
+        if n > self.entries.len() {
+            return Err(format!(
+                "seek: step {} past end of trace ({})",
+                n,
+                self.len()
+            ));
+        }
+        let base = keyframes
+            .iter()
+            .filter(|k| k.step_index as usize <= n)
+            .max_by_key(|k| k.step_index)
+            .ok_or_else(|| format!("seek: no keyframe at or before step {}", n))?;
+        let mut snapshot = base.snapshot.clone();
+        let replayed = &self.entries[base.step_index as usize..n];
+        for e in replayed {
+            e.apply(&mut snapshot);
+        }
+        match (self.entries.get(n), replayed.last()) {
+            (Some(next), _) => {
+                snapshot.steps_taken = next.steps_taken;
+                snapshot.cost_spent = next.cost_spent;
+            }
+            (None, Some(last)) => {
+                snapshot.steps_taken = last.steps_taken + 1;
+                snapshot.cost_spent = last.cost_spent.saturating_add(last.cost());
+            }
+            (None, None) => {}
+        }
+        Ok(snapshot)
+    }
+}
+
+impl TraceEntry {
+    fn apply(&self, s: &mut FrameSnapshot) {
+        match (s.state, self.state_after) {
+            (FrameState::Paused(_), FrameState::Paused(_)) => {}
+            (from, FrameState::Paused(_)) => s.paused_from = Some(from),
+            _ => s.paused_from = None,
+        }
+        s.state = self.state_after;
+        s.position = self.position;
+        s.prompt_index = self.prompt_index;
+        s.generated_token_ids.extend_from_slice(&self.committed);
+        s.tokens_generated += self.committed.len();
+        if self.stop_reason.is_some() {
+            s.stop_reason = self.stop_reason;
+        }
+    }
+
+    /// What a driver charges for this step: its reported `step.cost`, else 1.
+    fn cost(&self) -> u64 {
+        self.receipts
+            .iter()
+            .filter(|(kind, _)| kind == crate::STEP_COST)
+            .map(|(_, v)| *v)
+            .reduce(u64::saturating_add)
+            .unwrap_or(1)
+    }
+}
+
+/// The frame as it was just before backend step `step_index`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Keyframe {
+    pub step_index: u64,
+    pub snapshot: FrameSnapshot,
+}
+
+/// Where two traces of the same run first part ways; see [`bisect`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Bisection {
+    pub step_index: u64,
+    /// The frame just before the differing step, when keyframes allow rebuilding it.
+    pub before: Option<FrameSnapshot>,
+    /// `None` when that trace had already ended.
+    pub left: Option<TraceEntry>,
+    pub right: Option<TraceEntry>,
+}
+
+impl fmt::Display for Bisection {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "traces differ at step {}", self.step_index)?;
+        match &self.before {
+            Some(s) => writeln!(f, "  before: {:?}", s)?,
+            None => writeln!(f, "  before: (no keyframe)")?,
+        }
+        writeln!(f, "  left:   {:?}", self.left)?;
+        write!(f, "  right:  {:?}", self.right)
+    }
+}
+
+/// First step at which `left` and `right` differ, with the context needed to
+/// reproduce it; `None` when they are identical.
+///
+/// `keyframes` come from the left run (see [`RecordingStepper::with_keyframes`]) and
+/// are used to rebuild the frame just before the differing step.
+pub fn bisect(left: &Trace, right: &Trace, keyframes: &[Keyframe]) -> Option<Bisection> {
+    let steps = left.len().max(right.len());
+    let i = (0..steps).find(|&i| left.get(i) != right.get(i))?;
+    Some(Bisection {
+        step_index: i as u64,
+        before: left.seek(keyframes, i).ok(),
+        left: left.get(i).cloned(),
+        right: right.get(i).cloned(),
+    })
+}
+
+/// Wraps a stepper and records every result it returns into a [`Trace`].
+pub struct RecordingStepper<S> {
+    pub inner: S,
+    pub trace: Trace,
+    /// Snapshots taken every `keyframe_every` steps, starting with step 0.
+    pub keyframes: Vec<Keyframe>,
+    keyframe_every: Option<u64>,
+}
+
+impl<S> RecordingStepper<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            trace: Trace::new(),
+            keyframes: Vec::new(),
+            keyframe_every: None,
+        }
+    }
+
+    /// Also snapshot the frame before every `every`th step, for [`Trace::seek`].
+    pub fn with_keyframes(mut self, every: u64) -> Self {
+        self.keyframe_every = Some(every.max(1));
+        self
+    }
+
+    pub fn into_parts(self) -> (S, Trace) {
+        (self.inner, self.trace)
+    }
+}
+
+impl<M, S: FrameStepper<M>> FrameStepper<M> for RecordingStepper<S> {
+    fn step(&mut self, frame: &mut Frame<M>) -> Result<StepResult, String> {
+        self.step_with(frame, &mut Progress::new())
+    }
+
+    fn step_with(
+        &mut self,
+        frame: &mut Frame<M>,
+        progress: &mut Progress<'_>,
+    ) -> Result<StepResult, String> {
+        let index = self.trace.len() as u64;
+        let due = self.keyframe_every.is_some_and(|every| index % every == 0);
+        if due && self.keyframes.last().map(|k| k.step_index) != Some(index) {
+            self.keyframes.push(Keyframe {
+                step_index: index,
+                snapshot: frame.snapshot(),
+            });
+        }
+        let output_before = frame.generated_token_ids.len();
+        let r = self.inner.step_with(frame, progress)?;
+        self.trace
+            .push(TraceEntry::observe(index, output_before, frame, &r));
+        Ok(r)
+    }
+
+    fn peek(&mut self, frame: &Frame<M>) -> Option<StepPreview> {
+        self.inner.peek(frame)
+    }
+
+    fn export_state(&mut self, frame: &Frame<M>) -> Option<Vec<u8>> {
+        self.inner.export_state(frame)
+    }
+
+    fn import_state(&mut self, frame: &mut Frame<M>, state: Vec<u8>) -> Result<(), String> {
+        self.inner.import_state(frame, state)
+    }
+}
+
+/// First step at which a replay diverged from its recorded trace.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Mismatch {
+    pub step_index: u64,
+    /// `None` when the replay ran past the end of the trace.
+    pub expected: Option<TraceEntry>,
+    pub actual: TraceEntry,
+}
+
+impl fmt::Display for Mismatch {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match &self.expected {
+            Some(e) => write!(
+                f,
+                "trace mismatch at step {}: expected {:?}, got {:?}",
+                self.step_index, e, self.actual
+            ),
+            None => write!(
+                f,
+                "trace mismatch at step {}: trace ended, got {:?}",
+                self.step_index, self.actual
+            ),
+        }
+    }
+}
+
+/// Wraps a stepper and checks each of its results against a recorded [`Trace`].
+///
+/// The first divergence is kept in [`VerifyingStepper::mismatch`] and returned as a
+/// step error; every later step fails with the same error.
+pub struct VerifyingStepper<S> {
+    pub inner: S,
+    expected: Trace,
+    next: u64,
+    mismatch: Option<Mismatch>,
+}
+
+impl<S> VerifyingStepper<S> {
+    pub fn new(inner: S, expected: Trace) -> Self {
+        Self {
+            inner,
+            expected,
+            next: 0,
+            mismatch: None,
+        }
+    }
+
+    pub fn mismatch(&self) -> Option<&Mismatch> {
+        self.mismatch.as_ref()
+    }
+
+    /// Number of steps verified so far.
+    pub fn verified(&self) -> u64 {
+        self.next
+    }
+
+    /// True once every recorded step has been replayed without divergence.
+    pub fn is_complete(&self) -> bool {
+        self.mismatch.is_none() && self.next as usize == self.expected.len()
+    }
+}
+
+impl<M, S: FrameStepper<M>> FrameStepper<M> for VerifyingStepper<S> {
+    fn step(&mut self, frame: &mut Frame<M>) -> Result<StepResult, String> {
+        self.step_with(frame, &mut Progress::new())
+    }
+
+    fn step_with(
+        &mut self,
+        frame: &mut Frame<M>,
+        progress: &mut Progress<'_>,
+    ) -> Result<StepResult, String> {
+        if let Some(m) = &self.mismatch {
+            return Err(m.to_string());
+        }
+        let output_before = frame.generated_token_ids.len();
+        let r = self.inner.step_with(frame, progress)?;
+        let actual = TraceEntry::observe(self.next, output_before, frame, &r);
+        let expected = self.expected.get(self.next as usize);
+        if expected != Some(&actual) {
+            let m = Mismatch {
+                step_index: self.next,
+                expected: expected.cloned(),
+                actual,
+            };
+            let err = m.to_string();
+            self.mismatch = Some(m);
+            return Err(err);
+        }
+        self.next += 1;
+        Ok(r)
+    }
+
+    fn peek(&mut self, frame: &Frame<M>) -> Option<StepPreview> {
+        self.inner.peek(frame)
+    }
+
+    fn export_state(&mut self, frame: &Frame<M>) -> Option<Vec<u8>> {
+        self.inner.export_state(frame)
+    }
+
+    fn import_state(&mut self, frame: &mut Frame<M>, state: Vec<u8>) -> Result<(), String> {
+        self.inner.import_state(frame, state)
+    }
+}
@@ -0,0 +1,205 @@
+//! Binary trace file format.
+//!
+//! ```text
+//! file   := magic "NSCT" | version u8 | record(header) | record(entry)*
+//! record := len u32 LE | payload [len] | crc u32 LE
+//! ```
+//!
+//! The CRC is CRC-32 (IEEE 802.3, as in zlib) over the payload. Records are
+//! independent, so a writer appends [`encode_record`] output as steps happen. A file
+//! cut short by a crash decodes up to the last whole record and reports the torn
+//! tail in [`TraceFile::torn_bytes`]; a record whose CRC does not match is an error.
+//!
+//! Payloads use the crate's fixed-width little-endian encoding (see
+//! [`FrameSnapshot::encode`](crate::FrameSnapshot::encode)).
+
+use crate::trace::{Trace, TraceEntry};
+use crate::wire::{DecodeError, Reader, Writer};
+
+pub const TRACE_MAGIC: [u8; 4] = *b"NSCT";
+pub const TRACE_VERSION: u8 = 3;
+
+/// File-level metadata, stored as the first record.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceHeader {
+    /// The traced frame's [`Frame::run_id`](crate::Frame::run_id), if any.
+    pub run_id: Option<u64>,
+}
+
+impl TraceHeader {
+    /// Magic, version and the header record: the start of a trace file.
+    pub fn encode(&self) -> Vec<u8> {
+        let mut w = Writer::default();
+        w.buf.extend_from_slice(&TRACE_MAGIC);
+        w.u8(TRACE_VERSION);
+        let mut body = Writer::default();
+        body.opt_u64(self.run_id);
+        frame_record(&mut w, &body.buf);
+        w.buf
+    }
+}
+
+/// One framed entry record, to append after [`TraceHeader::encode`].
+pub fn encode_record(entry: &TraceEntry) -> Vec<u8> {
+    let mut body = Writer::default();
+    body.u64(entry.step_index);
+    body.u32(entry.position);
+    body.u64(entry.prompt_index as u64);
+    body.u64(entry.steps_taken);
+    body.u64(entry.cost_spent);
+    body.state(entry.state_after);
+    body.outcome(entry.outcome);
+    body.opt_u32(entry.emitted_token);
+    body.u32s(&entry.committed);
+    body.opt_stop_reason(entry.stop_reason);
+    body.u32(entry.receipts.len() as u32);
+    for (kind, value) in &entry.receipts {
+        body.str(kind);
+        body.u64(*value);
+    }
+    let mut w = Writer::default();
+    frame_record(&mut w, &body.buf);
+    w.buf
+}
+
+/// A decoded trace file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TraceFile {
+    pub header: TraceHeader,
+    pub trace: Trace,
+    /// Bytes after the last whole record; non-zero when the writer was cut off.
+    pub torn_bytes: usize,
+}
+
+impl TraceFile {
+    pub fn new(header: TraceHeader, trace: Trace) -> Self {
+        Self {
+            header,
+            trace,
+            torn_bytes: 0,
+        }
+    }
+
+    /// True when the file ended on a record boundary.
+    pub fn is_complete(&self) -> bool {
+        self.torn_bytes == 0
+    }
+
+    pub fn encode(&self) -> Vec<u8> {
+        let mut buf = self.header.encode();
+        for e in &self.trace.entries {
+            buf.extend_from_slice(&encode_record(e));
+        }
+        buf
+    }
+
+    pub fn decode(buf: &[u8]) -> Result<Self, DecodeError> {
+        let mut r = Reader::new(buf);
+        if r.take(4)? != TRACE_MAGIC {
+            return Err(DecodeError::Malformed("trace magic"));
+        }
+        let version = r.u8()?;
+        if version != TRACE_VERSION {
+            return Err(DecodeError::UnsupportedVersion(version));
+        }
+        let mut body = Reader::new(read_record(&mut r)?);
+        let header = TraceHeader {
+            run_id: body.opt_u64()?,
+        };
+
+        let mut trace = Trace::new();
+        loop {
+            let rest = r.remaining();
+            if rest == 0 {
+                break;
+            }
+            let payload = match read_record(&mut r) {
+                Ok(p) => p,
+                Err(DecodeError::Incomplete) => {
+                    return Ok(Self {
+                        header,
+                        trace,
+                        torn_bytes: rest,
+                    })
+                }
+                Err(e) => return Err(e),
+            };
+            trace.push(read_entry(&mut Reader::new(payload))?);
+        }
+        Ok(Self::new(header, trace))
+    }
+}
+
+fn read_entry(r: &mut Reader<'_>) -> Result<TraceEntry, DecodeError> {
+    let step_index = r.u64()?;
+    let position = r.u32()?;
+    let prompt_index = r.usize()?;
+    let steps_taken = r.u64()?;
+    let cost_spent = r.u64()?;
+    let state_after = r.state()?;
+    let outcome = r.outcome()?;
+    let emitted_token = r.opt_u32()?;
+    let committed = r.u32s()?;
+    let stop_reason = r.opt_stop_reason()?;
+    let n = r.u32()?;
+    let receipts = (0..n)
+        .map(|_| Ok((r.string()?, r.u64()?)))
+        .collect::<Result<_, DecodeError>>()?;
+    if !r.is_empty() {
+        return Err(DecodeError::Malformed("trailing bytes in trace record"));
+    }
+    Ok(TraceEntry {
+        step_index,
+        position,
+        prompt_index,
+        steps_taken,
+        cost_spent,
+        state_after,
+        outcome,
+        emitted_token,
+        committed,
+        stop_reason,
+        receipts,
+    })
+}
+
+fn frame_record(w: &mut Writer, payload: &[u8]) {
+    w.u32(payload.len() as u32);
+    w.buf.extend_from_slice(payload);
+    w.u32(crc32(payload));
+}
+
+fn read_record<'a>(r: &mut Reader<'a>) -> Result<&'a [u8], DecodeError> {
+    let len = r.u32()? as usize;
+    let payload = r.take(len)?;
+    if r.u32()? != crc32(payload) {
+        return Err(DecodeError::Malformed("trace record crc"));
+    }
+    Ok(payload)
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut c = i as u32;
+        let mut k = 0;
+        while k < 8 {
+            c = if c & 1 != 0 {
+                0xedb8_8320 ^ (c >> 1)
+            } else {
+                c >> 1
+            };
+            k += 1;
+        }
+        table[i] = c;
+        i += 1;
+    }
+    table
+};
+
+fn crc32(bytes: &[u8]) -> u32 {
+    !bytes.iter().fold(!0u32, |c, &b| {
+        CRC_TABLE[((c ^ b as u32) & 0xff) as usize] ^ (c >> 8)
+    })
+}
@@ -0,0 +1,156 @@
+//! Optional type-state layer over [`FrameState`].
+//!
+//! [`Frame::typed`] borrows a frame as a [`TypedFrame`] whose phase is a type
+//! parameter, so a backend can only do what the phase allows: consume prompt tokens
+//! in [`Prefill`], emit tokens in [`Decode`], read the outcome in [`Finished`].
+//! Transitions consume the typed handle and update the underlying `FrameState`, so
+//! the dynamic frame stays the source of truth and drivers see no difference.
+//!
+//! ```
+//! use nsc_frame::typed::Phased;
+//! use nsc_frame::{Frame, NoopMem, StepResult, StopReason};
+//!
+//! fn step(frame: &mut Frame<NoopMem>) -> StepResult {
+//!     match frame.typed() {
+//!         Phased::Prefill(mut f) => {
+//!             f.consume(usize::MAX);
+//!             f.finish_prefill();
+//!             StepResult::advanced(None)
+//!         }
+//!         Phased::Decode(mut f) if !f.output_exhausted() => f.emit(7),
+//!         Phased::Decode(f) => f.finish(StopReason::MaxTokens),
+//!         Phased::Finished(f) => {
+//!             StepResult::finished(f.stop_reason().unwrap_or(StopReason::MaxTokens))
+//!         }
+//!         Phased::Other(_) => StepResult::yielded(),
+//!     }
+//! }
+//! ```
+
+use std::marker::PhantomData;
+
+use crate::{Frame, FrameState, StepResult, StopReason};
+
+/// Phase marker: consuming the prompt.
+#[derive(Debug, Clone, Copy)]
+pub enum Prefill {}
+
+/// Phase marker: generating output tokens.
+#[derive(Debug, Clone, Copy)]
+pub enum Decode {}
+
+/// Phase marker: done.
+#[derive(Debug, Clone, Copy)]
+pub enum Finished {}
+
+/// A frame borrowed in phase `P`.
+#[derive(Debug)]
+pub struct TypedFrame<'a, M, P> {
+    frame: &'a mut Frame<M>,
+    _phase: PhantomData<P>,
+}
+
+/// A frame sorted by its current phase; see [`Frame::typed`].
+#[derive(Debug)]
+pub enum Phased<'a, M> {
+    Prefill(TypedFrame<'a, M, Prefill>),
+    Decode(TypedFrame<'a, M, Decode>),
+    Finished(TypedFrame<'a, M, Finished>),
+    /// Paused or cancelled: no typed operations apply.
+    Other(&'a mut Frame<M>),
+}
+
+impl<M> Frame<M> {
+    /// Borrow this frame through the type-state layer.
+    pub fn typed(&mut self) -> Phased<'_, M> {
+        match self.state {
+            FrameState::Prefill => Phased::Prefill(TypedFrame::new(self)),
+            FrameState::Decode => Phased::Decode(TypedFrame::new(self)),
+            FrameState::Finished => Phased::Finished(TypedFrame::new(self)),
+            FrameState::Paused(_) | FrameState::Cancelled => Phased::Other(self),
+        }
+    }
+}
+
+impl<'a, M, P> TypedFrame<'a, M, P> {
+    fn new(frame: &'a mut Frame<M>) -> Self {
+        Self {
+            frame,
+            _phase: PhantomData,
+        }
+    }
+
+    fn into_phase<Q>(self, state: FrameState) -> TypedFrame<'a, M, Q> {
+        self.frame.state = state;
+        TypedFrame::new(self.frame)
+    }
+
+    /// Read-only access to the underlying frame.
+    pub fn frame(&self) -> &Frame<M> {
+        self.frame
+    }
+
+    pub fn mem(&mut self) -> &mut M {
+        &mut self.frame.mem
+    }
+
+    /// Give up the typed view and return the dynamic frame.
+    pub fn into_inner(self) -> &'a mut Frame<M> {
+        self.frame
+    }
+}
+
+impl<'a, M> TypedFrame<'a, M, Prefill> {
+    /// Prompt tokens not consumed yet.
+    pub fn remaining_prompt(&self) -> &[u32] {
+        let start = self
+            .frame
+            .prompt_index
+            .min(self.frame.prompt_token_ids.len());
+        &self.frame.prompt_token_ids[start..]
+    }
+
+    /// Mark up to `n` prompt tokens consumed; returns how many were.
+    pub fn consume(&mut self, n: usize) -> usize {
+        let n = n.min(self.remaining_prompt().len());
+        self.frame.prompt_index += n;
+        n
+    }
+
+    /// Move to decoding.
+    pub fn finish_prefill(self) -> TypedFrame<'a, M, Decode> {
+        self.into_phase(FrameState::Decode)
+    }
+}
+
+impl<'a, M> TypedFrame<'a, M, Decode> {
+    /// Whether the output cap has been reached.
+    pub fn output_exhausted(&self) -> bool {
+        self.frame
+            .limits
+            .output_exhausted(self.frame.tokens_generated)
+    }
+
+    /// Commit `token` to the output and advance the cursor.
+    pub fn emit(&mut self, token: u32) -> StepResult {
+        let frame = &mut *self.frame;
+        frame.generated_token_ids.push(token);
+        frame.tokens_generated += 1;
+        frame.cursor.position = frame.cursor.position.saturating_add(1);
+        StepResult::advanced(Some(token))
+    }
+
+    /// Finish the frame with `reason`.
+    pub fn finish(self, reason: StopReason) -> StepResult {
+        self.frame.stop_reason = Some(reason);
+        let _: TypedFrame<'a, M, Finished> = self.into_phase(FrameState::Finished);
+        StepResult::finished(reason)
+    }
+}
+
+impl<'a, M> TypedFrame<'a, M, Finished> {
+    /// Why the frame finished, if recorded.
+    pub fn stop_reason(&self) -> Option<StopReason> {
+        self.frame.stop_reason
+    }
+}
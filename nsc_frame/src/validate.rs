@@ -0,0 +1,271 @@
+//! Frame validation: fallible constructors and the vocabulary law.
+//!
+//! [`Frame::try_new`] and [`Frame::try_with_prompt`] reject limits that would make
+//! a frame finish before doing anything useful (see [`FrameLimits::validate`]).
+//! [`Frame::new`] stays infallible for callers that want those edge cases.
+//!
+//! Output caps are optional only when something else ends the frame: a driver
+//! checks [`Driver::validate`] before each step of a running frame, and
+//! [`DriverCommand::AdjustLimits`](crate::DriverCommand::AdjustLimits) cannot
+//! remove the last limit.
+//!
+//! With [`FrameLimits::vocab_size`] set, prompt ids are checked when the limit is
+//! attached ([`Frame::with_vocab_size`]) and every id a step commits is checked by the
+//! driver right after the backend returns. An out-of-range id means the tokenizer and
+//! backend disagree: the output log is cut back to just before it and the frame is
+//! finished with [`StopReason::TokenOutOfVocab`], so the id never reaches the sink,
+//! the token hasher or the counters.
+
+use std::fmt;
+
+use crate::{
+    Arbiter, Driver, Frame, FrameLimits, FrameState, FrameStepper, Receipt, StepOutcome,
+    StepResult, StopReason,
+};
+
+/// Receipt kind for an emitted or provided id rejected by the vocabulary law; value
+/// is the id.
+pub const LAW_VOCAB: &str = "law.vocab";
+
+/// Why a frame failed validation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FrameError {
+    /// The named limit is set to zero, so the frame could never make progress.
+    ZeroBudget(&'static str),
+    /// The prompt alone fills `max_total_tokens`, leaving no room for output.
+    PromptExceedsContext {
+        prompt_len: usize,
+        max_total_tokens: usize,
+    },
+    /// Prompt token at `index` is not below `vocab_size`.
+    TokenOutOfVocab {
+        index: usize,
+        token: u32,
+        vocab_size: u32,
+    },
+    /// No output cap and nothing else configured to end the frame.
+    Unbounded,
+    /// The cached prefix is longer than the prompt.
+    PrefixExceedsPrompt {
+        prefilled_len: usize,
+        prompt_len: usize,
+    },
+}
+
+impl fmt::Display for FrameError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            FrameError::ZeroBudget(limit) => write!(f, "{} is zero", limit),
+            FrameError::PromptExceedsContext {
+                prompt_len,
+                max_total_tokens,
+            } => write!(
+                f,
+                "prompt of {} tokens leaves no room under max_total_tokens {}",
+                prompt_len, max_total_tokens
+            ),
+            FrameError::Unbounded => write!(f, "unbounded frame has no way to stop"),
+            FrameError::PrefixExceedsPrompt {
+                prefilled_len,
+                prompt_len,
+            } => write!(
+                f,
+                "prefilled prefix of {} tokens exceeds prompt of {}",
+                prefilled_len, prompt_len
+            ),
+            FrameError::TokenOutOfVocab {
+                index,
+                token,
+                vocab_size,
+            } => write!(
+                f,
+                "prompt token {} at index {} is outside vocab of {}",
+                token, index, vocab_size
+            ),
+        }
+    }
+}
+
+impl std::error::Error for FrameError {}
+
+impl FrameLimits {
+    /// Whether some limit of the frame's own ends generation.
+    pub fn is_bounded(&self) -> bool {
+        self.max_new_tokens.is_some()
+            || self.max_total_tokens.is_some()
+            || self.deadline_ticks.is_some()
+            || self.max_cost.is_some()
+    }
+
+    /// Check these limits for a frame over `prompt_token_ids`.
+    pub fn validate(&self, prompt_token_ids: &[u32]) -> Result<(), FrameError> {
+        let zero = [
+            ("max_new_tokens", self.max_new_tokens == Some(0)),
+            ("max_total_tokens", self.max_total_tokens == Some(0)),
+            ("deadline_ticks", self.deadline_ticks == Some(0)),
+            ("max_cost", self.max_cost == Some(0)),
+            ("vocab_size", self.vocab_size == Some(0)),
+            ("max_age_rounds", self.max_age_rounds == Some(0)),
+        ];
+        if let Some((limit, _)) = zero.iter().find(|(_, is_zero)| *is_zero) {
+            return Err(FrameError::ZeroBudget(limit));
+        }
+        if !self.is_bounded() {
+            return Err(FrameError::Unbounded);
+        }
+        if let Some(max_total_tokens) = self.max_total_tokens {
+            if prompt_token_ids.len() >= max_total_tokens {
+                return Err(FrameError::PromptExceedsContext {
+                    prompt_len: prompt_token_ids.len(),
+                    max_total_tokens,
+                });
+            }
+        }
+        self.check_prompt(prompt_token_ids)
+    }
+
+    /// Whether `token` is a valid id under `vocab_size` (always, when unset).
+    pub fn admits_token(&self, token: u32) -> bool {
+        self.vocab_size.map_or(true, |v| token < v)
+    }
+
+    /// First prompt token outside the vocabulary, as an error.
+    pub fn check_prompt(&self, prompt_token_ids: &[u32]) -> Result<(), FrameError> {
+        let Some(vocab_size) = self.vocab_size else {
+            return Ok(());
+        };
+        match prompt_token_ids.iter().position(|&t| t >= vocab_size) {
+            Some(index) => Err(FrameError::TokenOutOfVocab {
+                index,
+                token: prompt_token_ids[index],
+                vocab_size,
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<M> Frame<M> {
+    /// A frame with no prompt under `limits`, if they [validate](FrameLimits::validate).
+    pub fn try_new(mem: M, limits: FrameLimits) -> Result<Self, FrameError> {
+        Self::try_with_prompt(mem, limits, Vec::new())
+    }
+
+    /// A frame over `prompt_token_ids` under `limits`, if they
+    /// [validate](FrameLimits::validate).
+    pub fn try_with_prompt(
+        mem: M,
+        limits: FrameLimits,
+        prompt_token_ids: Vec<u32>,
+    ) -> Result<Self, FrameError> {
+        limits.validate(&prompt_token_ids)?;
+        let mut frame = Self::with_prompt(mem, 0, prompt_token_ids);
+        frame.limits = limits;
+        Ok(frame)
+    }
+
+    /// A frame over `prompt_token_ids` whose first `prefilled_len` tokens the backend
+    /// already holds (e.g. a prefix-cache hit), so prefill starts after them. `limits`
+    /// are validated as by [`Frame::try_with_prompt`].
+    ///
+    /// A driver built on the frame receipts the reused length as `prefill.reused` on
+    /// its first envelope.
+    pub fn with_prompt_resumed(
+        mem: M,
+        limits: FrameLimits,
+        prompt_token_ids: Vec<u32>,
+        prefilled_len: usize,
+    ) -> Result<Self, FrameError> {
+        if prefilled_len > prompt_token_ids.len() {
+            return Err(FrameError::PrefixExceedsPrompt {
+                prefilled_len,
+                prompt_len: prompt_token_ids.len(),
+            });
+        }
+        let mut frame = Self::try_with_prompt(mem, limits, prompt_token_ids)?;
+        frame.prompt_index = prefilled_len;
+        frame.cursor.position = u32::try_from(prefilled_len).unwrap_or(u32::MAX);
+        Ok(frame)
+    }
+
+    /// Declare the vocabulary size and validate the prompt against it.
+    pub fn with_vocab_size(mut self, vocab_size: u32) -> Result<Self, FrameError> {
+        self.limits.vocab_size = Some(vocab_size);
+        self.limits.check_prompt(&self.prompt_token_ids)?;
+        Ok(self)
+    }
+}
+
+/// The `prefill.reused` receipt owed by a frame that has not been stepped yet but
+/// starts partway into its prompt.
+pub(crate) fn reused_prefix_receipt<M>(frame: &Frame<M>) -> Option<Receipt> {
+    let fresh = frame.state == FrameState::Prefill && frame.steps_taken == 0;
+    (fresh && frame.prompt_index > 0).then(|| Receipt::prefill_reused(frame.prompt_index as u64))
+}
+
+impl<M, S, A> Driver<M, S, A>
+where
+    S: FrameStepper<M>,
+    A: Arbiter<M>,
+{
+    /// Check that the frame can end: through its own limits, a finite
+    /// [`DynamicLimits`](crate::DynamicLimits) cap, a stop condition, or a
+    /// [`DriverHandle`](crate::DriverHandle) held outside the driver that can cancel
+    /// it. [`Driver::step`] refuses to step a running frame that fails this check.
+    pub fn validate(&self) -> Result<(), FrameError> {
+        if self.can_stop(&self.frame.limits) {
+            Ok(())
+        } else {
+            Err(FrameError::Unbounded)
+        }
+    }
+
+    /// Whether the frame could end under `limits` and this driver's other stop paths.
+    pub(crate) fn can_stop(&self, limits: &FrameLimits) -> bool {
+        limits.is_bounded()
+            || self
+                .dynamic_limits
+                .as_ref()
+                .is_some_and(|d| d.max_tokens() < usize::MAX)
+            || self.stop_condition.is_some()
+            || self.handle.as_ref().is_some_and(|h| h.is_held_elsewhere())
+    }
+
+    /// Finish the frame when the step committed an id outside the vocabulary, cutting
+    /// the output appended past `output_before` back to just before the first such id.
+    /// Cut the output log back to `len` tokens, taking the removed ones off the token
+    /// count and the cursor. Returns how many were removed.
+    pub(crate) fn truncate_output(&mut self, len: usize) -> usize {
+        let frame = &mut self.frame;
+        let removed = frame.generated_token_ids.len().saturating_sub(len);
+        frame.generated_token_ids.truncate(len);
+        frame.tokens_generated = frame.tokens_generated.saturating_sub(removed);
+        frame.cursor.position = frame.cursor.position.saturating_sub(removed as u32);
+        removed
+    }
+
+    pub(crate) fn enforce_vocab(&mut self, output_before: usize, r: &mut StepResult) {
+        let limits = &self.frame.limits;
+        let log = &self.frame.generated_token_ids;
+        let bad = match log.len() > output_before {
+            true => log
+                .since(output_before)
+                .enumerate()
+                .find(|&(_, t)| !limits.admits_token(t))
+                .map(|(i, t)| (output_before + i, t)),
+            false => r
+                .emitted_token
+                .filter(|&t| !limits.admits_token(t))
+                .map(|t| (output_before, t)),
+        };
+        let Some((at, token)) = bad else {
+            return;
+        };
+        self.truncate_output(at);
+        self.frame.state = FrameState::Finished;
+        r.emitted_token = self.frame.generated_token_ids.get(output_before);
+        r.outcome = StepOutcome::Finished;
+        r.stop_reason = Some(StopReason::TokenOutOfVocab(token));
+        r.receipts.push(Receipt::new(LAW_VOCAB, token as u64));
+    }
+}
@@ -0,0 +1,95 @@
+//! Read-only view of a frame handed to arbiters.
+
+use std::fmt;
+
+use crate::redact::{self, Redaction};
+use crate::tokens::TokenLog;
+use crate::{Frame, FrameLimits, FrameProgress, FrameState, StopReason, Tag};
+
+/// Most recent output tokens visible through a [`FrameView`].
+pub const FRAME_VIEW_SUFFIX: usize = 64;
+
+/// What policy may see of a frame: state, position, limits, counters, tags and a
+/// bounded suffix of the output. Never the frame's memory.
+///
+/// `Debug` prints only that suffix, under the frame's [`Redaction`].
+#[derive(Clone, Copy)]
+pub struct FrameView<'a> {
+    pub state: FrameState,
+    pub position: u32,
+    pub limits: &'a FrameLimits,
+    pub prompt_len: usize,
+    pub prompt_index: usize,
+    pub tokens_generated: usize,
+    pub steps_taken: u64,
+    pub cost_spent: u64,
+    pub stop_reason: Option<StopReason>,
+    pub tags: &'a [Tag],
+    pub run_id: Option<u64>,
+    generated: &'a TokenLog,
+    progress: FrameProgress,
+    redaction: Redaction,
+}
+
+impl<'a> FrameView<'a> {
+    pub fn has_tag(&self, tag: Tag) -> bool {
+        self.tags.contains(&tag)
+    }
+
+    pub fn progress(&self) -> FrameProgress {
+        self.progress
+    }
+
+    /// Up to [`FRAME_VIEW_SUFFIX`] of the latest output tokens, oldest first.
+    pub fn recent_tokens(&self) -> impl Iterator<Item = u32> + 'a {
+        self.generated.suffix(FRAME_VIEW_SUFFIX)
+    }
+}
+
+impl fmt::Debug for FrameView<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let policy = self.redaction;
+        let shown = self.generated.len().min(FRAME_VIEW_SUFFIX);
+        f.debug_struct("FrameView")
+            .field("state", &self.state)
+            .field("position", &self.position)
+            .field("limits", self.limits)
+            .field("prompt_len", &self.prompt_len)
+            .field("prompt_index", &self.prompt_index)
+            .field("tokens_generated", &self.tokens_generated)
+            .field("steps_taken", &self.steps_taken)
+            .field("cost_spent", &self.cost_spent)
+            .field("stop_reason", &self.stop_reason)
+            .field("tags", &self.tags)
+            .field("run_id", &self.run_id)
+            .field(
+                "recent_tokens",
+                &redact::DebugWith(|f: &mut fmt::Formatter<'_>| {
+                    policy.fmt_tokens(f, shown, self.recent_tokens())
+                }),
+            )
+            .field("progress", &self.progress)
+            .finish()
+    }
+}
+
+impl<M> Frame<M> {
+    pub fn view(&self) -> FrameView<'_> {
+        FrameView {
+            state: self.state,
+            position: self.cursor.position,
+            limits: &self.limits,
+            prompt_len: self.prompt_token_ids.len(),
+            prompt_index: self.prompt_index,
+            tokens_generated: self.tokens_generated,
+            steps_taken: self.steps_taken,
+            cost_spent: self.cost_spent,
+            stop_reason: self.stop_reason,
+            tags: &self.tags,
+            run_id: self.run_id,
+            generated: &self.generated_token_ids,
+            progress: self.progress(),
+            redaction: self.redaction,
+        }
+    }
+}
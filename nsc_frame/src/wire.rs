@@ -0,0 +1,331 @@
+//! Little-endian byte encoding shared by the crate's wire formats.
+
+use std::fmt;
+
+use crate::{FrameLimits, FrameState, PauseReason, StepOutcome, StopReason};
+
+/// Why a byte buffer could not be decoded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DecodeError {
+    /// More bytes are needed; retry once they arrive.
+    Incomplete,
+    UnsupportedVersion(u8),
+    UnknownTag {
+        what: &'static str,
+        tag: u8,
+    },
+    Malformed(&'static str),
+    /// A [`SnapshotCodec`](crate::codec::SnapshotCodec) refused to open the payload.
+    Codec(String),
+}
+
+impl fmt::Display for DecodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DecodeError::Incomplete => write!(f, "incomplete input"),
+            DecodeError::UnsupportedVersion(v) => write!(f, "unsupported version {}", v),
+            DecodeError::UnknownTag { what, tag } => write!(f, "unknown {} tag {}", what, tag),
+            DecodeError::Malformed(what) => write!(f, "malformed {}", what),
+            DecodeError::Codec(e) => write!(f, "codec: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for DecodeError {}
+
+#[derive(Debug, Default)]
+pub(crate) struct Writer {
+    pub(crate) buf: Vec<u8>,
+}
+
+impl Writer {
+    pub(crate) fn u8(&mut self, v: u8) {
+        self.buf.push(v);
+    }
+
+    pub(crate) fn u32(&mut self, v: u32) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn u64(&mut self, v: u64) {
+        self.buf.extend_from_slice(&v.to_le_bytes());
+    }
+
+    pub(crate) fn bool(&mut self, v: bool) {
+        self.u8(v as u8);
+    }
+
+    /// LEB128: seven bits per byte, high bit set on all but the last.
+    pub(crate) fn varint(&mut self, mut v: u64) {
+        while v >= 0x80 {
+            self.u8(v as u8 | 0x80);
+            v >>= 7;
+        }
+        self.u8(v as u8);
+    }
+
+    /// Signed varint, zigzag-mapped so small magnitudes stay short.
+    pub(crate) fn zigzag(&mut self, v: i64) {
+        self.varint(((v << 1) ^ (v >> 63)) as u64);
+    }
+
+    pub(crate) fn opt_u32(&mut self, v: Option<u32>) {
+        self.bool(v.is_some());
+        if let Some(v) = v {
+            self.u32(v);
+        }
+    }
+
+    pub(crate) fn opt_u64(&mut self, v: Option<u64>) {
+        self.bool(v.is_some());
+        if let Some(v) = v {
+            self.u64(v);
+        }
+    }
+
+    pub(crate) fn bytes(&mut self, v: &[u8]) {
+        self.u32(v.len() as u32);
+        self.buf.extend_from_slice(v);
+    }
+
+    pub(crate) fn str(&mut self, v: &str) {
+        self.bytes(v.as_bytes());
+    }
+
+    pub(crate) fn u32s(&mut self, v: &[u32]) {
+        self.u32(v.len() as u32);
+        for x in v {
+            self.u32(*x);
+        }
+    }
+
+    pub(crate) fn outcome(&mut self, v: StepOutcome) {
+        self.u8(match v {
+            StepOutcome::Advanced => 0,
+            StepOutcome::Yielded => 1,
+            StepOutcome::Finished => 2,
+            StepOutcome::Stalled => 3,
+        });
+    }
+
+    pub(crate) fn stop_reason(&mut self, v: StopReason) {
+        let (code, payload) = v.to_parts();
+        self.u8(code);
+        self.u32(payload);
+    }
+
+    pub(crate) fn opt_stop_reason(&mut self, v: Option<StopReason>) {
+        self.bool(v.is_some());
+        if let Some(v) = v {
+            self.stop_reason(v);
+        }
+    }
+
+    pub(crate) fn state(&mut self, v: FrameState) {
+        match v {
+            FrameState::Prefill => self.u8(0),
+            FrameState::Decode => self.u8(1),
+            FrameState::Paused(reason) => {
+                self.u8(2);
+                self.u8(match reason {
+                    PauseReason::Offloaded => 0,
+                    PauseReason::AwaitingInput => 1,
+                    PauseReason::Requested => 2,
+                });
+            }
+            FrameState::Finished => self.u8(3),
+            FrameState::Cancelled => self.u8(4),
+        }
+    }
+
+    pub(crate) fn limits(&mut self, v: &FrameLimits) {
+        self.opt_u64(v.max_new_tokens.map(|n| n as u64));
+        self.opt_u64(v.deadline_ticks);
+        self.opt_u64(v.max_cost);
+        self.opt_u64(v.max_total_tokens.map(|n| n as u64));
+        self.opt_u64(v.vocab_size.map(u64::from));
+        self.opt_u64(v.max_age_rounds);
+    }
+}
+
+#[derive(Debug)]
+pub(crate) struct Reader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.buf.is_empty()
+    }
+
+    pub(crate) fn remaining(&self) -> usize {
+        self.buf.len()
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'a [u8], DecodeError> {
+        if self.buf.len() < n {
+            return Err(DecodeError::Incomplete);
+        }
+        let (head, tail) = self.buf.split_at(n);
+        self.buf = tail;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8, DecodeError> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn u32(&mut self) -> Result<u32, DecodeError> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    pub(crate) fn u64(&mut self) -> Result<u64, DecodeError> {
+        let b = self.take(8)?;
+        let mut a = [0u8; 8];
+        a.copy_from_slice(b);
+        Ok(u64::from_le_bytes(a))
+    }
+
+    pub(crate) fn usize(&mut self) -> Result<usize, DecodeError> {
+        usize::try_from(self.u64()?).map_err(|_| DecodeError::Malformed("usize"))
+    }
+
+    pub(crate) fn varint(&mut self) -> Result<u64, DecodeError> {
+        let mut v = 0u64;
+        for shift in (0..64).step_by(7) {
+            let b = self.u8()?;
+            if shift == 63 && b > 1 {
+                return Err(DecodeError::Malformed("varint"));
+            }
+            v |= ((b & 0x7f) as u64) << shift;
+            if b & 0x80 == 0 {
+                return Ok(v);
+            }
+        }
+        Err(DecodeError::Malformed("varint"))
+    }
+
+    pub(crate) fn zigzag(&mut self) -> Result<i64, DecodeError> {
+        let v = self.varint()?;
+        Ok((v >> 1) as i64 ^ -((v & 1) as i64))
+    }
+
+    pub(crate) fn bool(&mut self) -> Result<bool, DecodeError> {
+        match self.u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodeError::Malformed("bool")),
+        }
+    }
+
+    pub(crate) fn opt_u32(&mut self) -> Result<Option<u32>, DecodeError> {
+        Ok(if self.bool()? {
+            Some(self.u32()?)
+        } else {
+            None
+        })
+    }
+
+    pub(crate) fn opt_u64(&mut self) -> Result<Option<u64>, DecodeError> {
+        Ok(if self.bool()? {
+            Some(self.u64()?)
+        } else {
+            None
+        })
+    }
+
+    pub(crate) fn bytes(&mut self) -> Result<&'a [u8], DecodeError> {
+        let n = self.u32()? as usize;
+        self.take(n)
+    }
+
+    pub(crate) fn string(&mut self) -> Result<String, DecodeError> {
+        let b = self.bytes()?;
+        String::from_utf8(b.to_vec()).map_err(|_| DecodeError::Malformed("utf-8 string"))
+    }
+
+    pub(crate) fn u32s(&mut self) -> Result<Vec<u32>, DecodeError> {
+        let n = self.u32()? as usize;
+        if self.buf.len() < n.saturating_mul(4) {
+            return Err(DecodeError::Incomplete);
+        }
+        (0..n).map(|_| self.u32()).collect()
+    }
+
+    pub(crate) fn outcome(&mut self) -> Result<StepOutcome, DecodeError> {
+        match self.u8()? {
+            0 => Ok(StepOutcome::Advanced),
+            1 => Ok(StepOutcome::Yielded),
+            2 => Ok(StepOutcome::Finished),
+            3 => Ok(StepOutcome::Stalled),
+            tag => Err(DecodeError::UnknownTag {
+                what: "outcome",
+                tag,
+            }),
+        }
+    }
+
+    pub(crate) fn stop_reason(&mut self) -> Result<StopReason, DecodeError> {
+        let code = self.u8()?;
+        let payload = self.u32()?;
+        StopReason::from_parts(code, payload).ok_or(DecodeError::UnknownTag {
+            what: "stop reason",
+            tag: code,
+        })
+    }
+
+    pub(crate) fn opt_stop_reason(&mut self) -> Result<Option<StopReason>, DecodeError> {
+        Ok(if self.bool()? {
+            Some(self.stop_reason()?)
+        } else {
+            None
+        })
+    }
+
+    pub(crate) fn state(&mut self) -> Result<FrameState, DecodeError> {
+        match self.u8()? {
+            0 => Ok(FrameState::Prefill),
+            1 => Ok(FrameState::Decode),
+            2 => match self.u8()? {
+                0 => Ok(FrameState::Paused(PauseReason::Offloaded)),
+                1 => Ok(FrameState::Paused(PauseReason::AwaitingInput)),
+                2 => Ok(FrameState::Paused(PauseReason::Requested)),
+                tag => Err(DecodeError::UnknownTag {
+                    what: "pause reason",
+                    tag,
+                }),
+            },
+            3 => Ok(FrameState::Finished),
+            4 => Ok(FrameState::Cancelled),
+            tag => Err(DecodeError::UnknownTag {
+                what: "frame state",
+                tag,
+            }),
+        }
+    }
+
+    pub(crate) fn limits(&mut self) -> Result<FrameLimits, DecodeError> {
+        Ok(FrameLimits {
+            max_new_tokens: match self.opt_u64()? {
+                Some(n) => Some(usize::try_from(n).map_err(|_| DecodeError::Malformed("usize"))?),
+                None => None,
+            },
+            deadline_ticks: self.opt_u64()?,
+            max_cost: self.opt_u64()?,
+            max_total_tokens: match self.opt_u64()? {
+                Some(n) => Some(usize::try_from(n).map_err(|_| DecodeError::Malformed("usize"))?),
+                None => None,
+            },
+            vocab_size: match self.opt_u64()? {
+                Some(n) => Some(u32::try_from(n).map_err(|_| DecodeError::Malformed("u32"))?),
+                None => None,
+            },
+            max_age_rounds: self.opt_u64()?,
+        })
+    }
+}
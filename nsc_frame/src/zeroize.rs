@@ -0,0 +1,82 @@
+//! Wiping of token buffers (feature `zeroize`).
+//!
+//! With the feature enabled, [`TokenLog`] wipes its chunks when dropped or truncated,
+//! so a frame's output is wiped with it. The prompt and the backend's memory are
+//! wiped by [`Frame::zeroize`], or by [`Frame::wipe`] when discarding a frame;
+//! `Frame` itself has no `Drop` impl, so enabling the feature never stops code from
+//! moving fields out of a frame. Writes are volatile so the optimizer cannot elide
+//! them.
+//!
+//! Sealed output chunks are shared between clones of a log; a chunk is wiped by the
+//! last log that holds it.
+//!
+//! Memory is the backend's: implement [`ZeroizeMem`] and attach it with
+//! [`Frame::with_mem_zeroize`] to have the frame call it at the same points.
+//!
+//! [`TokenLog`]: crate::TokenLog
+
+use std::ptr;
+use std::sync::atomic::{compiler_fence, Ordering};
+use std::sync::Arc;
+
+use crate::Frame;
+
+/// Memory that can wipe the user data it holds (e.g. a KV cache's blocks).
+pub trait ZeroizeMem {
+    fn zeroize(&mut self);
+}
+
+impl<M> Frame<M> {
+    /// Call `M`'s [`ZeroizeMem`] hook whenever this frame is wiped.
+    pub fn with_mem_zeroize(mut self) -> Self
+    where
+        M: ZeroizeMem,
+    {
+        self.mem_zeroize = Some(<M as ZeroizeMem>::zeroize);
+        self
+    }
+
+    /// Wipe and empty the prompt and output buffers and run the memory hook, if any.
+    ///
+    /// Counters and limits are left alone; the frame keeps its shape but no content.
+    pub fn zeroize(&mut self) {
+        wipe_vec(&mut self.prompt_token_ids);
+        self.generated_token_ids.zeroize();
+        if let Some(hook) = self.mem_zeroize {
+            hook(&mut self.mem);
+        }
+    }
+
+    /// Wipe the frame as [`Frame::zeroize`] does, then drop it.
+    pub fn wipe(mut self) {
+        self.zeroize();
+    }
+}
+
+/// Overwrite `buf` with zeros.
+pub(crate) fn wipe_slice(buf: &mut [u32]) {
+    for x in buf.iter_mut() {
+        // SAFETY: `x` is a valid, aligned, exclusive reference.
+        unsafe { ptr::write_volatile(x, 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Overwrite the whole allocation of `v` (spare capacity included) and empty it.
+pub(crate) fn wipe_vec(v: &mut Vec<u32>) {
+    wipe_slice(v);
+    v.clear();
+    for slot in v.spare_capacity_mut() {
+        // SAFETY: `slot` points into the vector's allocation; writing a `u32` into
+        // uninitialized capacity is always valid.
+        unsafe { ptr::write_volatile(slot.as_mut_ptr(), 0) };
+    }
+    compiler_fence(Ordering::SeqCst);
+}
+
+/// Wipe `chunk` if this is its last owner.
+pub(crate) fn wipe_chunk(mut chunk: Arc<[u32]>) {
+    if let Some(buf) = Arc::get_mut(&mut chunk) {
+        wipe_slice(buf);
+    }
+}
@@ -0,0 +1,57 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::trace::RecordingStepper;
+use nsc_frame::{Driver, KindSummary, ReceiptStats, StepOutcome};
+
+#[test]
+fn summaries_use_nearest_rank_percentiles() {
+    let mut stats = ReceiptStats::new();
+    for v in (1..=10).rev() {
+        stats.record("x", v);
+    }
+    stats.record("a", 7);
+    let x = KindSummary {
+        count: 10,
+        min: 1,
+        max: 10,
+        mean: 5.5,
+        p50: 5,
+        p90: 9,
+        p99: 10,
+    };
+    assert_eq!(stats.summary("x"), Some(x));
+    assert_eq!(stats.percentile("x", 0.0), Some(1));
+    assert_eq!(stats.summary("missing"), None);
+    let kinds: Vec<_> = stats.summaries().into_iter().map(|(k, _)| k).collect();
+    assert_eq!(kinds, ["a", "x"]);
+}
+
+#[test]
+fn histograms_bucket_values_up_to_each_bound() {
+    let mut stats = ReceiptStats::new();
+    for v in 1..=10 {
+        stats.record("x", v);
+    }
+    assert_eq!(stats.histogram("x", &[3, 7]), [3, 4, 3]);
+    assert_eq!(stats.histogram("missing", &[3]), [0, 0]);
+}
+
+#[test]
+fn stats_from_a_trace_match_the_observed_envelopes() {
+    let mut d = Driver::new(prompt_frame(3, 4), RecordingStepper::new(PromptStepper));
+    let mut live = ReceiptStats::new();
+    loop {
+        let r = d.step().unwrap();
+        live.observe(&r);
+        if r.outcome == StepOutcome::Finished {
+            break;
+        }
+    }
+    let traced = ReceiptStats::from_trace(&d.stepper.trace);
+    assert_eq!(traced.summary("prefill.tokens").unwrap().count, 3);
+    assert_eq!(
+        traced.summary("prefill.tokens"),
+        live.summary("prefill.tokens")
+    );
+}
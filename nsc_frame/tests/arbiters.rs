@@ -0,0 +1,126 @@
+use nsc_frame::{
+    Arbiter, ArbiterContext, BoxedArbiter, CachedArbiter, Decision, Frame, FrameState, FrameView,
+    QuorumArbiter, QuorumRule, StopReason,
+};
+
+/// Votes the same way every time.
+struct Fixed(Decision);
+
+impl Arbiter<()> for Fixed {
+    fn decide(&mut self, _frame: &FrameView<'_>, _ctx: &ArbiterContext) -> Decision {
+        self.0
+    }
+}
+
+fn quorum(rule: QuorumRule, votes: &[Decision]) -> Decision {
+    let voters = votes
+        .iter()
+        .map(|&d| Box::new(Fixed(d)) as BoxedArbiter<()>)
+        .collect();
+    let frame = Frame::new((), 4);
+    QuorumArbiter::new(rule, voters).decide(&frame.view(), &ArbiterContext::default())
+}
+
+#[test]
+fn stop_votes_count_as_refusals_and_keep_their_reason() {
+    let stop = Decision::Stop(StopReason::Cancelled);
+    let votes = [stop, Decision::Allow, stop];
+    assert_eq!(quorum(QuorumRule::Majority, &votes), stop);
+    assert_eq!(
+        quorum(QuorumRule::AnyRefuse, &[Decision::Allow, stop]),
+        stop
+    );
+    assert_eq!(
+        quorum(
+            QuorumRule::Majority,
+            &[stop, Decision::Allow, Decision::Allow]
+        ),
+        Decision::Allow
+    );
+}
+
+#[test]
+fn a_refusal_outranks_stop_votes() {
+    let stop = Decision::Stop(StopReason::Cancelled);
+    let votes = [stop, Decision::Refuse];
+    assert_eq!(quorum(QuorumRule::AnyRefuse, &votes), Decision::Refuse);
+    assert_eq!(quorum(QuorumRule::UnanimousAllow, &votes), Decision::Refuse);
+}
+
+#[test]
+fn votes_are_recorded_with_code_three_for_stop() {
+    let voters: Vec<BoxedArbiter<()>> = vec![
+        Box::new(Fixed(Decision::Allow)),
+        Box::new(Fixed(Decision::Stop(StopReason::Cancelled))),
+    ];
+    let mut q = QuorumArbiter::new(QuorumRule::Majority, voters);
+    let frame = Frame::new((), 4);
+    q.decide(&frame.view(), &ArbiterContext::default());
+    let mut receipts = Vec::new();
+    q.drain_receipts(&mut receipts);
+    let votes: Vec<_> = receipts.iter().map(|r| r.value_u64).collect();
+    assert_eq!(votes, [0, 4 + 3]);
+}
+
+#[test]
+fn each_rule_resolves_mixed_votes_its_own_way() {
+    use Decision::{Allow, Refuse, Yield};
+    let cases = [
+        (QuorumRule::UnanimousAllow, vec![Allow, Allow], Allow),
+        (QuorumRule::UnanimousAllow, vec![Allow, Allow, Yield], Yield),
+        (QuorumRule::UnanimousAllow, vec![Yield, Refuse], Refuse),
+        (QuorumRule::Majority, vec![Allow, Allow, Yield], Allow),
+        (QuorumRule::Majority, vec![Allow, Yield], Yield),
+        (QuorumRule::Majority, vec![Refuse, Allow], Refuse),
+        (QuorumRule::Majority, vec![Refuse, Allow, Allow], Allow),
+        (QuorumRule::AnyRefuse, vec![Allow, Allow, Refuse], Refuse),
+        (QuorumRule::AnyRefuse, vec![Allow, Yield], Yield),
+        (QuorumRule::AnyRefuse, vec![Allow, Allow, Yield], Allow),
+    ];
+    for (rule, votes, expected) in cases {
+        assert_eq!(quorum(rule, &votes), expected, "{:?} {:?}", rule, votes);
+    }
+}
+
+/// Allows every step and counts how often it was asked.
+#[derive(Default)]
+struct Counting {
+    calls: u64,
+}
+
+impl Arbiter<()> for Counting {
+    fn decide(&mut self, _frame: &FrameView<'_>, _ctx: &ArbiterContext) -> Decision {
+        self.calls += 1;
+        Decision::Allow
+    }
+}
+
+#[test]
+fn cached_decisions_expire_after_their_ttl() {
+    let mut cached = CachedArbiter::new(Counting::default(), 3);
+    let (frame, ctx) = (Frame::new((), 4), ArbiterContext::new());
+    for _ in 0..7 {
+        assert_eq!(cached.decide(&frame.view(), &ctx), Decision::Allow);
+    }
+    assert_eq!(cached.inner.calls, 3);
+    assert_eq!((cached.hits, cached.misses), (4, 3));
+}
+
+#[test]
+fn a_state_or_context_change_bypasses_the_cache() {
+    let mut cached = CachedArbiter::new(Counting::default(), 100);
+    let (mut frame, ctx) = (Frame::new((), 4), ArbiterContext::new());
+    cached.decide(&frame.view(), &ctx);
+    cached.decide(&frame.view(), &ctx);
+    assert_eq!(cached.inner.calls, 1);
+
+    frame.state = FrameState::Decode;
+    cached.decide(&frame.view(), &ctx);
+    assert_eq!(cached.inner.calls, 2);
+    ctx.set_load_permille(900);
+    cached.decide(&frame.view(), &ctx);
+    assert_eq!(cached.inner.calls, 3);
+    cached.invalidate();
+    cached.decide(&frame.view(), &ctx);
+    assert_eq!(cached.inner.calls, 4);
+}
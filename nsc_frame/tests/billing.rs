@@ -0,0 +1,68 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::{
+    Billing, Driver, Frame, FrameState, FrameStepper, NoopMem, StepOutcome, StepResult, StopReason,
+};
+
+/// Stalls on its first step, then finishes.
+struct StallOnce;
+
+impl FrameStepper<NoopMem> for StallOnce {
+    fn step(&mut self, frame: &mut Frame<NoopMem>) -> Result<StepResult, String> {
+        if frame.steps_taken == 0 {
+            return Ok(StepResult::stalled());
+        }
+        frame.state = FrameState::Finished;
+        Ok(StepResult::finished(StopReason::MaxTokens))
+    }
+}
+
+#[test]
+fn invoices_match_what_the_driver_charged() {
+    let mut d = Driver::new(prompt_frame(3, 4), PromptStepper);
+    let mut billing = Billing::new();
+    loop {
+        let r = d.step().unwrap();
+        billing.record(1, "t", &r);
+        if r.outcome == StepOutcome::Finished {
+            break;
+        }
+    }
+    let invoice = billing.frame_invoice(1).unwrap();
+    assert_eq!(invoice.cost_units, d.frame.cost_spent);
+    assert_eq!(invoice.steps, d.frame.steps_taken);
+    assert_eq!(invoice.prefill_tokens, 3);
+    assert_eq!(invoice.tokens_generated, 4);
+}
+
+#[test]
+fn stalled_backend_steps_are_billed() {
+    let mut d = Driver::new(prompt_frame(0, 4), StallOnce);
+    let mut billing = Billing::new();
+    let r = d.step().unwrap();
+    assert_eq!(r.outcome, StepOutcome::Stalled);
+    billing.record(1, "t", &r);
+    billing.record(1, "t", &d.step().unwrap());
+    let invoice = billing.frame_invoice(1).unwrap();
+    assert_eq!(invoice.steps, 2);
+    assert_eq!(invoice.cost_units, 2);
+    assert_eq!(invoice.cost_units, d.frame.cost_spent);
+}
+
+#[test]
+fn receipt_sums_saturate() {
+    let mut billing = Billing::new();
+    let r = StepResult::advanced(Some(1))
+        .with_cost(u64::MAX)
+        .with_receipt(nsc_frame::billing::PREFILL_TOKENS, u64::MAX)
+        .with_receipt(nsc_frame::billing::PREFILL_TOKENS, 1);
+    billing.record(1, "t", &r);
+    billing.record(2, "t", &r);
+    let frame = billing.frame_invoice(1).unwrap();
+    assert_eq!(frame.prefill_tokens, u64::MAX);
+    let tenant = billing.tenant_invoice("t").unwrap();
+    assert_eq!(tenant.cost_units, u64::MAX);
+    assert_eq!(tenant.prefill_tokens, u64::MAX);
+    assert_eq!(tenant.steps, 2);
+}
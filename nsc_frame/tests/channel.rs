@@ -0,0 +1,97 @@
+use std::thread;
+
+use nsc_frame::channel::{TryRecvError, TrySendError};
+use nsc_frame::{token_channel, StopReason, TokenEvent};
+
+#[test]
+fn threaded_producer_delivers_every_event_in_order() {
+    const N: u32 = 20_000;
+    let (mut tx, mut rx) = token_channel(4);
+    let producer = thread::spawn(move || {
+        let events = (0..N)
+            .map(TokenEvent::Token)
+            .chain([TokenEvent::End(StopReason::StopSequence(3))]);
+        for event in events {
+            while let Err(TrySendError::Full(_)) = tx.try_send(event) {
+                thread::yield_now();
+            }
+        }
+    });
+
+    let mut got = Vec::new();
+    loop {
+        match rx.try_recv() {
+            Ok(event) => got.push(event),
+            Err(TryRecvError::Empty) => thread::yield_now(),
+            Err(e) => {
+                assert_eq!(e, TryRecvError::Disconnected);
+                break;
+            }
+        }
+    }
+    producer.join().unwrap();
+    assert_eq!(got.len(), N as usize + 1);
+    assert!(got[..N as usize]
+        .iter()
+        .zip(0..)
+        .all(|(e, i)| *e == TokenEvent::Token(i)));
+    assert_eq!(
+        got[N as usize],
+        TokenEvent::End(StopReason::StopSequence(3))
+    );
+}
+
+#[test]
+fn dropping_the_receiver_disconnects_the_sender() {
+    let (mut tx, rx) = token_channel(2);
+    drop(rx);
+    let event = TokenEvent::Token(1);
+    assert_eq!(tx.try_send(event), Err(TrySendError::Disconnected(event)));
+}
+
+#[test]
+fn dropping_the_sender_disconnects_after_the_backlog() {
+    let (mut tx, mut rx) = token_channel(2);
+    tx.try_send(TokenEvent::Token(1)).unwrap();
+    tx.try_send(TokenEvent::Token(2)).unwrap();
+    drop(tx);
+    assert_eq!(rx.try_recv(), Ok(TokenEvent::Token(1)));
+    assert_eq!(rx.try_recv(), Ok(TokenEvent::Token(2)));
+    assert_eq!(rx.try_recv(), Err(TryRecvError::Disconnected));
+}
+
+#[test]
+fn full_and_empty_hold_across_wraparound() {
+    let (mut tx, mut rx) = token_channel(3);
+    let mut next = 0;
+    for _ in 0..5 {
+        for _ in 0..3 {
+            tx.try_send(TokenEvent::Token(next)).unwrap();
+            next += 1;
+        }
+        assert!(tx.is_full());
+        let extra = TokenEvent::Token(next);
+        assert_eq!(tx.try_send(extra), Err(TrySendError::Full(extra)));
+
+        let mut out = Vec::new();
+        assert_eq!(rx.drain_into(&mut out), 3);
+        let expected: Vec<_> = (next - 3..next).map(TokenEvent::Token).collect();
+        assert_eq!(out, expected);
+        assert_eq!(rx.try_recv(), Err(TryRecvError::Empty));
+    }
+}
+
+#[test]
+fn unknown_slot_bits_are_rejected() {
+    for event in [
+        TokenEvent::Token(u32::MAX),
+        TokenEvent::End(StopReason::Custom(7)),
+        TokenEvent::End(StopReason::Expired),
+    ] {
+        assert_eq!(TokenEvent::from_bits(event.to_bits()), Some(event));
+    }
+    assert_eq!(TokenEvent::from_bits(0), None);
+    assert_eq!(TokenEvent::from_bits(3 << 56), None);
+    assert_eq!(TokenEvent::from_bits((2 << 56) | (200 << 32)), None);
+    assert_eq!(TokenEvent::from_bits((1 << 56) | (1 << 32)), None);
+}
@@ -0,0 +1,45 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::{Driver, PauseReason, StepOutcome, StepResult, StopReason};
+
+fn receipt(r: &StepResult, kind: &str) -> Option<u64> {
+    r.receipts
+        .iter()
+        .find(|x| x.kind == kind)
+        .map(|x| x.value_u64)
+}
+
+#[test]
+fn abort_finishes_with_the_callers_reason_and_keeps_the_output() {
+    let mut d = Driver::new(prompt_frame(1, 8), PromptStepper);
+    for _ in 0..3 {
+        d.step().unwrap();
+    }
+    let output = d.frame.generated_token_ids.to_vec();
+    d.abort(StopReason::Custom(9));
+    let r = d.step().unwrap();
+    assert_eq!(r.outcome, StepOutcome::Finished);
+    assert_eq!(r.stop_reason, Some(StopReason::Custom(9)));
+    assert_eq!(receipt(&r, "command.abort"), Some(1));
+    assert_eq!(d.frame.generated_token_ids.to_vec(), output);
+    assert_eq!(output.len(), 2);
+}
+
+#[test]
+fn abort_through_the_handle_reaches_paused_frames_but_not_finished_ones() {
+    let mut d = Driver::new(prompt_frame(1, 8), PromptStepper);
+    let handle = d.handle();
+    d.step().unwrap();
+    d.frame.pause(PauseReason::Requested);
+    handle.abort(StopReason::Custom(1));
+    let r = d.step().unwrap();
+    assert_eq!(r.stop_reason, Some(StopReason::Custom(1)));
+    assert_eq!(receipt(&r, "command.abort"), Some(1));
+    assert_eq!(d.frame.paused_from, None);
+
+    handle.abort(StopReason::Custom(2));
+    let again = d.step().unwrap();
+    assert_eq!(receipt(&again, "command.abort"), Some(0));
+    assert_eq!(again.stop_reason, Some(StopReason::Custom(1)));
+}
@@ -0,0 +1,77 @@
+//! Backends shared by the integration tests.
+
+#![allow(dead_code)]
+
+use nsc_frame::{Frame, FrameState, FrameStepper, NoopMem, Receipt, StepResult, StopReason};
+
+/// Prefills one prompt token per step, then emits `position * 7 % 100` per step
+/// until the output cap.
+#[derive(Debug, Default)]
+pub struct PromptStepper;
+
+impl<M> FrameStepper<M> for PromptStepper {
+    fn step(&mut self, frame: &mut Frame<M>) -> Result<StepResult, String> {
+        match frame.state {
+            FrameState::Prefill => {
+                if frame.prompt_index < frame.prompt_token_ids.len() {
+                    frame.prompt_index += 1;
+                    frame.cursor.position += 1;
+                }
+                if frame.prompt_index == frame.prompt_token_ids.len() {
+                    frame.state = FrameState::Decode;
+                }
+                Ok(StepResult::advanced(None).with_receipt("prefill.tokens", 1))
+            }
+            FrameState::Decode => {
+                if frame.limits.output_exhausted(frame.tokens_generated) {
+                    frame.state = FrameState::Finished;
+                    return Ok(StepResult::finished(StopReason::MaxTokens));
+                }
+                let tok = frame.cursor.position * 7 % 100;
+                frame.generated_token_ids.push(tok);
+                frame.tokens_generated += 1;
+                frame.cursor.position += 1;
+                Ok(StepResult::advanced(Some(tok)))
+            }
+            _ => Err("prompt stepper: frame not running".to_string()),
+        }
+    }
+}
+
+/// Commits `width` tokens per decode step through `generated_token_ids`, reporting
+/// the first as the envelope's token and a `step.cost` of 2.
+#[derive(Debug)]
+pub struct WideStepper {
+    pub width: u32,
+}
+
+impl FrameStepper<NoopMem> for WideStepper {
+    fn step(&mut self, frame: &mut Frame<NoopMem>) -> Result<StepResult, String> {
+        match frame.state {
+            FrameState::Prefill => {
+                frame.state = FrameState::Decode;
+                Ok(StepResult::advanced(None))
+            }
+            FrameState::Decode => {
+                if frame.limits.output_exhausted(frame.tokens_generated) {
+                    frame.state = FrameState::Finished;
+                    return Ok(StepResult::finished(StopReason::MaxTokens));
+                }
+                let first = frame.cursor.position;
+                for i in 0..self.width {
+                    frame.generated_token_ids.push(first + i);
+                }
+                frame.tokens_generated += self.width as usize;
+                frame.cursor.position += self.width;
+                let mut r = StepResult::advanced(Some(first));
+                r.receipts.push(Receipt::new(nsc_frame::STEP_COST, 2));
+                Ok(r)
+            }
+            _ => Err("wide stepper: frame not running".to_string()),
+        }
+    }
+}
+
+pub fn prompt_frame(prompt_len: u32, max_new_tokens: usize) -> Frame<NoopMem> {
+    Frame::with_prompt(NoopMem, max_new_tokens, (0..prompt_len).collect())
+}
@@ -0,0 +1,80 @@
+use nsc_frame::compact::CompactTrace;
+use nsc_frame::protocol::DecodeError;
+use nsc_frame::trace::{Trace, TraceEntry};
+use nsc_frame::{FrameState, StepOutcome};
+
+fn entry(step_index: u64, position: u32) -> TraceEntry {
+    TraceEntry {
+        step_index,
+        position,
+        prompt_index: 0,
+        steps_taken: 0,
+        cost_spent: 0,
+        state_after: FrameState::Decode,
+        outcome: StepOutcome::Advanced,
+        emitted_token: None,
+        committed: Vec::new(),
+        stop_reason: None,
+        receipts: Vec::new(),
+    }
+}
+
+fn trace(entries: &[(u64, u32)]) -> Trace {
+    let mut t = Trace::new();
+    for &(step, pos) in entries {
+        t.push(entry(step, pos));
+    }
+    t
+}
+
+fn varint(mut v: u64, out: &mut Vec<u8>) {
+    while v >= 0x80 {
+        out.push(v as u8 | 0x80);
+        v >>= 7;
+    }
+    out.push(v as u8);
+}
+
+/// `base`'s bytes with its entries (all zero, six bytes each) replaced by
+/// `(step delta, position delta)` pairs, zigzag encoded.
+fn with_deltas(base: &Trace, deltas: &[(i64, i64)]) -> CompactTrace {
+    let mut bytes = base.compact().into_bytes();
+    bytes.truncate(bytes.len() - 6 * deltas.len());
+    for &(step, pos) in deltas {
+        for d in [step, pos] {
+            varint(((d << 1) ^ (d >> 63)) as u64, &mut bytes);
+        }
+        bytes.extend_from_slice(&[0, 0, 0, 0]);
+    }
+    CompactTrace::from_bytes(bytes)
+}
+
+#[test]
+fn large_step_indices_round_trip() {
+    let big = i64::MAX as u64;
+    let t = trace(&[(big - 1, 3), (big, u32::MAX), (big + 7, 0), (u64::MAX, 9)]);
+    assert_eq!(t.compact().expand().unwrap(), t);
+}
+
+#[test]
+fn out_of_range_deltas_are_malformed() {
+    let one = trace(&[(0, 0)]);
+    assert_eq!(
+        with_deltas(&one, &[(-1, 0)]).expand(),
+        Err(DecodeError::Malformed("step index"))
+    );
+    assert_eq!(
+        with_deltas(&one, &[(0, i64::from(u32::MAX) + 1)]).expand(),
+        Err(DecodeError::Malformed("position"))
+    );
+    let three = trace(&[(0, 0), (1, 0), (2, 0)]);
+    assert_eq!(
+        with_deltas(&three, &[(i64::MAX, 0), (i64::MAX, 0), (0, 0)]).expand(),
+        Err(DecodeError::Malformed("step index"))
+    );
+    let two = trace(&[(0, 0), (1, 0)]);
+    assert_eq!(
+        with_deltas(&two, &[(0, i64::MAX), (0, i64::MAX)]).expand(),
+        Err(DecodeError::Malformed("position"))
+    );
+}
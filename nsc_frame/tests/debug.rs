@@ -0,0 +1,26 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::{DebugDriver, Driver, FrameState, StepOutcome};
+
+#[test]
+fn back_inside_prefill_restores_prompt_progress() {
+    let mut d = DebugDriver::new(prompt_frame(5, 3), PromptStepper);
+    for _ in 0..3 {
+        d.step().unwrap();
+    }
+    assert_eq!(d.driver.frame.prompt_index, 3);
+
+    d.back().unwrap();
+    assert_eq!(d.driver.frame.prompt_index, 2);
+    assert_eq!(d.driver.frame.cursor.position, 2);
+    assert_eq!(d.driver.frame.state, FrameState::Prefill);
+
+    while d.step().unwrap().outcome != StepOutcome::Finished {}
+    let mut plain = Driver::new(prompt_frame(5, 3), PromptStepper);
+    while plain.step().unwrap().outcome != StepOutcome::Finished {}
+    assert_eq!(
+        d.driver.frame.generated_token_ids.to_vec(),
+        plain.frame.generated_token_ids.to_vec()
+    );
+}
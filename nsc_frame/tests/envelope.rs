@@ -0,0 +1,29 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::{Driver, DriverCommand, FrameState, StepOutcome};
+
+#[test]
+fn every_envelope_is_numbered_and_carries_the_state_it_left() {
+    let mut d = Driver::new(prompt_frame(2, 2), PromptStepper);
+    let mut seen = Vec::new();
+    for i in 0..8 {
+        if i == 3 {
+            d.enqueue(DriverCommand::Pause);
+        }
+        if i == 4 {
+            d.enqueue(DriverCommand::Resume);
+        }
+        let r = d.step().unwrap();
+        assert_eq!(r.state_after, d.frame.state);
+        seen.push((r.step_index, r.outcome, r.state_after));
+    }
+    let indices: Vec<u64> = seen.iter().map(|s| s.0).collect();
+    assert_eq!(indices, (0..8).collect::<Vec<_>>());
+    assert_eq!(seen[0].2, FrameState::Prefill);
+    assert_eq!(seen[1].2, FrameState::Decode);
+    assert_eq!(seen[3].1, StepOutcome::Yielded);
+    assert!(matches!(seen[3].2, FrameState::Paused(_)));
+    // Finished frames keep numbering their re-step envelopes.
+    assert_eq!(seen[7], (7, StepOutcome::Finished, FrameState::Finished));
+}
@@ -0,0 +1,41 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::{Driver, DynamicLimits, FrameState, StepOutcome, StopReason};
+
+fn run<S: nsc_frame::FrameStepper<nsc_frame::NoopMem>>(d: &mut Driver<nsc_frame::NoopMem, S>) {
+    while d.step().unwrap().outcome != StepOutcome::Finished {}
+}
+
+#[test]
+fn extending_a_finished_frame_continues_decoding() {
+    let mut d = Driver::new(prompt_frame(2, 3), PromptStepper);
+    run(&mut d);
+    assert_eq!(d.frame.stop_reason, Some(StopReason::MaxTokens));
+
+    d.extend_limit(2).unwrap();
+    assert_eq!(d.frame.state, FrameState::Decode);
+    let r = d.step().unwrap();
+    assert!(r
+        .receipts
+        .iter()
+        .any(|x| x.kind == "limits.extend" && x.value_u64 == 2));
+    run(&mut d);
+    assert_eq!(d.frame.tokens_generated, 5);
+}
+
+#[test]
+fn a_lower_dynamic_limit_refuses_the_extension() {
+    let limits = DynamicLimits::new(3);
+    let mut d = Driver::new(prompt_frame(2, 3), PromptStepper).with_dynamic_limits(limits.clone());
+    run(&mut d);
+
+    assert!(d.extend_limit(2).is_err());
+    assert_eq!(d.frame.limits.max_new_tokens, Some(3));
+    assert_eq!(d.frame.state, FrameState::Finished);
+
+    limits.set_max_tokens(5);
+    d.extend_limit(2).unwrap();
+    run(&mut d);
+    assert_eq!(d.frame.tokens_generated, 5);
+}
@@ -0,0 +1,67 @@
+#![cfg(feature = "fuzz")]
+
+mod common;
+
+use common::PromptStepper;
+use nsc_frame::fuzz::{differential, FuzzCase, FuzzConfig};
+use nsc_frame::{Frame, FrameState, FrameStepper, NoopMem, StepResult, StopReason};
+
+/// [`PromptStepper`] with every decoded token off by one.
+struct OffByOne;
+
+impl FrameStepper<NoopMem> for OffByOne {
+    fn step(&mut self, frame: &mut Frame<NoopMem>) -> Result<StepResult, String> {
+        let mut r = PromptStepper.step(frame)?;
+        if let Some(tok) = r.emitted_token {
+            frame
+                .generated_token_ids
+                .truncate(frame.generated_token_ids.len() - 1);
+            frame.generated_token_ids.push(tok + 1);
+            r.emitted_token = Some(tok + 1);
+        }
+        if frame.state == FrameState::Finished && r.stop_reason.is_none() {
+            r.stop_reason = Some(StopReason::MaxTokens);
+        }
+        Ok(r)
+    }
+}
+
+#[test]
+fn generated_cases_pass_validation() {
+    let config = FuzzConfig {
+        max_prompt_len: 8,
+        max_new_tokens: 4,
+        ..FuzzConfig::default()
+    };
+    for seed in 0..2_000 {
+        let case = FuzzCase::generate(seed, &config);
+        assert_eq!(
+            case.limits.validate(&case.prompt_token_ids),
+            Ok(()),
+            "{:?}",
+            case
+        );
+    }
+}
+
+#[test]
+fn shrunk_divergences_pass_validation() {
+    let config = FuzzConfig::default();
+    let mut found = 0;
+    for seed in 0..20 {
+        let Err(d) = differential([seed], &config, || NoopMem, || PromptStepper, || OffByOne)
+        else {
+            // Budgets this tight can end the frame before it decodes anything.
+            continue;
+        };
+        let case = &d.case;
+        assert_eq!(
+            case.limits.validate(&case.prompt_token_ids),
+            Ok(()),
+            "{}",
+            d
+        );
+        found += 1;
+    }
+    assert!(found > 0);
+}
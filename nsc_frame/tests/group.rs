@@ -0,0 +1,365 @@
+mod common;
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use common::PromptStepper;
+use nsc_frame::group::{GROUP_BACKOFF, GROUP_BOOST, GROUP_EXPIRED, GROUP_STARVED};
+use nsc_frame::{
+    Arbiter, ArbiterContext, Candidate, Decision, Driver, Frame, FrameError, FrameGroup,
+    FrameLimits, FrameState, FrameStepper, FrameView, PauseReason, Receipt, ReceiptSink, Scheduler,
+    StepOutcome, StepResult, StopReason, YieldBackoff,
+};
+
+/// [`PromptStepper`] that fails its first step when `fail_first` is set.
+struct FailFirst {
+    fail_first: bool,
+}
+
+impl FrameStepper<()> for FailFirst {
+    fn step(&mut self, frame: &mut Frame<()>) -> Result<StepResult, String> {
+        if std::mem::take(&mut self.fail_first) {
+            return Err("backend down".to_string());
+        }
+        PromptStepper.step(frame)
+    }
+}
+
+fn group(fail: &[bool]) -> FrameGroup<(), FailFirst> {
+    let mut g = FrameGroup::new(());
+    for &fail_first in fail {
+        let frame = g.frame_with_prompt(4, Vec::new());
+        g.push(Driver::new(frame, FailFirst { fail_first }));
+    }
+    g
+}
+
+#[test]
+fn a_failed_round_keeps_the_envelopes_already_stepped() {
+    let mut g = group(&[false, true, false]);
+    let e = g.step_round().unwrap_err();
+    assert_eq!(e.member, 1);
+    assert_eq!(e.error, "backend down");
+    assert_eq!(e.stepped.len(), 1);
+    assert_eq!(e.stepped[0].0, 0);
+    assert_eq!(g.members()[0].frame.steps_taken, 1);
+    assert_eq!(g.members()[2].frame.steps_taken, 0);
+}
+
+#[test]
+fn boost_receipts_attach_to_the_step_that_was_boosted() {
+    let mut g = group(&[true, false]).with_finish_boost(8);
+    assert!(g.step_round().is_err());
+    let round = g.step_round().unwrap();
+    for (_, r) in &round {
+        let boosts = r.receipts.iter().filter(|x| x.kind == GROUP_BOOST).count();
+        assert_eq!(boosts, 1);
+    }
+}
+
+#[test]
+fn yielded_members_sit_out_the_backoff() {
+    let mut g = group(&[false, false]).with_yield_backoff(YieldBackoff::Fixed(2));
+    g.member_mut(0).unwrap().frame.pause(PauseReason::Requested);
+
+    let stepped = |round: &[(usize, StepResult)]| round.iter().map(|(i, _)| *i).collect::<Vec<_>>();
+    let first = g.step_round().unwrap();
+    assert_eq!(first[0].1.outcome, StepOutcome::Yielded);
+    assert_eq!(stepped(&g.step_round().unwrap()), [1]);
+    assert_eq!(stepped(&g.step_round().unwrap()), [1]);
+
+    g.member_mut(0).unwrap().frame.resume();
+    let round = g.step_round().unwrap();
+    assert_eq!(stepped(&round), [0, 1]);
+    let backoff: Vec<_> = round[0]
+        .1
+        .receipts
+        .iter()
+        .filter(|x| x.kind == GROUP_BACKOFF)
+        .map(|x| x.value_u64)
+        .collect();
+    assert_eq!(backoff, [2]);
+    assert_eq!(stepped(&g.step_round().unwrap()), [0, 1]);
+}
+
+#[test]
+fn exponential_backoff_doubles_up_to_its_cap() {
+    let backoff = YieldBackoff::Exponential { base: 1, max: 4 };
+    let rounds: Vec<_> = (1..=5).map(|streak| backoff.rounds(streak)).collect();
+    assert_eq!(rounds, [1, 2, 4, 4, 4]);
+    assert_eq!(YieldBackoff::Fixed(3).rounds(9), 3);
+}
+
+#[test]
+fn stats_count_yields_waits_and_age() {
+    let mut g = group(&[false, false]).with_yield_backoff(YieldBackoff::Fixed(2));
+    g.member_mut(0).unwrap().frame.pause(PauseReason::Requested);
+    for _ in 0..3 {
+        g.step_round().unwrap();
+    }
+
+    let stats = g.all_stats();
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats[0], g.frame_stats(0).unwrap());
+    assert_eq!(
+        (stats[0].yields, stats[0].waits, stats[0].age_rounds),
+        (1, 2, 3)
+    );
+    assert_eq!(stats[0].state, FrameState::Paused(PauseReason::Requested));
+    assert_eq!((stats[1].yields, stats[1].waits), (0, 0));
+    assert_eq!(stats[1].steps_taken, 3);
+    assert!(g.frame_stats(2).is_none());
+
+    g.member_mut(0).unwrap().frame.resume();
+    while !g.is_finished() {
+        g.step_round().unwrap();
+    }
+    let done = g.frame_stats(0).unwrap();
+    assert_eq!(done.tokens_generated, 4);
+    assert_eq!(done.stop_reason, Some(StopReason::MaxTokens));
+}
+
+/// Member indices `g` steps over `ticks` ticks; `None` for ticks that stepped nobody.
+fn ticks<S: FrameStepper<()>>(g: &mut FrameGroup<(), S>, ticks: usize) -> Vec<Option<usize>> {
+    (0..ticks)
+        .map(|_| g.step_tick().unwrap().map(|(i, _)| i))
+        .collect()
+}
+
+#[test]
+fn ticks_round_robin_by_default() {
+    let mut g = group(&[false, false, false]);
+    assert_eq!(ticks(&mut g, 6), [0, 1, 2, 0, 1, 2].map(Some));
+    assert_eq!(g.frame_stats(0).unwrap().idle_rounds, 2);
+    assert_eq!(g.frame_stats(2).unwrap().idle_rounds, 0);
+}
+
+/// Always the highest member index offered.
+struct Last;
+
+impl Scheduler for Last {
+    fn pick(&mut self, _round: u64, ready: &[Candidate]) -> Option<usize> {
+        ready.last().map(|c| c.index)
+    }
+
+    fn receipts(&mut self, picked: &Candidate, out: &mut Vec<Receipt>) {
+        out.push(Receipt::new("test.picked", picked.index as u64));
+    }
+}
+
+#[test]
+fn a_custom_scheduler_picks_and_annotates_each_tick() {
+    let mut g = group(&[false, false]).with_scheduler(Last);
+    let (i, r) = g.step_tick().unwrap().unwrap();
+    assert_eq!(i, 1);
+    assert!(r
+        .receipts
+        .iter()
+        .any(|x| x.kind == "test.picked" && x.value_u64 == 1));
+    // Member 1 runs to the end before member 0 is stepped at all.
+    assert_eq!(ticks(&mut g, 6), [1, 1, 1, 1, 1, 0].map(Some));
+    assert!(g.members()[1].frame.state == FrameState::Finished);
+}
+
+/// Picks a member index that no group has.
+struct Stray;
+
+impl Scheduler for Stray {
+    fn pick(&mut self, _round: u64, _ready: &[Candidate]) -> Option<usize> {
+        Some(99)
+    }
+}
+
+#[test]
+fn picking_a_member_that_was_not_offered_fails_the_tick() {
+    let mut g = group(&[false]).with_scheduler(Stray);
+    let e = g.step_tick().unwrap_err();
+    assert_eq!(e.member, 99);
+    assert_eq!(g.members()[0].frame.steps_taken, 0);
+}
+
+#[test]
+fn expired_members_are_stepped_before_the_scheduler_picks() {
+    let mut g = group(&[false, false]).with_scheduler(Last);
+    g.member_mut(0).unwrap().frame.limits.max_age_rounds = Some(2);
+    assert_eq!(ticks(&mut g, 3), [1, 1, 0].map(Some));
+    assert_eq!(g.members()[0].frame.stop_reason, Some(StopReason::Expired));
+}
+
+#[test]
+fn a_tick_with_nothing_live_steps_nobody() {
+    let mut g = group(&[]);
+    assert_eq!(g.step_tick().unwrap().map(|(i, _)| i), None);
+}
+
+/// Values of the `kind` receipts on `r`.
+fn receipts(r: &StepResult, kind: &str) -> Vec<u64> {
+    r.receipts
+        .iter()
+        .filter(|x| x.kind == kind)
+        .map(|x| x.value_u64)
+        .collect()
+}
+
+#[test]
+fn starved_members_are_stepped_before_the_scheduler_picks() {
+    let mut g = group(&[false, false, false])
+        .with_scheduler(Last)
+        .with_starvation_limit(2);
+    let mut starved = Vec::new();
+    for _ in 0..6 {
+        let (i, r) = g.step_tick().unwrap().unwrap();
+        starved.push((i, receipts(&r, GROUP_STARVED)));
+    }
+    assert_eq!(
+        starved,
+        [
+            (2, vec![]),
+            (2, vec![]),
+            (0, vec![2]),
+            (1, vec![3]),
+            (2, vec![2]),
+            (0, vec![2]),
+        ]
+    );
+}
+
+#[test]
+fn starvation_cuts_a_backoff_short() {
+    let mut g = group(&[false, false])
+        .with_yield_backoff(YieldBackoff::Fixed(10))
+        .with_starvation_limit(3);
+    g.member_mut(0).unwrap().frame.pause(PauseReason::Requested);
+    g.step_round().unwrap();
+    g.member_mut(0).unwrap().frame.resume();
+    for _ in 0..3 {
+        assert_eq!(g.step_round().unwrap().len(), 1);
+    }
+    let round = g.step_round().unwrap();
+    assert_eq!(round[0].0, 0);
+    assert_eq!(receipts(&round[0].1, GROUP_STARVED), [3]);
+    assert_eq!(receipts(&round[0].1, GROUP_BACKOFF), [3]);
+    assert_eq!(g.frame_stats(0).unwrap().idle_rounds, 0);
+}
+
+/// Counts the envelopes a driver returns.
+struct Envelopes(Arc<AtomicUsize>);
+
+impl ReceiptSink for Envelopes {
+    fn accept(&mut self, _receipts: &[Receipt]) {
+        self.0.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+#[test]
+fn drain_cancels_failed_members_and_steps_them_once_more() {
+    let mut g = group(&[false, true, false]);
+    let envelopes = Arc::new(AtomicUsize::new(0));
+    g.member_mut(1).unwrap().receipt_sink = Some(Box::new(Envelopes(envelopes.clone())));
+    let report = g.drain(10);
+    assert_eq!(report.failed, [(1, "backend down".to_string())]);
+    assert_eq!(envelopes.load(Ordering::Relaxed), 1);
+    assert_eq!(report.members[1].frame.state, FrameState::Cancelled);
+    assert_eq!((report.in_flight, report.completed), (3, 2));
+    assert!(report.cancelled.is_empty());
+    assert!(!report.is_clean());
+}
+
+#[test]
+fn drain_cuts_off_members_that_outrun_the_budget() {
+    let report = group(&[false, false]).drain(3);
+    assert_eq!(report.rounds, 3);
+    assert_eq!(report.cancelled, [0, 1]);
+    assert_eq!(report.completed, 0);
+    assert!(report
+        .members
+        .iter()
+        .all(|d| d.frame.state == FrameState::Cancelled));
+}
+
+/// Refuses every step when set, allows it otherwise.
+struct RefuseIf(bool);
+
+impl Arbiter<()> for RefuseIf {
+    fn decide(&mut self, _frame: &FrameView<'_>, _ctx: &ArbiterContext) -> Decision {
+        if self.0 {
+            Decision::Refuse
+        } else {
+            Decision::Allow
+        }
+    }
+}
+
+#[test]
+fn members_their_arbiter_refused_do_not_count_as_completed() {
+    let mut g = FrameGroup::new(());
+    for refuse in [true, false] {
+        let frame = g.frame_with_prompt(4, Vec::new());
+        g.push(Driver::with_arbiter(frame, PromptStepper, RefuseIf(refuse)));
+    }
+    let report = g.drain(20);
+    assert_eq!((report.in_flight, report.completed), (2, 1));
+    assert!(report.cancelled.is_empty() && report.failed.is_empty());
+    assert!(!report.is_clean());
+    assert_eq!(
+        report.members[1].frame.stop_reason,
+        Some(StopReason::MaxTokens)
+    );
+}
+
+#[test]
+fn members_past_their_age_limit_expire_in_a_round() {
+    let mut g = group(&[false, false]);
+    g.push(Driver::new(g.frame(100), FailFirst { fail_first: false }));
+    g.member_mut(2).unwrap().frame.limits.max_age_rounds = Some(2);
+    g.step_round().unwrap();
+    g.step_round().unwrap();
+    let round = g.step_round().unwrap();
+    let (i, r) = &round[2];
+    assert_eq!(*i, 2);
+    assert_eq!(r.stop_reason, Some(StopReason::Expired));
+    assert_eq!(receipts(r, GROUP_EXPIRED), [2]);
+    assert_eq!(
+        g.frame_stats(2).unwrap().stop_reason,
+        Some(StopReason::Expired)
+    );
+    // The others are untouched and the expired member is no longer stepped.
+    assert_eq!(g.step_round().unwrap().len(), 2);
+}
+
+#[test]
+fn an_age_limit_of_zero_is_rejected() {
+    let limits = FrameLimits {
+        max_age_rounds: Some(0),
+        ..FrameLimits::new(4)
+    };
+    assert_eq!(
+        limits.validate(&[]),
+        Err(FrameError::ZeroBudget("max_age_rounds"))
+    );
+}
+
+#[test]
+fn members_near_their_cap_go_first_in_each_round() {
+    let mut g = FrameGroup::new(()).with_finish_boost(2);
+    for max_new_tokens in [8, 8, 3] {
+        let frame = g.frame_with_prompt(max_new_tokens, Vec::new());
+        g.push(Driver::new(frame, PromptStepper));
+    }
+    let order = |round: Vec<(usize, StepResult)>| -> Vec<(usize, Vec<u64>)> {
+        round
+            .iter()
+            .map(|(i, r)| (*i, receipts(r, GROUP_BOOST)))
+            .collect()
+    };
+    // Prefill, then one token: member 2 is left with two.
+    g.step_round().unwrap();
+    assert_eq!(
+        order(g.step_round().unwrap()),
+        [(0, vec![]), (1, vec![]), (2, vec![])]
+    );
+    assert_eq!(
+        order(g.step_round().unwrap()),
+        [(2, vec![2]), (0, vec![]), (1, vec![])]
+    );
+}
@@ -0,0 +1,70 @@
+mod common;
+
+use std::sync::{Arc, Mutex};
+
+use common::PromptStepper;
+use nsc_frame::heartbeat::{MAX_HEARTBEATS, STEP_HEARTBEAT};
+use nsc_frame::trace::RecordingStepper;
+use nsc_frame::{Driver, Frame, FrameStepper, Progress, StepResult};
+
+/// [`PromptStepper`] that beats `beats` times during every step.
+struct Beating {
+    beats: u64,
+}
+
+impl FrameStepper<()> for Beating {
+    fn step(&mut self, frame: &mut Frame<()>) -> Result<StepResult, String> {
+        PromptStepper.step(frame)
+    }
+
+    fn step_with(
+        &mut self,
+        frame: &mut Frame<()>,
+        progress: &mut Progress<'_>,
+    ) -> Result<StepResult, String> {
+        for i in 0..self.beats {
+            progress.beat(i);
+        }
+        self.step(frame)
+    }
+}
+
+fn heartbeats(r: &StepResult) -> Vec<u64> {
+    r.receipts
+        .iter()
+        .filter(|x| x.kind == STEP_HEARTBEAT)
+        .map(|x| x.value_u64)
+        .collect()
+}
+
+#[test]
+fn every_beat_reaches_the_hook_and_the_first_ones_become_receipts() {
+    let seen = Arc::new(Mutex::new(Vec::new()));
+    let hook = seen.clone();
+    let frame = Frame::with_prompt((), 4, vec![1]);
+    let mut d = Driver::new(frame, Beating { beats: 20 })
+        .on_heartbeat(move |v| hook.lock().unwrap().push(v));
+    let r = d.step().unwrap();
+    assert_eq!(*seen.lock().unwrap(), (0..20).collect::<Vec<_>>());
+    assert_eq!(
+        heartbeats(&r),
+        (0..MAX_HEARTBEATS as u64).collect::<Vec<_>>()
+    );
+}
+
+#[test]
+fn recording_steppers_pass_beats_through() {
+    let stepper = RecordingStepper::new(Beating { beats: 2 });
+    let mut d = Driver::new(Frame::with_prompt((), 4, vec![1]), stepper);
+    let r = d.step().unwrap();
+    assert_eq!(heartbeats(&r), [0, 1]);
+}
+
+#[test]
+fn steppers_without_step_with_send_no_heartbeats() {
+    let mut d = Driver::new(Frame::with_prompt((), 4, vec![1]), PromptStepper);
+    assert!(heartbeats(&d.step().unwrap()).is_empty());
+    let mut progress = Progress::new();
+    progress.beat(3);
+    assert_eq!(progress.beats(), [3]);
+}
@@ -0,0 +1,60 @@
+use nsc_frame::ledger::ReceiptAggregate;
+use nsc_frame::{ReceiptBudget, ReceiptLedger, StepResult};
+
+fn step(receipts: &[(&'static str, u64)]) -> StepResult {
+    receipts
+        .iter()
+        .fold(StepResult::advanced(None), |r, &(kind, v)| {
+            r.with_receipt(kind, v)
+        })
+}
+
+fn kinds(ledger: &ReceiptLedger, frame_id: u64) -> Vec<(u64, &'static str)> {
+    let frame = ledger.frame(frame_id).unwrap();
+    frame.retained().map(|(step, r)| (step, r.kind)).collect()
+}
+
+#[test]
+fn the_oldest_unprotected_receipts_are_folded_over_budget() {
+    let budget = ReceiptBudget {
+        max_count: Some(3),
+        max_bytes: None,
+    };
+    let mut ledger = ReceiptLedger::new(budget);
+    ledger.record(1, &step(&[("law.vocab", 9), ("mw.a", 1)]));
+    ledger.record(1, &step(&[("mw.a", 2), ("mw.b", 5)]));
+    ledger.record(2, &step(&[("mw.a", 7)]));
+
+    assert_eq!(
+        kinds(&ledger, 1),
+        [(0, "law.vocab"), (1, "mw.a"), (1, "mw.b")]
+    );
+    let frame = ledger.frame(1).unwrap();
+    let folded: Vec<_> = frame.aggregated().collect();
+    assert_eq!(folded, [("mw.a", ReceiptAggregate { count: 1, sum: 1 })]);
+    assert_eq!((frame.folded(), frame.steps()), (1, 2));
+    // Frames are budgeted separately.
+    assert_eq!(kinds(&ledger, 2), [(0, "mw.a")]);
+}
+
+#[test]
+fn protected_receipts_may_keep_a_frame_over_budget() {
+    let budget = ReceiptBudget {
+        max_count: None,
+        max_bytes: Some(20),
+    };
+    let mut ledger = ReceiptLedger::new(budget).protect("mw.");
+    ledger.record(1, &step(&[("mw.a", 1), ("mw.b", 2), ("other", 3)]));
+    let frame = ledger.frame(1).unwrap();
+    assert_eq!(kinds(&ledger, 1), [(0, "mw.a"), (0, "mw.b")]);
+    assert_eq!(frame.bytes(), 2 * (4 + 4 + 8));
+    assert!(frame.bytes() > 20);
+
+    let mut open = ReceiptLedger::new(budget).with_protected(Vec::new());
+    open.record(1, &step(&[("law.vocab", 1), ("law.vocab", 2)]));
+    // One `law.vocab` receipt alone is 21 bytes, so neither is kept.
+    assert_eq!(kinds(&open, 1), []);
+    assert_eq!(open.frame(1).unwrap().folded(), 2);
+    assert!(open.remove(1).is_some());
+    assert!(open.frame(1).is_none());
+}
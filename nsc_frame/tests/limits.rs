@@ -0,0 +1,88 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper, WideStepper};
+use nsc_frame::{
+    Driver, FrameState, ManualClock, NoopMem, PauseReason, StepOutcome, StepResult, StopReason,
+};
+
+fn finished_with(r: &StepResult) -> Option<StopReason> {
+    (r.outcome == StepOutcome::Finished)
+        .then_some(r.stop_reason)
+        .flatten()
+}
+
+#[test]
+fn deadlines_count_driver_steps_without_a_clock() {
+    let mut frame = prompt_frame(0, 100);
+    frame.limits.deadline_ticks = Some(3);
+    let mut d = Driver::new(frame, PromptStepper);
+    for left in [3, 2, 1] {
+        let r = d.step().unwrap();
+        assert_eq!(finished_with(&r), None);
+        assert_eq!(d.remaining_ticks(), Some(left));
+    }
+    let r = d.step().unwrap();
+    assert_eq!(finished_with(&r), Some(StopReason::DeadlineExceeded));
+    assert_eq!(d.frame.stop_reason, Some(StopReason::DeadlineExceeded));
+}
+
+#[test]
+fn a_clock_deadline_finishes_paused_frames_too() {
+    let clock = ManualClock::new(50);
+    let mut frame = prompt_frame(2, 100);
+    frame.limits.deadline_ticks = Some(10);
+    let mut d = Driver::new(frame, PromptStepper).with_clock(clock.clone());
+    d.step().unwrap();
+    assert_eq!(d.remaining_ticks(), Some(10));
+
+    d.frame.pause(PauseReason::Requested);
+    clock.advance(9);
+    assert_eq!(d.step().unwrap().outcome, StepOutcome::Yielded);
+    clock.advance(1);
+    let r = d.step().unwrap();
+    assert_eq!(finished_with(&r), Some(StopReason::DeadlineExceeded));
+    assert_eq!(d.frame.state, FrameState::Finished);
+}
+
+#[test]
+fn steps_spend_their_reported_cost_until_the_budget_is_gone() {
+    let mut frame = prompt_frame(0, 100);
+    frame.limits.max_cost = Some(5);
+    let mut d = Driver::new(frame, WideStepper { width: 1 });
+    // Prefill reports no cost and is charged one unit; each decode step reports two.
+    for spent in [1, 3, 5] {
+        let r = d.step().unwrap();
+        assert_eq!(finished_with(&r), None);
+        assert_eq!(d.frame.cost_spent, spent);
+    }
+    let r = d.step().unwrap();
+    assert_eq!(finished_with(&r), Some(StopReason::CostBudgetExhausted));
+    assert_eq!(r.reported_cost(), None);
+    assert_eq!(d.frame.cost_spent, 5);
+}
+
+/// Step `d` until it finishes; returns the stop reason and tokens generated.
+fn run(d: &mut Driver<NoopMem, PromptStepper>) -> (Option<StopReason>, usize) {
+    loop {
+        let r = d.step().unwrap();
+        if r.outcome == StepOutcome::Finished {
+            return (r.stop_reason, d.frame.tokens_generated);
+        }
+    }
+}
+
+#[test]
+fn the_total_cap_counts_the_prompt_and_the_new_cap_does_not() {
+    let mut frame = prompt_frame(3, 100);
+    frame.limits.max_new_tokens = None;
+    frame.limits.max_total_tokens = Some(5);
+    assert_eq!(frame.progress().remaining_tokens, Some(2));
+    let mut d = Driver::new(frame, PromptStepper);
+    assert_eq!(run(&mut d), (Some(StopReason::MaxTotalTokens), 2));
+
+    let mut frame = prompt_frame(3, 2);
+    frame.limits.max_total_tokens = Some(100);
+    assert_eq!(frame.progress().remaining_tokens, Some(2));
+    let mut d = Driver::new(frame, PromptStepper);
+    assert_eq!(run(&mut d), (Some(StopReason::MaxTokens), 2));
+}
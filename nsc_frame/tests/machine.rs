@@ -0,0 +1,97 @@
+mod common;
+
+use common::PromptStepper;
+use nsc_frame::validate::LAW_VOCAB;
+use nsc_frame::{
+    Driver, Frame, FrameMachine, FrameState, FrameStepper, MachineInput, MachineOutput,
+    StepOutcome, StepResult, StopReason, STEP_STALLED,
+};
+
+fn machine() -> FrameMachine<(), PromptStepper> {
+    let frame = Frame::with_prompt((), 4, vec![1, 2])
+        .with_vocab_size(50)
+        .unwrap();
+    FrameMachine::new(Driver::new(frame, PromptStepper))
+}
+
+#[test]
+fn provided_tokens_outside_the_vocab_are_refused() {
+    let mut m = machine();
+    m.handle_input(MachineInput::ProvideTokens(vec![3, 70, 80]));
+
+    assert!(matches!(m.poll_output(), Some(MachineOutput::Error(_))));
+    assert_eq!(m.driver().frame.prompt_token_ids, [1, 2]);
+    let rejected: Vec<_> = m
+        .driver()
+        .pending_receipts
+        .iter()
+        .filter(|r| r.kind == LAW_VOCAB)
+        .map(|r| r.value_u64)
+        .collect();
+    assert_eq!(rejected, [70]);
+}
+
+#[test]
+fn provided_tokens_inside_the_vocab_extend_the_prompt() {
+    let mut m = machine();
+    m.handle_input(MachineInput::ProvideTokens(vec![3, 49]));
+
+    assert_eq!(m.poll_output(), None);
+    assert_eq!(m.driver().frame.prompt_token_ids, [1, 2, 3, 49]);
+}
+
+/// [`PromptStepper`] that stalls with cause 7 before every decode step.
+struct Stalling {
+    stall: bool,
+}
+
+impl FrameStepper<()> for Stalling {
+    fn step(&mut self, frame: &mut Frame<()>) -> Result<StepResult, String> {
+        if frame.state != FrameState::Decode {
+            return PromptStepper.step(frame);
+        }
+        self.stall = !self.stall;
+        match self.stall {
+            false => Ok(StepResult::stalled().with_receipt(STEP_STALLED, 7)),
+            true => PromptStepper.step(frame),
+        }
+    }
+}
+
+#[test]
+fn stalled_steps_make_no_progress_and_say_why() {
+    let frame = Frame::with_prompt((), 2, Vec::new());
+    let mut d = Driver::new(frame, Stalling { stall: true });
+    d.step().unwrap();
+    let r = d.step().unwrap();
+    assert_eq!(r.outcome, StepOutcome::Stalled);
+    assert_eq!(r.emitted_token, None);
+    assert!(r
+        .receipts
+        .iter()
+        .any(|x| x.kind == STEP_STALLED && x.value_u64 == 7));
+    assert_eq!(d.frame.state, FrameState::Decode);
+    assert_eq!(d.frame.tokens_generated, 0);
+}
+
+#[test]
+fn the_machine_reports_stalls_between_tokens() {
+    let frame = Frame::with_prompt((), 2, Vec::new());
+    let mut m = FrameMachine::new(Driver::new(frame, Stalling { stall: true }));
+    let mut outputs = Vec::new();
+    for _ in 0..8 {
+        m.handle_input(MachineInput::Tick);
+        outputs.extend(std::iter::from_fn(|| m.poll_output()));
+    }
+    assert_eq!(
+        outputs,
+        [
+            MachineOutput::Stalled,
+            MachineOutput::EmitToken(0),
+            MachineOutput::Stalled,
+            MachineOutput::EmitToken(7),
+            MachineOutput::Stalled,
+            MachineOutput::Finished(StopReason::MaxTokens),
+        ]
+    );
+}
@@ -0,0 +1,39 @@
+mod common;
+
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use common::PromptStepper;
+use nsc_frame::{DeepCloneMem, Driver, Frame, Tag};
+
+/// A shared handle that clones shallowly but can also be duplicated in full.
+#[derive(Clone, Default)]
+struct Kv(Rc<RefCell<Vec<u32>>>);
+
+impl DeepCloneMem for Kv {
+    fn deep_clone(&self) -> Self {
+        Kv(Rc::new(RefCell::new(self.0.borrow().clone())))
+    }
+}
+
+#[test]
+fn a_deep_clone_shares_no_memory_with_the_original() {
+    let frame = Frame::with_prompt(Kv::default(), 4, vec![1, 2]).with_tag(Tag::BATCH);
+    let mut d = Driver::new(frame, PromptStepper);
+    for _ in 0..3 {
+        d.step().unwrap();
+    }
+    d.frame.mem.0.borrow_mut().push(10);
+
+    let copy = d.frame.deep_clone();
+    assert_eq!(copy.snapshot(), d.frame.snapshot());
+    assert_eq!(copy.run_id, d.frame.run_id);
+    assert_eq!(copy.tags, [Tag::BATCH]);
+
+    copy.mem.0.borrow_mut().push(11);
+    assert_eq!(*d.frame.mem.0.borrow(), [10]);
+    let shallow = d.frame.mem.clone();
+    shallow.0.borrow_mut().push(12);
+    assert_eq!(*d.frame.mem.0.borrow(), [10, 12]);
+    assert_eq!(*copy.mem.0.borrow(), [10, 11]);
+}
@@ -0,0 +1,72 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::migration::{MigrationBundle, MIGRATION_MAGIC};
+use nsc_frame::protocol::DecodeError;
+use nsc_frame::snapshot::SNAPSHOT_MAGIC;
+use nsc_frame::{Driver, FrameSnapshot, ManualClock, NoArbiter, NoopMem, StepOutcome, StopReason};
+
+fn deadline_frame(deadline: u64) -> nsc_frame::Frame<NoopMem> {
+    let mut frame = prompt_frame(0, 100);
+    frame.limits.deadline_ticks = Some(deadline);
+    frame
+}
+
+#[test]
+fn migration_does_not_restart_the_deadline() {
+    let src_clock = ManualClock::new(1_000);
+    let mut src = Driver::new(deadline_frame(10), PromptStepper).with_clock(src_clock.clone());
+    src.step().unwrap();
+    src_clock.advance(6);
+
+    let bytes = src.export_for_migration().encode();
+    let bundle = MigrationBundle::decode(&bytes).unwrap();
+    assert_eq!(bundle.snapshot.elapsed_ticks, 6);
+
+    let dst_clock = ManualClock::new(0);
+    let mut dst = Driver::resume_from_migration(bundle, NoopMem, PromptStepper, NoArbiter)
+        .unwrap()
+        .with_clock(dst_clock.clone());
+    assert_eq!(dst.remaining_ticks(), Some(4));
+    assert_eq!(dst.step().unwrap().outcome, StepOutcome::Advanced);
+    dst_clock.advance(4);
+    let r = dst.step().unwrap();
+    assert_eq!(r.stop_reason, Some(StopReason::DeadlineExceeded));
+}
+
+#[test]
+fn restored_snapshots_keep_counting_driver_ticks() {
+    let mut src = Driver::new(deadline_frame(5), PromptStepper);
+    for _ in 0..3 {
+        src.step().unwrap();
+    }
+    let snapshot = FrameSnapshot::decode(&src.snapshot().encode()).unwrap();
+    let mut dst = Driver::new(snapshot.into_frame(NoopMem), PromptStepper);
+    let mut steps = 0;
+    while dst.step().unwrap().outcome != StepOutcome::Finished {
+        steps += 1;
+    }
+    assert_eq!(dst.frame.stop_reason, Some(StopReason::DeadlineExceeded));
+    assert!(steps < 5);
+}
+
+#[test]
+fn version_one_encodings_are_rejected() {
+    let mut snapshot = deadline_frame(5).snapshot().encode();
+    assert_eq!(snapshot[..4], SNAPSHOT_MAGIC);
+    snapshot[4] = 1;
+    assert_eq!(
+        FrameSnapshot::decode(&snapshot),
+        Err(DecodeError::UnsupportedVersion(1))
+    );
+
+    let mut bundle = Driver::new(deadline_frame(5), PromptStepper)
+        .export_for_migration()
+        .encode();
+    assert_eq!(bundle[..4], MIGRATION_MAGIC);
+    bundle[4] = 1;
+    assert_eq!(
+        MigrationBundle::decode(&bundle),
+        Err(DecodeError::UnsupportedVersion(1))
+    );
+}
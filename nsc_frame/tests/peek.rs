@@ -0,0 +1,39 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper};
+use nsc_frame::trace::RecordingStepper;
+use nsc_frame::{Driver, EitherStepper, NoopStepper, StepOutcome, StepPreview, StopReason};
+
+#[test]
+fn previews_match_the_steps_that_follow_and_change_nothing() {
+    let mut d = Driver::new(prompt_frame(2, 3), RecordingStepper::new(NoopStepper));
+    let mut previews = Vec::new();
+    loop {
+        let before = d.frame.snapshot();
+        let preview = d.peek().unwrap();
+        assert_eq!(d.frame.snapshot(), before);
+        let r = d.step().unwrap();
+        assert!(preview.matches(&r), "{:?} vs {}", preview, r);
+        previews.push(preview);
+        if r.outcome == StepOutcome::Finished {
+            break;
+        }
+    }
+    assert_eq!(previews.len(), 5);
+    assert_eq!(
+        previews.last().unwrap().stop_reason,
+        Some(StopReason::MaxTokens)
+    );
+    assert_eq!(d.peek(), None);
+}
+
+#[test]
+fn steppers_that_cannot_tell_preview_nothing() {
+    let mut d = Driver::new(prompt_frame(2, 3), PromptStepper);
+    assert_eq!(d.peek(), None);
+
+    let either = EitherStepper::<NoopStepper, PromptStepper>::Left(NoopStepper);
+    let mut d = Driver::new(prompt_frame(0, 3), either);
+    d.step().unwrap();
+    assert_eq!(d.peek(), Some(StepPreview::token(0)));
+}
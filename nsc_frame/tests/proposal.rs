@@ -0,0 +1,71 @@
+mod common;
+
+use common::{prompt_frame, WideStepper};
+use nsc_frame::{
+    Driver, Frame, FrameStepper, NoopMem, Proposal, StepOutcome, StepResult, StopReason,
+    SPEC_ACCEPTED, SPEC_MALFORMED,
+};
+
+/// [`WideStepper`] of width 2 that attaches `proposal` to every decode step.
+struct Proposing(Proposal);
+
+impl FrameStepper<NoopMem> for Proposing {
+    fn step(&mut self, frame: &mut Frame<NoopMem>) -> Result<StepResult, String> {
+        let r = WideStepper { width: 2 }.step(frame)?;
+        Ok(match r.emitted_token {
+            Some(_) => r.with_proposal(self.0.clone()),
+            None => r,
+        })
+    }
+}
+
+fn proposal(committed: Option<usize>, accepted_len: usize) -> Proposal {
+    Proposal {
+        candidates: vec![vec![0, 1], vec![0, 2, 3]],
+        committed,
+        accepted_len,
+    }
+}
+
+fn receipt(r: &StepResult, kind: &str) -> Option<u64> {
+    r.receipts
+        .iter()
+        .find(|x| x.kind == kind)
+        .map(|x| x.value_u64)
+}
+
+#[test]
+fn proposals_are_validated_against_their_candidates() {
+    assert!(proposal(Some(1), 3).validate().is_ok());
+    assert!(proposal(None, 0).validate().is_ok());
+    assert!(proposal(Some(2), 0).validate().is_err());
+    assert!(proposal(Some(0), 3).validate().is_err());
+    assert!(proposal(None, 1).validate().is_err());
+}
+
+#[test]
+fn a_well_formed_proposal_is_counted_and_receipted() {
+    let mut d = Driver::new(prompt_frame(0, 8), Proposing(proposal(Some(0), 2)));
+    d.step().unwrap();
+    let r = d.step().unwrap();
+    assert_eq!(r.outcome, StepOutcome::Advanced);
+    assert_eq!(receipt(&r, SPEC_ACCEPTED), Some(2));
+    assert_eq!(d.proposal_stats.steps, 1);
+    assert_eq!(d.frame.tokens_generated, 2);
+}
+
+#[test]
+fn a_malformed_proposal_finishes_the_frame_without_its_tokens() {
+    let mut d = Driver::new(prompt_frame(0, 8), Proposing(proposal(Some(5), 1)));
+    d.step().unwrap();
+    let r = d.step().unwrap();
+    assert_eq!(r.outcome, StepOutcome::Finished);
+    assert_eq!(r.stop_reason, Some(StopReason::BackendError));
+    assert_eq!(r.emitted_token, None);
+    assert_eq!(receipt(&r, SPEC_MALFORMED), Some(2));
+    assert_eq!(receipt(&r, SPEC_ACCEPTED), None);
+    assert_eq!(d.proposal_stats.steps, 0);
+    assert!(d.frame.generated_token_ids.is_empty());
+    assert_eq!((d.frame.tokens_generated, d.frame.cursor.position), (0, 0));
+    assert_eq!(d.frame.stop_reason, Some(StopReason::BackendError));
+}
@@ -0,0 +1,55 @@
+use nsc_frame::protocol::{Command, DecodeError, Response, StepEnvelope, PROTOCOL_VERSION};
+use nsc_frame::{FrameState, StepOutcome};
+
+/// A complete frame whose body stops short of what its tag promises.
+fn short_frame(body: &[u8]) -> Vec<u8> {
+    let mut out = ((body.len() + 1) as u32).to_le_bytes().to_vec();
+    out.push(PROTOCOL_VERSION);
+    out.extend_from_slice(body);
+    out
+}
+
+#[test]
+fn short_bodies_are_malformed_not_incomplete() {
+    let step = short_frame(&[1, 7, 0]);
+    assert_eq!(
+        Command::decode(&step).unwrap_err(),
+        DecodeError::Malformed("truncated message body")
+    );
+    assert_eq!(
+        Response::decode(&short_frame(&[1, 7, 0, 0, 0, 0, 0, 0, 0, 0])).unwrap_err(),
+        DecodeError::Malformed("truncated message body")
+    );
+}
+
+#[test]
+fn a_partial_frame_is_still_incomplete() {
+    let mut buf = Vec::new();
+    Command::Step { frame_id: 7 }.encode(&mut buf);
+    assert_eq!(
+        Command::decode(&buf[..buf.len() - 1]).unwrap_err(),
+        DecodeError::Incomplete
+    );
+    assert_eq!(Command::decode(&buf).unwrap().1, buf.len());
+}
+
+#[test]
+fn step_responses_round_trip() {
+    let envelope = StepEnvelope {
+        outcome: StepOutcome::Advanced,
+        emitted_token: Some(9),
+        stop_reason: None,
+        receipts: vec![("step.cost".to_string(), 3)],
+        proposal: None,
+        tokens_committed: 1,
+        state_after: FrameState::Decode,
+        step_index: 4,
+    };
+    let resp = Response::Step {
+        frame_id: 1,
+        envelope,
+    };
+    let mut buf = Vec::new();
+    resp.encode(&mut buf);
+    assert_eq!(Response::decode(&buf).unwrap(), (resp, buf.len()));
+}
@@ -0,0 +1,165 @@
+mod common;
+
+use common::PromptStepper;
+use nsc_frame::schedulers::{
+    LatencyTarget, Replay, ShortestJobFirst, TenantShare, WeightedFair, SLA_ATTAINMENT,
+};
+use nsc_frame::{Driver, FrameGroup, FrameState, ScheduleEntry, Scheduler, Tag};
+
+/// A group with one member per `(prompt length, max new tokens)`.
+fn group(jobs: &[(u32, usize)]) -> FrameGroup<(), PromptStepper> {
+    let mut g = FrameGroup::new(());
+    for &(prompt_len, max_new_tokens) in jobs {
+        let frame = g.frame_with_prompt(max_new_tokens, (0..prompt_len).collect());
+        g.push(Driver::new(frame, PromptStepper));
+    }
+    g
+}
+
+/// Member indices in the order they finish when ticking `g` to the end.
+fn finish_order(mut g: FrameGroup<(), PromptStepper>) -> Vec<usize> {
+    let mut order = Vec::new();
+    while !g.is_finished() {
+        let (i, _) = g.step_tick().unwrap().unwrap();
+        if g.members()[i].frame.state == FrameState::Finished {
+            order.push(i);
+        }
+    }
+    order
+}
+
+fn with(jobs: &[(u32, usize)], scheduler: impl Scheduler + Send + Sync + 'static) -> Vec<usize> {
+    finish_order(group(jobs).with_scheduler(scheduler))
+}
+
+#[test]
+fn shortest_job_first_runs_the_fewest_tokens_left_first() {
+    let jobs = [(0, 6), (0, 2), (0, 4), (0, 2)];
+    assert_eq!(with(&jobs, ShortestJobFirst::default()), [1, 3, 2, 0]);
+}
+
+#[test]
+fn shortest_job_first_can_count_the_prompt_left() {
+    let jobs = [(0, 4), (8, 2)];
+    assert_eq!(with(&jobs, ShortestJobFirst::default()), [1, 0]);
+    let sjf = ShortestJobFirst {
+        count_prefill: true,
+    };
+    assert_eq!(with(&jobs, sjf), [0, 1]);
+}
+
+#[test]
+fn latency_target_steps_members_behind_their_rate_first() {
+    let mut g = group(&[(0, 64), (0, 64)])
+        .with_scheduler(LatencyTarget::new().target(Tag::INTERACTIVE, 0.5));
+    g.member_mut(0).unwrap().frame.tags.push(Tag::INTERACTIVE);
+    let mut attained = Vec::new();
+    for _ in 0..40 {
+        let (i, r) = g.step_tick().unwrap().unwrap();
+        let receipt = r.receipts.iter().find(|x| x.kind == SLA_ATTAINMENT);
+        assert_eq!(receipt.is_some(), i == 0);
+        attained.extend(receipt.map(|x| x.value_u64));
+    }
+    // The targeted member keeps up with half a token per round but is throttled
+    // beyond it, so the untargeted one gets the remaining rounds.
+    let stats = g.all_stats();
+    let rate = stats[0].tokens_generated as f64 / stats[0].age_rounds as f64;
+    assert!((0.45..=0.55).contains(&rate), "rate {}", rate);
+    assert!(stats[1].steps_taken >= 18);
+    assert!(attained.iter().skip(2).all(|&a| a < 1000));
+}
+
+#[test]
+fn members_ahead_of_target_still_run_when_nobody_else_can() {
+    let only = with(&[(0, 3)], LatencyTarget::new().target(Tag::BATCH, 0.1));
+    assert_eq!(only, [0]);
+}
+
+/// Tick `g` to the end and return every `(member, emitted token)` in order.
+fn tick_out(g: &mut FrameGroup<(), PromptStepper>) -> Vec<(usize, Option<u32>)> {
+    let mut out = Vec::new();
+    while let Some((i, r)) = g.step_tick().unwrap() {
+        out.push((i, r.emitted_token));
+    }
+    out
+}
+
+#[test]
+fn a_recorded_schedule_replays_the_same_interleaving() {
+    let jobs = [(2, 3), (0, 5), (1, 2)];
+    let sjf = ShortestJobFirst {
+        count_prefill: true,
+    };
+    let mut recorded = group(&jobs).with_scheduler(sjf).with_schedule_log();
+    let first = tick_out(&mut recorded);
+    assert_eq!(recorded.schedule().len(), first.len());
+    assert_eq!(
+        recorded.schedule()[0],
+        ScheduleEntry {
+            round: 0,
+            member: 2
+        }
+    );
+
+    let replay = Replay::new(recorded.schedule().to_vec());
+    let mut replayed = group(&jobs).with_scheduler(replay).with_schedule_log();
+    assert_eq!(tick_out(&mut replayed), first);
+    assert_eq!(replayed.schedule(), recorded.schedule());
+}
+
+#[test]
+fn a_replay_that_diverges_fails_the_tick() {
+    let schedule = vec![
+        ScheduleEntry {
+            round: 0,
+            member: 0,
+        },
+        ScheduleEntry {
+            round: 2,
+            member: 0,
+        },
+    ];
+    let mut g = group(&[(0, 4), (0, 4)]).with_scheduler(Replay::new(schedule));
+    assert_eq!(g.step_tick().unwrap().unwrap().0, 0);
+    // No entry for round 1: nobody steps.
+    assert!(g.step_tick().unwrap().is_none());
+    // This run cancels member 0 where the recorded one did not.
+    g.member_mut(0).unwrap().frame.cancel();
+    assert_eq!(g.step_tick().unwrap_err().member, 0);
+}
+
+#[test]
+fn weighted_fair_splits_ticks_by_tenant_weight() {
+    let wfq = WeightedFair::new()
+        .tenant(Tag::INTERACTIVE, 3)
+        .tenant(Tag::BATCH, 1);
+    let mut g = group(&[(0, 64); 4]).with_scheduler(wfq);
+    g.member_mut(0).unwrap().frame.tags.push(Tag::INTERACTIVE);
+    g.member_mut(1).unwrap().frame.tags.push(Tag::BATCH);
+    g.member_mut(2).unwrap().frame.tags.push(Tag::BATCH);
+    for _ in 0..50 {
+        g.step_tick().unwrap().unwrap();
+    }
+    let steps: Vec<u64> = g.all_stats().iter().map(|s| s.steps_taken).collect();
+    // 3 : 1 : 1 between the tenants; the two batch members split theirs.
+    assert_eq!(steps, [30, 5, 5, 10]);
+}
+
+#[test]
+fn weighted_fair_reports_the_shares_it_delivered() {
+    let wfq = WeightedFair::new().tenant(Tag::BATCH, 2);
+    let mut g = group(&[(0, 64), (0, 64)]).with_scheduler(wfq.clone());
+    g.member_mut(1).unwrap().frame.tags.push(Tag::BATCH);
+    for _ in 0..9 {
+        g.step_tick().unwrap();
+    }
+    let share = |tenant, weight, served| TenantShare {
+        tenant,
+        weight,
+        served,
+    };
+    assert_eq!(
+        wfq.shares(),
+        [share(Some(Tag::BATCH), 2, 6), share(None, 1, 3)]
+    );
+}
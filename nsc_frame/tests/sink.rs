@@ -0,0 +1,264 @@
+mod common;
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+
+use common::PromptStepper;
+use nsc_frame::sink::{EMIT_BOUNDARY, SINK_DROPPED};
+use nsc_frame::{
+    Driver, FlushPolicy, Frame, FrameStepper, Receipt, ReceiptSink, SinkControl, StepOutcome,
+    StepResult, TokenSink, STEP_COST,
+};
+
+/// Takes `left` tokens, then refuses everything.
+struct Capped {
+    left: usize,
+}
+
+impl TokenSink for Capped {
+    fn offer(&mut self, _token: u32) -> SinkControl {
+        if self.left == 0 {
+            return SinkControl::Backpressure;
+        }
+        self.left -= 1;
+        SinkControl::Continue
+    }
+}
+
+fn run_to_finish(left: usize) -> (Driver<(), PromptStepper>, StepResult) {
+    let frame = Frame::with_prompt((), 4, vec![1]);
+    let mut d = Driver::new(frame, PromptStepper)
+        .with_sink(Capped { left })
+        .with_flush_policy(FlushPolicy::EveryN(16));
+    loop {
+        let r = d.step().unwrap();
+        if r.outcome == StepOutcome::Finished {
+            return (d, r);
+        }
+    }
+}
+
+fn dropped(r: &StepResult) -> Option<u64> {
+    r.receipts
+        .iter()
+        .find(|x| x.kind == SINK_DROPPED)
+        .map(|x| x.value_u64)
+}
+
+#[test]
+fn finishing_into_a_full_sink_reports_what_it_refused() {
+    let (mut d, r) = run_to_finish(1);
+    assert_eq!(dropped(&r), Some(3));
+    assert_eq!(d.sink_backlog().len(), 3);
+
+    // Re-stepping the finished frame reports nothing new.
+    let again = d.step().unwrap();
+    assert_eq!(dropped(&again), None);
+    assert_eq!(d.sink_backlog().len(), 3);
+}
+
+#[test]
+fn finishing_into_a_sink_with_room_reports_nothing() {
+    let (d, r) = run_to_finish(4);
+    assert_eq!(dropped(&r), None);
+    assert!(d.sink_backlog().is_empty());
+}
+
+#[test]
+fn a_cancelled_frame_reports_its_dropped_tokens_once() {
+    let frame = Frame::with_prompt((), 8, vec![1]);
+    let mut d = Driver::new(frame, PromptStepper)
+        .with_sink(Capped { left: 1 })
+        .with_flush_policy(FlushPolicy::EveryN(16));
+    for _ in 0..4 {
+        d.step().unwrap();
+    }
+    d.frame.cancel();
+    assert_eq!(dropped(&d.step().unwrap()), Some(2));
+    assert_eq!(dropped(&d.step().unwrap()), None);
+}
+
+fn receipt(r: &StepResult, kind: &str) -> Option<u64> {
+    r.receipts
+        .iter()
+        .find(|x| x.kind == kind)
+        .map(|x| x.value_u64)
+}
+
+#[test]
+fn flow_control_blocks_decoding_until_credits_are_granted() {
+    let frame = Frame::with_prompt((), 8, vec![1, 2]);
+    let mut d = Driver::new(frame, PromptStepper).with_flow_control(1);
+    let handle = d.handle();
+    // Prefill is never blocked, and the one credit buys one token.
+    for _ in 0..3 {
+        assert_eq!(d.step().unwrap().outcome, StepOutcome::Advanced);
+    }
+    assert_eq!(d.flow_credits(), Some(0));
+    let blocked = d.step().unwrap();
+    assert_eq!(blocked.outcome, StepOutcome::Yielded);
+    assert_eq!(receipt(&blocked, "flow.blocked"), Some(1));
+    assert_eq!(d.frame.tokens_generated, 1);
+
+    handle.grant_credits(2);
+    let r = d.step().unwrap();
+    assert_eq!(receipt(&r, "command.grant_credits"), Some(2));
+    assert_eq!(r.outcome, StepOutcome::Advanced);
+    assert_eq!(d.step().unwrap().outcome, StepOutcome::Advanced);
+    assert_eq!(d.step().unwrap().outcome, StepOutcome::Yielded);
+    assert_eq!(d.frame.tokens_generated, 3);
+}
+
+#[test]
+fn credits_granted_without_flow_control_are_ignored() {
+    let mut d = Driver::new(Frame::with_prompt((), 4, vec![]), PromptStepper);
+    d.handle().grant_credits(5);
+    let r = d.step().unwrap();
+    assert_eq!(receipt(&r, "command.grant_credits"), Some(0));
+    assert_eq!(d.flow_credits(), None);
+}
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u32>>>);
+
+impl TokenSink for Shared {
+    fn offer(&mut self, token: u32) -> SinkControl {
+        self.0.lock().unwrap().push(token);
+        SinkControl::Continue
+    }
+}
+
+/// [`PromptStepper`] that marks every third output token as a boundary.
+struct Boundaries;
+
+impl FrameStepper<()> for Boundaries {
+    fn step(&mut self, frame: &mut Frame<()>) -> Result<StepResult, String> {
+        let r = PromptStepper.step(frame)?;
+        Ok(match r.emitted_token {
+            Some(_) if frame.tokens_generated % 3 == 0 => r.with_receipt(EMIT_BOUNDARY, 1),
+            _ => r,
+        })
+    }
+}
+
+/// Tokens the sink holds after each step until `d` finishes.
+fn delivered<S: FrameStepper<()>>(mut d: Driver<(), S>, sink: Shared) -> Vec<usize> {
+    let mut seen = Vec::new();
+    loop {
+        let r = d.step().unwrap();
+        seen.push(sink.0.lock().unwrap().len());
+        if r.outcome == StepOutcome::Finished {
+            return seen;
+        }
+    }
+}
+
+#[test]
+fn every_n_hands_tokens_over_in_groups_and_flushes_at_the_end() {
+    let sink = Shared::default();
+    let d = Driver::new(Frame::with_prompt((), 5, vec![]), PromptStepper)
+        .with_sink(sink.clone())
+        .with_flush_policy(FlushPolicy::EveryN(2));
+    // Prefill, five tokens, then the finishing step.
+    assert_eq!(delivered(d, sink), [0, 0, 2, 2, 4, 4, 5]);
+}
+
+#[test]
+fn at_boundary_hands_tokens_over_at_marked_steps() {
+    let sink = Shared::default();
+    let d = Driver::new(Frame::with_prompt((), 7, vec![]), Boundaries)
+        .with_sink(sink.clone())
+        .with_flush_policy(FlushPolicy::AtBoundary);
+    assert_eq!(delivered(d, sink), [0, 0, 0, 3, 3, 3, 6, 6, 7]);
+}
+
+#[derive(Clone, Default)]
+struct Collected(Arc<Mutex<Vec<Receipt>>>);
+
+impl ReceiptSink for Collected {
+    fn accept(&mut self, receipts: &[Receipt]) {
+        self.0.lock().unwrap().extend_from_slice(receipts);
+    }
+}
+
+#[test]
+fn the_receipt_sink_sees_every_envelope_and_may_strip_it() {
+    let collected = Collected::default();
+    let mut d = Driver::new(Frame::with_prompt((), 2, vec![1]), PromptStepper)
+        .with_receipt_sink(collected.clone())
+        .strip_receipts();
+    let mut returned = 0;
+    loop {
+        let r = d.step().unwrap();
+        returned += r.receipts.len();
+        if r.outcome == StepOutcome::Finished {
+            break;
+        }
+    }
+    assert_eq!(returned, 0);
+    let kinds: Vec<_> = collected.0.lock().unwrap().iter().map(|x| x.kind).collect();
+    assert_eq!(kinds.iter().filter(|&&k| k == "prefill.tokens").count(), 1);
+    assert!(kinds.contains(&STEP_COST));
+}
+
+#[test]
+fn stripping_without_a_receipt_sink_keeps_receipts() {
+    let mut d = Driver::new(Frame::with_prompt((), 2, vec![1]), PromptStepper).strip_receipts();
+    assert!(!d.step().unwrap().receipts.is_empty());
+}
+
+#[test]
+fn holdback_keeps_tokens_back_until_a_boundary_or_the_end() {
+    let mut d = Driver::new(Frame::with_prompt((), 5, vec![]), Boundaries).with_utf8_holdback();
+    let mut safe = Vec::new();
+    loop {
+        let r = d.step().unwrap();
+        safe.push(d.safe_emit_upto());
+        if r.outcome == StepOutcome::Finished {
+            break;
+        }
+    }
+    assert_eq!(safe, [0, 0, 0, 3, 3, 3, 5]);
+
+    let mut plain = Driver::new(Frame::with_prompt((), 5, vec![]), Boundaries);
+    plain.step().unwrap();
+    plain.step().unwrap();
+    assert_eq!(plain.safe_emit_upto(), 1);
+}
+
+/// Refuses tokens while its gate is shut.
+#[derive(Clone, Default)]
+struct Gated {
+    open: Arc<AtomicBool>,
+    taken: Shared,
+}
+
+impl TokenSink for Gated {
+    fn offer(&mut self, token: u32) -> SinkControl {
+        if !self.open.load(Ordering::Relaxed) {
+            return SinkControl::Backpressure;
+        }
+        self.taken.offer(token)
+    }
+}
+
+#[test]
+fn a_refusing_sink_makes_the_driver_yield_until_it_drains() {
+    let sink = Gated::default();
+    let mut d =
+        Driver::new(Frame::with_prompt((), 4, vec![]), PromptStepper).with_sink(sink.clone());
+    d.step().unwrap();
+    d.step().unwrap();
+    assert_eq!(d.sink_backlog().len(), 1);
+
+    let r = d.step().unwrap();
+    assert_eq!(r.outcome, StepOutcome::Yielded);
+    assert_eq!(receipt(&r, "sink.backpressure"), Some(1));
+    assert_eq!(d.frame.tokens_generated, 1);
+
+    sink.open.store(true, Ordering::Relaxed);
+    let r = d.step().unwrap();
+    assert_eq!(r.outcome, StepOutcome::Advanced);
+    assert_eq!(sink.taken.0.lock().unwrap().len(), 2);
+    assert!(d.sink_backlog().is_empty());
+}
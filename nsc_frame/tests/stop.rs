@@ -0,0 +1,79 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper, WideStepper};
+use nsc_frame::stop::{EosToken, StopMatcher, StopSequences};
+use nsc_frame::{DebugDriver, Driver, StepOutcome, StopReason};
+
+/// Feeds `tokens` one by one; returns `(position, sequence index)` of every match.
+fn matches(sequences: &[Vec<u32>], tokens: &[u32]) -> Vec<(usize, u32)> {
+    let mut m = StopMatcher::new(sequences);
+    tokens
+        .iter()
+        .enumerate()
+        .filter_map(|(i, &t)| m.advance(t).map(|s| (i, s)))
+        .collect()
+}
+
+#[test]
+fn matcher_finds_overlapping_occurrences() {
+    assert_eq!(
+        matches(&[vec![1, 2, 1]], &[1, 2, 1, 2, 1]),
+        [(2, 0), (4, 0)]
+    );
+    assert_eq!(matches(&[vec![1, 2, 1, 3]], &[1, 2, 1, 2, 1, 3]), [(5, 0)]);
+}
+
+#[test]
+fn matcher_follows_fail_links_into_other_patterns() {
+    assert_eq!(matches(&[vec![1, 2, 3], vec![2, 4]], &[1, 2, 4]), [(2, 1)]);
+    assert_eq!(matches(&[vec![1, 2, 3], vec![2, 3]], &[1, 2, 3]), [(2, 0)]);
+    assert_eq!(matches(&[vec![5, 6, 7], vec![6]], &[5, 6, 9]), [(1, 1)]);
+}
+
+#[test]
+fn matcher_reports_the_lowest_index_among_patterns_ending_together() {
+    let sequences = [vec![9, 4, 5], vec![4, 5], vec![5], vec![]];
+    assert_eq!(matches(&sequences, &[4, 5]), [(1, 1)]);
+    assert_eq!(matches(&sequences, &[9, 4, 5]), [(2, 0)]);
+    assert_eq!(matches(&sequences, &[5, 5]), [(0, 2), (1, 2)]);
+}
+
+/// Runs `WideStepper { width: 3 }` (ids 0, 1, 2, then 3, 4, 5, ...) under `condition`.
+fn run_wide(
+    condition: impl nsc_frame::StopCondition<nsc_frame::NoopMem> + Send + Sync + 'static,
+) -> Driver<nsc_frame::NoopMem, WideStepper> {
+    let mut d =
+        Driver::new(prompt_frame(0, 12), WideStepper { width: 3 }).with_stop_condition(condition);
+    while d.step().unwrap().outcome != StepOutcome::Finished {}
+    d
+}
+
+#[test]
+fn conditions_see_every_token_of_a_wide_step() {
+    let d = run_wide(StopMatcher::new(&[vec![3, 4]]));
+    assert_eq!(d.frame.stop_reason, Some(StopReason::StopSequence(0)));
+    assert_eq!(d.frame.tokens_generated, 6);
+
+    let d = run_wide(EosToken(4));
+    assert_eq!(d.frame.stop_reason, Some(StopReason::Eos));
+    assert_eq!(d.frame.tokens_generated, 6);
+
+    let d = run_wide(StopSequences::new(vec![vec![7, 8], vec![2, 3]]));
+    assert_eq!(d.frame.stop_reason, Some(StopReason::StopSequence(1)));
+    assert_eq!(d.frame.tokens_generated, 6);
+}
+
+#[test]
+fn rewinding_resynchronises_the_matcher() {
+    // PromptStepper emits 0, 7, 14, 21, ...; 14 never repeats, so [14, 14] must not fire.
+    let mut d = DebugDriver::new(prompt_frame(0, 8), PromptStepper);
+    d.driver.stop_condition = Some(Box::new(StopMatcher::new(&[vec![14, 14]])));
+    for _ in 0..4 {
+        d.step().unwrap();
+    }
+    assert_eq!(d.driver.frame.generated_token_ids.last(), Some(14));
+
+    d.back().unwrap();
+    while d.step().unwrap().outcome != StepOutcome::Finished {}
+    assert_eq!(d.driver.frame.stop_reason, Some(StopReason::MaxTokens));
+}
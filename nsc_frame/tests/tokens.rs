@@ -0,0 +1,77 @@
+mod common;
+
+use std::borrow::Cow;
+use std::sync::{Arc, Mutex};
+
+use common::{prompt_frame, WideStepper};
+use nsc_frame::tokens::TOKEN_CHUNK_LEN;
+use nsc_frame::{Billing, Driver, Fnv1a64, SinkControl, StepOutcome, TokenLog, TokenSink};
+
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u32>>>);
+
+impl TokenSink for Shared {
+    fn offer(&mut self, token: u32) -> SinkControl {
+        self.0.lock().unwrap().push(token);
+        SinkControl::Continue
+    }
+}
+
+#[test]
+fn multi_token_steps_reach_the_digest_sink_and_invoice() {
+    let sink = Shared::default();
+    let mut d = Driver::new(prompt_frame(0, 9), WideStepper { width: 3 })
+        .with_token_hasher(Fnv1a64::new())
+        .with_sink(sink.clone());
+    let mut billing = Billing::new();
+    loop {
+        let r = d.step().unwrap();
+        billing.record(1, "t", &r);
+        if r.outcome == StepOutcome::Finished {
+            break;
+        }
+    }
+    let output = d.frame.generated_token_ids.to_vec();
+    assert_eq!(output.len(), 9);
+    assert_eq!(d.token_digest(), Some(Fnv1a64::digest_of(output.clone())));
+    assert_eq!(*sink.0.lock().unwrap(), output);
+    assert_eq!(billing.frame_invoice(1).unwrap().tokens_generated, 9);
+}
+
+#[test]
+fn multi_token_steps_spend_credits_and_stay_held_back() {
+    let mut d = Driver::new(prompt_frame(0, 9), WideStepper { width: 3 })
+        .with_flow_control(4)
+        .with_utf8_holdback();
+    d.step().unwrap();
+    let r = d.step().unwrap();
+    assert_eq!(r.tokens_committed, 3);
+    assert_eq!(d.flow_credits(), Some(1));
+    assert_eq!(d.safe_emit_upto(), 0);
+}
+
+#[test]
+fn since_starts_mid_chunk() {
+    let log: TokenLog = (0..(TOKEN_CHUNK_LEN as u32 + 10)).collect();
+    let tail: Vec<u32> = log.since(TOKEN_CHUNK_LEN - 2).collect();
+    assert_eq!(tail.len(), 12);
+    assert_eq!(tail[0], TOKEN_CHUNK_LEN as u32 - 2);
+    assert_eq!(log.since(log.len() + 5).count(), 0);
+}
+
+#[test]
+fn as_slice_borrows_within_one_chunk_and_copies_past_it() {
+    let mut log: TokenLog = (0..TOKEN_CHUNK_LEN as u32).collect();
+    assert!(matches!(log.as_slice(), Cow::Borrowed(_)));
+    assert_eq!(log.as_slice().len(), TOKEN_CHUNK_LEN);
+
+    log.push(7);
+    let slice = log.as_slice();
+    assert!(matches!(slice, Cow::Owned(_)));
+    assert_eq!(slice[TOKEN_CHUNK_LEN], 7);
+    assert_eq!(*slice, log.to_vec()[..]);
+
+    let short = TokenLog::from(vec![1, 2, 3]);
+    assert_eq!(&short.as_slice()[1..], [2, 3]);
+    assert!(TokenLog::new().as_slice().is_empty());
+}
@@ -0,0 +1,160 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper, WideStepper};
+use nsc_frame::protocol::DecodeError;
+use nsc_frame::trace::{bisect, RecordingStepper, Trace};
+use nsc_frame::tracefile::{encode_record, TraceFile, TraceHeader};
+use nsc_frame::{Driver, DriverCommand, FrameSnapshot, FrameStepper, StepOutcome};
+
+fn progress(s: &FrameSnapshot) -> (u32, usize, Vec<u32>, u64, u64) {
+    (
+        s.position,
+        s.prompt_index,
+        s.generated_token_ids.clone(),
+        s.steps_taken,
+        s.cost_spent,
+    )
+}
+
+#[test]
+fn seek_matches_the_driven_frame_at_every_step() {
+    let stepper = RecordingStepper::new(WideStepper { width: 3 }).with_keyframes(3);
+    let mut d = Driver::new(prompt_frame(2, 12), stepper);
+    let mut seen = vec![d.frame.snapshot()];
+    let mut i = 0;
+    loop {
+        if i == 2 {
+            d.enqueue(DriverCommand::Pause);
+        }
+        if i == 4 {
+            d.enqueue(DriverCommand::Resume);
+        }
+        i += 1;
+        let r = d.step().unwrap();
+        if d.stepper.trace.len() == seen.len() {
+            seen.push(d.frame.snapshot());
+        }
+        if r.outcome == StepOutcome::Finished {
+            break;
+        }
+    }
+    let rec = &d.stepper;
+    for (n, expected) in seen.iter().enumerate() {
+        let got = rec.trace.seek(&rec.keyframes, n).unwrap();
+        assert_eq!(progress(&got), progress(expected), "step {}", n);
+    }
+}
+
+#[test]
+fn keyframes_of_a_directly_called_stepper_are_not_adjusted() {
+    let mut rec = RecordingStepper::new(PromptStepper).with_keyframes(1);
+    let mut frame = prompt_frame(3, 2);
+    for _ in 0..4 {
+        rec.step(&mut frame).unwrap();
+    }
+    assert!(rec.keyframes.iter().all(|k| k.snapshot.steps_taken == 0));
+    let got = rec.trace.seek(&rec.keyframes, 2).unwrap();
+    assert_eq!(got.prompt_index, 2);
+    assert_eq!(got.steps_taken, 0);
+}
+
+#[test]
+fn committed_tokens_survive_every_trace_encoding() {
+    let mut d = Driver::new(
+        prompt_frame(1, 6),
+        RecordingStepper::new(WideStepper { width: 3 }),
+    );
+    d.run_to_completion().unwrap();
+    let trace = d.stepper.trace.clone();
+    assert_eq!(trace.get(1).unwrap().committed, [0, 1, 2]);
+
+    assert_eq!(trace.compact().expand().unwrap(), trace);
+    let file = TraceFile::new(TraceHeader::default(), trace.clone());
+    assert_eq!(TraceFile::decode(&file.encode()).unwrap().trace, trace);
+}
+
+fn recorded_trace() -> Trace {
+    let mut d = Driver::new(prompt_frame(2, 3), RecordingStepper::new(PromptStepper));
+    d.run_to_completion().unwrap();
+    d.stepper.trace.clone()
+}
+
+#[test]
+fn trace_files_are_written_record_by_record() {
+    let trace = recorded_trace();
+    let header = TraceHeader { run_id: Some(42) };
+    let mut appended = header.encode();
+    for e in &trace.entries {
+        appended.extend_from_slice(&encode_record(e));
+    }
+    let file = TraceFile::new(header, trace);
+    assert_eq!(appended, file.encode());
+
+    let decoded = TraceFile::decode(&appended).unwrap();
+    assert_eq!(decoded.header.run_id, Some(42));
+    assert_eq!(decoded.trace, file.trace);
+    assert!(decoded.is_complete());
+}
+
+#[test]
+fn a_torn_trace_file_keeps_its_whole_records() {
+    let trace = recorded_trace();
+    let whole = TraceFile::new(TraceHeader::default(), trace.clone()).encode();
+    let last = encode_record(trace.entries.last().unwrap()).len();
+
+    let torn = TraceFile::decode(&whole[..whole.len() - 3]).unwrap();
+    assert_eq!(torn.torn_bytes, last - 3);
+    assert_eq!(torn.trace.entries, trace.entries[..trace.entries.len() - 1]);
+    assert!(!torn.is_complete());
+}
+
+#[test]
+fn damaged_trace_files_are_rejected() {
+    let mut buf = TraceFile::new(TraceHeader::default(), recorded_trace()).encode();
+    let end = buf.len() - 6;
+    buf[end] ^= 0x40;
+    assert_eq!(
+        TraceFile::decode(&buf).unwrap_err(),
+        DecodeError::Malformed("trace record crc")
+    );
+    buf[0] = b'X';
+    assert_eq!(
+        TraceFile::decode(&buf).unwrap_err(),
+        DecodeError::Malformed("trace magic")
+    );
+}
+
+fn run_recorded(max_new_tokens: usize) -> RecordingStepper<PromptStepper> {
+    let stepper = RecordingStepper::new(PromptStepper).with_keyframes(2);
+    let mut d = Driver::new(prompt_frame(2, max_new_tokens), stepper);
+    d.run_to_completion().unwrap();
+    d.stepper
+}
+
+#[test]
+fn bisect_finds_the_first_differing_step_and_the_frame_before_it() {
+    let (left, right) = (run_recorded(3), run_recorded(5));
+    assert_eq!(bisect(&left.trace, &left.trace, &left.keyframes), None);
+
+    let b = bisect(&left.trace, &right.trace, &left.keyframes).unwrap();
+    assert_eq!(b.step_index, 5);
+    assert_eq!(b.left.unwrap().outcome, StepOutcome::Finished);
+    assert_eq!(b.right.unwrap().outcome, StepOutcome::Advanced);
+    let before = b.before.unwrap();
+    assert_eq!(before.generated_token_ids.len(), 3);
+    assert_eq!(before.prompt_index, 2);
+}
+
+#[test]
+fn bisect_reports_a_trace_that_ended_early() {
+    let full = run_recorded(3);
+    let mut short = full.trace.clone();
+    short.entries.truncate(4);
+    let b = bisect(&full.trace, &short, &[]).unwrap();
+    assert_eq!(b.step_index, 4);
+    assert!(b.left.is_some() && b.right.is_none());
+    assert_eq!(b.before, None);
+    assert!(b
+        .to_string()
+        .starts_with("traces differ at step 4\n  before: (no keyframe)"));
+}
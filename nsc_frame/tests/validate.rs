@@ -0,0 +1,115 @@
+mod common;
+
+use common::{prompt_frame, PromptStepper, WideStepper};
+use nsc_frame::command::ADJUST_LIMITS_UNBOUNDED;
+use nsc_frame::validate::LAW_VOCAB;
+use nsc_frame::{
+    Driver, DriverCommand, DynamicLimits, Frame, FrameError, FrameLimits, NoopMem, StepOutcome,
+    StopReason, COMMAND_REJECTED,
+};
+
+fn unbounded_driver() -> Driver<nsc_frame::NoopMem, PromptStepper> {
+    let mut frame = prompt_frame(2, 4);
+    frame.limits = FrameLimits::unbounded();
+    Driver::new(frame, PromptStepper)
+}
+
+#[test]
+fn unbounded_frames_do_not_step_without_a_stop_path() {
+    let mut d = unbounded_driver();
+    assert!(d.step().is_err());
+    assert_eq!(d.frame.steps_taken, 0);
+
+    // A handle nobody kept cannot cancel anything.
+    drop(d.handle());
+    assert!(d.step().is_err());
+
+    let handle = d.handle();
+    assert!(d.step().is_ok());
+    handle.cancel();
+    d.step().unwrap();
+    assert!(handle.is_done());
+}
+
+#[test]
+fn a_finite_dynamic_cap_is_a_stop_path() {
+    let d = unbounded_driver().with_dynamic_limits(DynamicLimits::unlimited());
+    assert!(d.validate().is_err());
+    let d = unbounded_driver().with_dynamic_limits(DynamicLimits::new(8));
+    assert!(d.validate().is_ok());
+}
+
+#[test]
+fn adjust_limits_cannot_unbound_a_running_frame() {
+    let mut d = Driver::new(prompt_frame(2, 4), PromptStepper);
+    d.enqueue(DriverCommand::AdjustLimits(FrameLimits::unbounded()));
+    let r = d.step().unwrap();
+    assert!(r
+        .receipts
+        .iter()
+        .any(|x| x.kind == COMMAND_REJECTED && x.value_u64 == ADJUST_LIMITS_UNBOUNDED));
+    assert_eq!(d.frame.limits.max_new_tokens, Some(4));
+
+    let handle = d.handle();
+    d.enqueue(DriverCommand::AdjustLimits(FrameLimits::unbounded()));
+    let r = d.step().unwrap();
+    assert!(r.receipts.iter().all(|x| x.kind != COMMAND_REJECTED));
+    assert_eq!(d.frame.limits.max_new_tokens, None);
+    drop(handle);
+}
+
+/// Runs `WideStepper { width: 3 }` under `vocab_size` to the end; the second decode
+/// step commits ids 3, 4 and 5.
+fn run_wide(
+    vocab_size: u32,
+) -> (
+    Driver<nsc_frame::NoopMem, WideStepper>,
+    nsc_frame::StepResult,
+) {
+    let frame = prompt_frame(0, 8).with_vocab_size(vocab_size).unwrap();
+    let mut d = Driver::new(frame, WideStepper { width: 3 });
+    loop {
+        let r = d.step().unwrap();
+        if r.outcome == StepOutcome::Finished {
+            return (d, r);
+        }
+    }
+}
+
+#[test]
+fn vocab_law_checks_every_token_of_a_wide_step() {
+    let (d, r) = run_wide(5);
+    assert_eq!(r.stop_reason, Some(StopReason::TokenOutOfVocab(5)));
+    assert_eq!(d.frame.generated_token_ids, vec![0, 1, 2, 3, 4]);
+    assert_eq!(d.frame.tokens_generated, 5);
+    assert_eq!(d.frame.cursor.position, 5);
+    assert_eq!((r.emitted_token, r.tokens_committed), (Some(3), 2));
+    assert!(r
+        .receipts
+        .iter()
+        .any(|x| x.kind == LAW_VOCAB && x.value_u64 == 5));
+}
+
+#[test]
+fn vocab_law_removes_a_bad_first_token_and_the_rest_of_its_step() {
+    let (d, r) = run_wide(3);
+    assert_eq!(r.stop_reason, Some(StopReason::TokenOutOfVocab(3)));
+    assert_eq!(d.frame.generated_token_ids, vec![0, 1, 2]);
+    assert_eq!(d.frame.tokens_generated, 3);
+    assert_eq!(d.frame.cursor.position, 3);
+    assert_eq!((r.emitted_token, r.tokens_committed), (None, 0));
+}
+
+#[test]
+fn resumed_frames_validate_their_limits() {
+    let prompt = vec![1, 2, 3, 4];
+    let frame = Frame::with_prompt_resumed(NoopMem, FrameLimits::new(4), prompt.clone(), 3);
+    let frame = frame.unwrap();
+    assert_eq!((frame.prompt_index, frame.cursor.position), (3, 3));
+    assert_eq!(frame.limits.max_new_tokens, Some(4));
+
+    let zero = Frame::with_prompt_resumed(NoopMem, FrameLimits::new(0), prompt.clone(), 3);
+    assert!(matches!(zero, Err(FrameError::ZeroBudget(_))));
+    let long = Frame::with_prompt_resumed(NoopMem, FrameLimits::new(4), prompt, 5);
+    assert!(matches!(long, Err(FrameError::PrefixExceedsPrompt { .. })));
+}
@@ -0,0 +1,32 @@
+mod common;
+
+use common::prompt_frame;
+use nsc_frame::view::FRAME_VIEW_SUFFIX;
+use nsc_frame::Redaction;
+
+fn frame_with_output(n: u32) -> nsc_frame::Frame<nsc_frame::NoopMem> {
+    let mut frame = prompt_frame(0, n as usize);
+    for i in 0..n {
+        frame.generated_token_ids.push(1000 + i);
+    }
+    frame.tokens_generated = n as usize;
+    frame
+}
+
+#[test]
+fn view_debug_shows_only_the_bounded_suffix() {
+    let frame = frame_with_output(100);
+    let out = format!("{:?}", frame.view());
+    let first_shown = 1000 + 100 - FRAME_VIEW_SUFFIX as u32;
+    assert!(out.contains(&first_shown.to_string()));
+    assert!(out.contains("1099"));
+    assert!(!out.contains(&(first_shown - 1).to_string()));
+}
+
+#[test]
+fn view_debug_follows_the_frame_redaction() {
+    let frame = frame_with_output(100).with_redaction(Redaction::LengthsOnly);
+    let out = format!("{:?}", frame.view());
+    assert!(out.contains(&format!("<{} tokens>", FRAME_VIEW_SUFFIX)));
+    assert!(!out.contains("1099"));
+}
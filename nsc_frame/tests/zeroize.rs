@@ -0,0 +1,45 @@
+mod common;
+
+use common::prompt_frame;
+use nsc_frame::Frame;
+
+#[test]
+fn fields_move_out_of_a_frame_with_any_features() {
+    let frame = prompt_frame(3, 4);
+    let Frame {
+        prompt_token_ids,
+        generated_token_ids,
+        ..
+    } = frame;
+    assert_eq!(prompt_token_ids.len(), 3);
+    assert!(generated_token_ids.is_empty());
+}
+
+#[cfg(feature = "zeroize")]
+#[test]
+fn wipe_runs_the_memory_hook() {
+    use std::sync::atomic::{AtomicBool, Ordering};
+    use std::sync::Arc;
+
+    use nsc_frame::zeroize::ZeroizeMem;
+
+    struct Cache(Arc<AtomicBool>);
+
+    impl ZeroizeMem for Cache {
+        fn zeroize(&mut self) {
+            self.0.store(true, Ordering::SeqCst);
+        }
+    }
+
+    let wiped = Arc::new(AtomicBool::new(false));
+    let mut frame = Frame::new(Cache(wiped.clone()), 4).with_mem_zeroize();
+    frame.prompt_token_ids = vec![1, 2, 3];
+    frame.generated_token_ids.push(4);
+    frame.zeroize();
+    assert!(frame.prompt_token_ids.is_empty());
+    assert!(frame.generated_token_ids.is_empty());
+    assert!(wiped.swap(false, Ordering::SeqCst));
+
+    frame.wipe();
+    assert!(wiped.load(Ordering::SeqCst));
+}